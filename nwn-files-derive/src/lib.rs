@@ -0,0 +1,156 @@
+//! `#[derive(X2daRow)]` for structs of `Option<T>` fields, see
+//! `nwn_files::types::X2daRow`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input,
+    Data,
+    DeriveInput,
+    Fields,
+    GenericArgument,
+    LitStr,
+    PathArguments,
+    Type,
+};
+
+/// Columns are taken from the field names in order, `#[x2da(rename = "Label")]`
+/// overrides a single column name. Every field has to be an `Option<T>` where
+/// `T` implements `X2daItem` and `FromStr`.
+#[proc_macro_derive(X2daRow, attributes(x2da))]
+pub fn derive_x2da_row(input: TokenStream)
+    -> TokenStream
+{
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct RowField {
+    ident: syn::Ident,
+    inner_type: Type,
+    column: String,
+}
+
+fn expand(input: DeriveInput)
+    -> syn::Result<TokenStream2>
+{
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(
+                &input.ident,
+                "X2daRow can only be derived for structs with named fields",
+            )),
+        },
+        _ => return Err(syn::Error::new_spanned(
+            &input.ident,
+            "X2daRow can only be derived for structs",
+        )),
+    };
+
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let ident = field.ident.clone().unwrap();
+
+            Ok(RowField {
+                inner_type: option_inner_type(&field.ty)?,
+                column: column_name(field)?.unwrap_or_else(|| ident.to_string()),
+                ident,
+            })
+        })
+        .collect::<syn::Result<Vec<RowField>>>()?;
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let size = fields.len();
+    let columns = fields.iter().map(|f| &f.column);
+    let idents = fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+    let types = fields.iter().map(|f| &f.inner_type);
+
+    Ok(quote! {
+        impl #impl_generics ::nwn_files::types::X2daRow for #name #type_generics #where_clause {
+            const SIZE: usize = #size;
+
+            type Row = [Option<Box<dyn ::nwn_files::types::X2daItem>>; #size];
+
+            fn columns()
+                -> Option<Vec<String>>
+            {
+                Some(vec![#(String::from(#columns)),*])
+            }
+
+            fn to_row(&self)
+                -> Self::Row
+            {
+                [
+                    #(self.#idents.to_owned().map(::nwn_files::types::X2daItem::boxed)),*
+                ]
+            }
+
+            fn from_strings(strings: Vec<Option<String>>)
+                -> Result<Self, ::nwn_files::types::X2daError>
+            {
+                let mut strings = strings.into_iter();
+
+                Ok(#name {
+                    #(
+                        #idents: strings
+                            .next()
+                            .flatten()
+                            .map(|v| v.parse::<#types>())
+                            .transpose()
+                            .or(Err(::nwn_files::types::X2daError::InvalidTableItem))?,
+                    )*
+                })
+            }
+        }
+    })
+}
+
+fn option_inner_type(ty: &Type)
+    -> syn::Result<Type>
+{
+    let error = || syn::Error::new_spanned(ty, "X2daRow fields have to be Option<T>");
+
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last().ok_or_else(error)?,
+        _ => return Err(error()),
+    };
+
+    if segment.ident != "Option" {
+        return Err(error());
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(inner) => Ok(inner.clone()),
+            _ => Err(error()),
+        },
+        _ => Err(error()),
+    }
+}
+
+fn column_name(field: &syn::Field)
+    -> syn::Result<Option<String>>
+{
+    let mut rename = None;
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("x2da")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("unknown x2da attribute, expected `rename`"))
+            }
+        })?;
+    }
+
+    Ok(rename)
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+/// Positional arguments plus `--option value` pairs.
+pub struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I)
+        -> Self
+    {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(option) => {
+                    let value = args.next().unwrap_or_default();
+                    options.insert(option.to_owned(), value);
+                },
+                None => positional.push(arg),
+            }
+        }
+
+        Args {
+            positional,
+            options,
+        }
+    }
+
+    pub fn command(&self)
+        -> Option<&str>
+    {
+        self.positional.first().map(String::as_str)
+    }
+
+    pub fn positional(&self, index: usize)
+        -> Result<&str, String>
+    {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| format!("Missing argument {}.", index))
+    }
+
+    pub fn option(&self, name: &str)
+        -> Option<&str>
+    {
+        self.options.get(name).map(String::as_str)
+    }
+}
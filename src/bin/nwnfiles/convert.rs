@@ -0,0 +1,380 @@
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+
+use nwn_files::{Dynamic2da, SsfBuilder, SsfFile, TlkFile, TlkTranslation};
+use nwn_files::types::{
+    Encoding,
+    LanguageId,
+    ResRef,
+    SsfEntry,
+    StrRef,
+    TlkEntry,
+    TlkSound,
+    TranslationFormat,
+    TranslationMerge,
+    Version,
+};
+
+use super::args::Args;
+use super::{extension, CliResult};
+
+const TLK_HEADER: &str = "# tlk";
+const NONE: &str = "****";
+
+pub fn run(args: &Args)
+    -> CliResult
+{
+    let input = args.positional(1)?;
+    let output = args.positional(2)?;
+
+    match (extension(input).as_str(), extension(output).as_str()) {
+        ("tlk", "txt") => tlk_to_text(input, output, encoding(args)?),
+        ("txt", "tlk") => text_to_tlk(input, output, encoding(args)?),
+        ("ssf", "txt") => ssf_to_text(input, output),
+        ("txt", "ssf") => text_to_ssf(input, output),
+        ("2da", "txt") => x2da_to_text(input, output),
+        ("txt", "2da") => text_to_x2da(input, output),
+        ("2da", "2da") => reformat_x2da(input, output, args.option("format")),
+        ("tlk", to) if TranslationFormat::from_extension(to).is_some() =>
+            tlk_to_translation(input, output, args),
+        (from, "tlk") if TranslationFormat::from_extension(from).is_some() =>
+            translation_to_tlk(input, output, args),
+        (from, to) => Err(format!("Can't convert <.{}> to <.{}>.", from, to).into()),
+    }
+}
+
+/// `--encoding` takes any WHATWG label, `windows-1251` for example.
+fn encoding(args: &Args)
+    -> Result<Option<&'static Encoding>, String>
+{
+    match args.option("encoding") {
+        Some(label) => Encoding::for_label(label.as_bytes())
+            .map(Some)
+            .ok_or_else(|| format!("Unknown encoding <{}>.", label)),
+        None => Ok(None),
+    }
+}
+
+fn escape(s: &str)
+    -> String
+{
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+fn unescape(s: &str)
+    -> String
+{
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+fn line_error(line: usize, message: &str)
+    -> Box<dyn std::error::Error>
+{
+    format!("Line {}: {}", line + 1, message).into()
+}
+
+/// Tlk text is a header line followed by one line per entry:
+/// `strref <tab> sound resref <tab> sound length <tab> text`. StrRefs
+/// missing from the text are written as empty entries.
+fn tlk_to_text(input: &str, output: &str, encoding: Option<&'static Encoding>)
+    -> CliResult
+{
+    let tlk = read_tlk(input, encoding)?;
+    let mut writer = BufWriter::new(File::create(output)?);
+
+    writeln!(writer, "{} {}", TLK_HEADER, tlk.language_id as u32)?;
+
+    for (i, entry) in tlk.iter() {
+        let (res_ref, sound_length) = match &entry.sound {
+            Some(sound) => (
+                sound.res_ref.to_string(),
+                sound.sound_length.map(|l| l.to_string()).unwrap_or_default(),
+            ),
+            None => (String::new(), String::new()),
+        };
+
+        writeln!(writer, "{}\t{}\t{}\t{}", i, res_ref, sound_length, escape(&entry.string))?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn text_to_tlk(input: &str, output: &str, encoding: Option<&'static Encoding>)
+    -> CliResult
+{
+    let text = fs::read_to_string(input)?;
+    let mut lines = text.lines().enumerate();
+
+    let language_id = match lines.next() {
+        Some((_, line)) if line.starts_with(TLK_HEADER) => line[TLK_HEADER.len()..]
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| line_error(0, "Invalid language id."))?,
+        _ => return Err(line_error(0, "Expected a <# tlk> header.")),
+    };
+
+    let mut tlk = TlkFile::new();
+    tlk.language_id = LanguageId::from(language_id);
+    tlk.encoding = encoding;
+    tlk.alternative = false;
+
+    for (i, line) in lines {
+        let mut fields = line.splitn(4, '\t');
+
+        let strref = fields.next().unwrap_or_default();
+        let (res_ref, sound_length, string) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(r), Some(l), Some(s)) => (r, l, s),
+            _ => return Err(line_error(i, "Expected 4 tab separated fields.")),
+        };
+
+        let strref = match strref.parse::<u32>() {
+            Ok(strref) if tlk.entry(strref as usize).is_none() => StrRef(strref),
+            _ => return Err(line_error(i, "StrRefs must be unique numbers.")),
+        };
+
+        let sound = if res_ref.is_empty() {
+            None
+        } else {
+            let sound_length = if sound_length.is_empty() {
+                None
+            } else {
+                Some(sound_length.parse::<f32>().map_err(|_| line_error(i, "Invalid sound length."))?)
+            };
+
+            Some(TlkSound {
+                res_ref: ResRef::try_from(res_ref).map_err(|e| line_error(i, &e.to_string()))?,
+                sound_length,
+            })
+        };
+
+        tlk.set(strref, TlkEntry {
+            string: unescape(string),
+            sound,
+        }).map_err(|e| line_error(i, &e.to_string()))?;
+    }
+
+    tlk.write(&mut BufWriter::new(File::create(output)?))?;
+
+    Ok(())
+}
+
+fn read_tlk(path: &str, encoding: Option<&'static Encoding>)
+    -> Result<TlkFile, Box<dyn std::error::Error>>
+{
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let tlk = match encoding {
+        Some(encoding) => TlkFile::parse_with_encoding(&mut reader, false, encoding)?,
+        None => TlkFile::parse_from(&mut reader, false)?,
+    };
+
+    Ok(tlk)
+}
+
+/// `--target` names an already translated tlk whose strings become the
+/// translations.
+fn tlk_to_translation(input: &str, output: &str, args: &Args)
+    -> CliResult
+{
+    let format = TranslationFormat::from_extension(&extension(output))
+        .ok_or("Unknown translation format.")?;
+
+    let source = read_tlk(input, encoding(args)?)?;
+
+    let target = match args.option("target") {
+        Some(path) => Some(read_tlk(path, None)?),
+        None => None,
+    };
+
+    let translation = TlkTranslation::from_tlk(&source, target.as_ref());
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    translation.write(&mut writer, format)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Without `--into` untranslated strings keep their source text. With it
+/// the translations are merged into that tlk, `--merge untranslated` only
+/// fills its missing or empty strings.
+fn translation_to_tlk(input: &str, output: &str, args: &Args)
+    -> CliResult
+{
+    let format = TranslationFormat::from_extension(&extension(input))
+        .ok_or("Unknown translation format.")?;
+
+    let translation = TlkTranslation::parse_from(&mut BufReader::new(File::open(input)?), format)?;
+
+    let merge = match args.option("merge") {
+        None | Some("all") => TranslationMerge::All,
+        Some("untranslated") => TranslationMerge::Untranslated,
+        Some(other) => return Err(format!("Unknown merge mode <{}>.", other).into()),
+    };
+
+    let mut tlk = match args.option("into") {
+        Some(path) => {
+            let mut tlk = read_tlk(path, encoding(args)?)?;
+            translation.apply(&mut tlk, merge);
+            tlk
+        },
+        None => translation.to_tlk(),
+    };
+
+    tlk.encoding = encoding(args)?;
+    tlk.write(&mut BufWriter::new(File::create(output)?))?;
+
+    Ok(())
+}
+
+/// Ssf text is one `resref <tab> strref` line per slot, `****` marks empty
+/// values.
+fn ssf_to_text(input: &str, output: &str)
+    -> CliResult
+{
+    let ssf = SsfFile::parse_from(&mut File::open(input)?)?;
+    let mut writer = BufWriter::new(File::create(output)?);
+
+    for entry in ssf.entries.iter() {
+        let res_ref = if entry.res_ref.is_empty() { NONE.to_owned() } else { entry.res_ref.to_string() };
+        let string_ref = entry.string_ref.map(|s| s.to_string()).unwrap_or_else(|| NONE.to_owned());
+
+        writeln!(writer, "{}\t{}", res_ref, string_ref)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn text_to_ssf(input: &str, output: &str)
+    -> CliResult
+{
+    let text = fs::read_to_string(input)?;
+    let mut builder = SsfBuilder::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let mut fields = line.split('\t');
+
+        let (res_ref, string_ref) = match (fields.next(), fields.next()) {
+            (Some(r), Some(s)) => (r.trim(), s.trim()),
+            _ => return Err(line_error(i, "Expected 2 tab separated fields.")),
+        };
+
+        let res_ref = match res_ref {
+            NONE => ResRef::default(),
+            r => ResRef::try_from(r).map_err(|e| line_error(i, &e.to_string()))?,
+        };
+
+        let string_ref = match string_ref {
+            NONE => None,
+            s => Some(StrRef(s.parse::<u32>().map_err(|_| line_error(i, "Invalid StrRef."))?)),
+        };
+
+        builder.add_entry(SsfEntry {
+            res_ref,
+            string_ref,
+        });
+    }
+
+    builder.write(&mut File::create(output)?)?;
+
+    Ok(())
+}
+
+/// 2da text is the column names followed by one line per row, all tab
+/// separated, `****` marks empty cells.
+fn x2da_to_text(input: &str, output: &str)
+    -> CliResult
+{
+    let x2da = Dynamic2da::parse_from(&mut BufReader::new(File::open(input)?))?;
+    let mut writer = BufWriter::new(File::create(output)?);
+
+    writeln!(writer, "{}", x2da.columns.join("\t"))?;
+
+    for row in x2da.rows.iter() {
+        let cells = row
+            .iter()
+            .map(|c| c.as_deref().unwrap_or(NONE))
+            .collect::<Vec<&str>>();
+
+        writeln!(writer, "{}", cells.join("\t"))?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn text_to_x2da(input: &str, output: &str)
+    -> CliResult
+{
+    let text = fs::read_to_string(input)?;
+    let mut lines = text.lines().enumerate();
+
+    let columns = match lines.next() {
+        Some((_, line)) => line.split('\t').map(String::from).collect(),
+        None => return Err(line_error(0, "Expected the column names.")),
+    };
+
+    let mut x2da = Dynamic2da::new(columns);
+
+    for (i, line) in lines.filter(|(_, l)| !l.is_empty()) {
+        let row = line
+            .split('\t')
+            .map(|c| match c {
+                NONE => None,
+                c => Some(c.to_owned()),
+            })
+            .collect();
+
+        x2da.add_row(row).map_err(|e| line_error(i, &e.to_string()))?;
+    }
+
+    x2da.write(&mut File::create(output)?)?;
+
+    Ok(())
+}
+
+/// Keeps the input's layout unless `--format text` or `--format binary` is
+/// given.
+fn reformat_x2da(input: &str, output: &str, format: Option<&str>)
+    -> CliResult
+{
+    let mut x2da = Dynamic2da::parse_from(&mut BufReader::new(File::open(input)?))?;
+
+    match format {
+        Some("text") => x2da.header.version = Version::V2,
+        Some("binary") => x2da.header.version = Version::V2B,
+        Some(other) => return Err(format!("Unknown 2da format <{}>, use text or binary.", other).into()),
+        None => {},
+    }
+
+    x2da.write(&mut File::create(output)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use nwn_files::{Dynamic2da, TlkDiff, TlkFile, X2daDiff};
+
+use super::args::Args;
+use super::{extension, CliResult};
+
+pub fn run(args: &Args)
+    -> CliResult
+{
+    let old = args.positional(1)?;
+    let new = args.positional(2)?;
+    let json = match args.option("format") {
+        Some("json") => true,
+        Some("text") | None => false,
+        Some(other) => return Err(format!("Unknown diff format <{}>, use text or json.", other).into()),
+    };
+
+    match (extension(old).as_str(), extension(new).as_str()) {
+        ("2da", "2da") => diff_x2da(old, new, json),
+        ("tlk", "tlk") if !json => diff_tlk(old, new),
+        ("tlk", "tlk") => Err("Tlk diffs are only available as text.".into()),
+        (a, b) => Err(format!("Can't diff <.{}> with <.{}>.", a, b).into()),
+    }
+}
+
+fn diff_x2da(old: &str, new: &str, json: bool)
+    -> CliResult
+{
+    let old = Dynamic2da::parse_from(&mut BufReader::new(File::open(old)?))?;
+    let new = Dynamic2da::parse_from(&mut BufReader::new(File::open(new)?))?;
+
+    let diff = X2daDiff::new(&old, &new);
+
+    if json {
+        println!("{}", diff.to_json());
+    } else {
+        print!("{}", diff);
+    }
+
+    Ok(())
+}
+
+/// Both tlks are read as custom tlks, so StrRefs carry the custom flag
+/// the way haks reference them.
+fn diff_tlk(old: &str, new: &str)
+    -> CliResult
+{
+    let old = TlkFile::parse_from(&mut BufReader::new(File::open(old)?), true)?;
+    let new = TlkFile::parse_from(&mut BufReader::new(File::open(new)?), true)?;
+
+    print!("{}", TlkDiff::new(&old, &new));
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use nwn_files::{BifReader, ErfReader, ResMan, resource_file_name};
+use nwn_files::types::ResourceType;
+
+use super::args::Args;
+use super::{extension, CliResult};
+
+/// The `--name` and `--type` options, matching everything when missing.
+struct Filter {
+    name: Option<String>,
+    resource_type: Option<ResourceType>,
+}
+
+impl Filter {
+    fn from_args(args: &Args)
+        -> Result<Self, String>
+    {
+        let resource_type = match args.option("type") {
+            Some(t) => Some(
+                ResourceType::from_extension(t)
+                    .ok_or_else(|| format!("<{}> isn't a known resource type.", t))?
+            ),
+            None => None,
+        };
+
+        Ok(Filter {
+            name: args.option("name").map(str::to_lowercase),
+            resource_type,
+        })
+    }
+
+    fn matches(&self, name: &str, resource_type: &ResourceType)
+        -> bool
+    {
+        self.name.as_ref().map(|n| n.eq_ignore_ascii_case(name)).unwrap_or(true)
+            && self.resource_type.as_ref().map(|t| t == resource_type).unwrap_or(true)
+    }
+}
+
+pub fn run(args: &Args)
+    -> CliResult
+{
+    let path = args.positional(1)?;
+    let dir = Path::new(args.positional(2)?);
+    let filter = Filter::from_args(args)?;
+
+    fs::create_dir_all(dir)?;
+
+    let count = match extension(path).as_str() {
+        "key" => extract_key(path, dir, &filter, args.option("root"))?,
+        "bif" => extract_bif(path, dir, &filter)?,
+        "erf" | "hak" | "mod" | "sav" => extract_erf(path, dir, &filter)?,
+        other => return Err(format!("Can't extract from <.{}> files.", other).into()),
+    };
+
+    println!("Extracted {} resources to {}", count, dir.display());
+
+    Ok(())
+}
+
+fn extract_key(path: &str, dir: &Path, filter: &Filter, root: Option<&str>)
+    -> Result<usize, Box<dyn std::error::Error>>
+{
+    let root = match root {
+        Some(root) => Path::new(root).to_path_buf(),
+        None => Path::new(path).parent().unwrap_or_else(|| Path::new(".")).to_path_buf(),
+    };
+
+    let mut resman = ResMan::new();
+    resman.mount_key(path, root)?;
+
+    let entries = resman
+        .list()
+        .into_iter()
+        .filter(|e| filter.matches(&e.name, &e.resource_type))
+        .collect::<Vec<_>>();
+
+    for e in entries.iter() {
+        let resource = resman
+            .try_get(&e.name, e.resource_type.clone())?
+            .ok_or_else(|| format!("<{}> couldn't be read.", *e.name))?;
+
+        fs::write(dir.join(resource_file_name(&e.name, &e.resource_type)?), &resource.data)?;
+    }
+
+    Ok(entries.len())
+}
+
+/// Bifs don't store names, resources are written as `index.ext`.
+fn extract_bif(path: &str, dir: &Path, filter: &Filter)
+    -> Result<usize, Box<dyn std::error::Error>>
+{
+    if filter.name.is_some() {
+        return Err("Bifs don't store names, use the key to extract by name.".into());
+    }
+
+    let mut reader = BifReader::open(path)?;
+    let mut count = 0;
+
+    for i in 0..reader.len() {
+        let resource_type = reader.resource_type(i).unwrap();
+
+        if filter.matches("", &resource_type) {
+            let resource = reader.read(i)?;
+            fs::write(dir.join(resource_file_name(&i.to_string(), &resource_type)?), &resource.bytes)?;
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+fn extract_erf(path: &str, dir: &Path, filter: &Filter)
+    -> Result<usize, Box<dyn std::error::Error>>
+{
+    let mut reader = ErfReader::new(File::open(path)?)?;
+
+    let indices = reader
+        .iter()
+        .enumerate()
+        .filter(|(_, (key, _))| filter.matches(&key.file_name, &key.resource_type))
+        .map(|(i, _)| i)
+        .collect::<Vec<usize>>();
+
+    for i in indices.iter() {
+        let resource = reader.read(*i)?;
+
+        fs::write(dir.join(resource_file_name(&resource.name, &resource.resource_type)?), &resource.data)?;
+    }
+
+    Ok(indices.len())
+}
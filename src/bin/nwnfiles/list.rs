@@ -0,0 +1,69 @@
+use std::fs::File;
+
+use nwn_files::{BifReader, ErfReader};
+
+use super::args::Args;
+use super::{extension, CliResult};
+
+pub fn run(args: &Args)
+    -> CliResult
+{
+    let path = args.positional(1)?;
+
+    match extension(path).as_str() {
+        "key" => list_key(path),
+        "bif" => list_bif(path),
+        "erf" | "hak" | "mod" | "sav" => list_erf(path),
+        other => Err(format!("Can't list <.{}> files.", other).into()),
+    }
+}
+
+fn type_name(resource_type: &nwn_files::types::ResourceType)
+    -> String
+{
+    resource_type
+        .extension()
+        .map(String::from)
+        .unwrap_or_else(|| resource_type.id().to_string())
+}
+
+fn list_key(path: &str)
+    -> CliResult
+{
+    let key = nwn_files::parse_key(path)?;
+
+    for bif in key.0.iter() {
+        println!("{}", bif.name);
+
+        for r in bif.resources.iter() {
+            println!("    {:<24} {:>6}", format!("{}.{}", r.name, type_name(&r.resource_type)), r.resource_index);
+        }
+    }
+
+    Ok(())
+}
+
+fn list_bif(path: &str)
+    -> CliResult
+{
+    let reader = BifReader::open(path)?;
+
+    for (i, vr) in reader.variable_resources.iter().enumerate() {
+        let resource_type = reader.resource_type(i).unwrap();
+        println!("{:>6} {:<6} {:>10}", i, type_name(&resource_type), vr.resource_size);
+    }
+
+    Ok(())
+}
+
+fn list_erf(path: &str)
+    -> CliResult
+{
+    let reader = ErfReader::new(File::open(path)?)?;
+
+    for (key, item) in reader.iter() {
+        println!("{:<36} {:>10}", format!("{}.{}", *key.file_name, type_name(&key.resource_type)), item.size);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+mod args;
+mod convert;
+mod diff;
+mod extract;
+mod list;
+
+use std::error::Error;
+use std::path::Path;
+use std::process;
+
+use nwn_files::ErfFile;
+
+use args::Args;
+
+const USAGE: &str = "\
+Usage:
+    nwnfiles list <file.key|.bif|.erf|.hak|.mod|.sav>
+    nwnfiles extract <file.key|.bif|.erf|.hak|.mod|.sav> <directory>
+        [--name <resref>] [--type <extension>] [--root <install directory>]
+    nwnfiles pack <directory> <output.erf|.hak|.mod|.sav>
+    nwnfiles convert <input> <output>
+        .tlk, .ssf and .2da files convert to and from .txt,
+        [--encoding <label>] overrides the code page of a .tlk's language,
+        .tlk converts to and from .po, .xlf and .csv translations,
+        [--target <translated.tlk>] fills in existing translations,
+        [--into <translated.tlk>] [--merge all|untranslated] merges
+        translations into an existing tlk instead of building a new one,
+        .2da to .2da rewrites the table, [--format text|binary] switches
+        between the text and the binary V2.b layout
+    nwnfiles diff <old.2da> <new.2da> [--format text|json]
+    nwnfiles diff <old.tlk> <new.tlk>";
+
+pub type CliResult = Result<(), Box<dyn Error>>;
+
+/// The lowercase extension of a path, empty if it has none.
+pub fn extension<P: AsRef<Path>>(path: P)
+    -> String
+{
+    path.as_ref()
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn pack(args: &Args)
+    -> CliResult
+{
+    let dir = args.positional(1)?;
+    let output = args.positional(2)?;
+
+    let count = ErfFile::pack_directory(dir, output)?;
+
+    println!("Packed {} resources into {}", count, output);
+
+    Ok(())
+}
+
+fn main()
+{
+    let args = Args::parse(std::env::args().skip(1));
+
+    let result = match args.command() {
+        Some("list") => list::run(&args),
+        Some("extract") => extract::run(&args),
+        Some("pack") => pack(&args),
+        Some("convert") => convert::run(&args),
+        Some("diff") => diff::run(&args),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("nwnfiles: {}", e);
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
@@ -1,7 +1,23 @@
 use crate::types::*;
+use crate::types::{
+    Resource as NamedResource,
+    Error as MyError,
+};
 use crate::helpers::conversion::*;
+use crate::helpers::reader::ReaderExt;
+use std::convert::TryFrom;
 use std::path::Path;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write, BufReader, BufWriter};
 use crate::helpers::file::{ read_file_to_vec };
+use super::key::{
+    BifFile as KeyBifFile,
+    BifResource,
+    MAX_RESOURCE_INDEX,
+    MAX_BIF_INDEX,
+};
+
+const BIF_INDEX_SHIFT: u32 = 20;
 
 #[derive(Debug)]
 pub struct BifHeader {
@@ -35,58 +51,540 @@ pub struct Resource {
     pub resource_type: ResourceType,
 }
 
-pub fn parse<P: AsRef<Path>>(file_path: P) -> std::io::Result<BifFile2>  {
+pub fn parse<P: AsRef<Path>>(file_path: P) -> Result<BifFile2, MyError>  {
     let bytes = read_file_to_vec(file_path)?;
+    parse_bytes(bytes)
+}
 
-    let header = parse_header(&bytes);
+pub fn parse_bytes(bytes: Vec<u8>) -> Result<BifFile2, MyError> {
+    let header = parse_header(&bytes)?;
     // dbg!("{:?}", &header);
     
-    let variable_resources = parse_variable_resources(&bytes, &header);
+    let variable_resources = parse_variable_resources(
+        &bytes,
+        header.variable_table_offset as usize,
+        header.variable_resource_count as usize,
+    )?;
     // dbg!("{:?}", &variable_resources);
 
     let resources = variable_resources
         .into_iter()
         .map(|vr| {
             let o = vr.resource_offset as usize;
-            let bytes = Vec::from(&bytes[o..o+vr.resource_size as usize]);
+            let bytes = Vec::from(checked_slice(&bytes, o, vr.resource_size as usize)?);
             
-            Resource {
+            Ok(Resource {
                 bytes: bytes,
                 resource_type: ResourceType::from(vr.resource_type),
-            }
+            })
         })
-        .collect();
+        .collect::<Result<Vec<Resource>, MyError>>()?;
 
     Ok(BifFile2(resources))
 }
 
-fn parse_header(bytes: &Vec<u8>) -> BifHeader {
-    BifHeader {
+fn parse_header(bytes: &[u8]) -> Result<BifHeader, MyError> {
+    let file_type = String::from_utf8_lossy(checked_slice(bytes, 0, 4)?);
+    let file_version = String::from_utf8_lossy(checked_slice(bytes, 4, 4)?);
+
+    if FileType::from(file_type.as_ref()) != FileType::Bif {
+        return Err(MyError::BadMagic(FileType::Bif, file_type.into_owned()));
+    }
+
+    if Version::from(file_version.as_ref()) != Version::V1 {
+        return Err(MyError::UnsupportedVersion(FileType::Bif, file_version.into_owned()));
+    }
+
+    Ok(BifHeader {
         file_type: FileType::Bif,
         version: Version::V1,
-        variable_resource_count: u32_from_bytes(&bytes[8..12]),
-        fixed_resource_count: u32_from_bytes(&bytes[12..16]),
-        variable_table_offset: u32_from_bytes(&bytes[16..20]),
-    }
+        variable_resource_count: checked_u32_from_bytes(bytes, 8)?,
+        fixed_resource_count: checked_u32_from_bytes(bytes, 12)?,
+        variable_table_offset: checked_u32_from_bytes(bytes, 16)?,
+    })
 }
 
-fn parse_variable_resources(bytes: &Vec<u8>, header: &BifHeader) -> Vec<VariableResource> {
-    const VARIABLE_RESOURCE_SIZE: usize = 16;
-    let index_start = header.variable_table_offset as usize;
-    let resource_count = header.variable_resource_count as usize;
-
+fn parse_variable_resources(bytes: &[u8], index_start: usize, resource_count: usize)
+    -> Result<Vec<VariableResource>, MyError>
+{
     (0..resource_count)
-        .into_iter()
         .map(|i| {
-            let index = index_start + VARIABLE_RESOURCE_SIZE * i;
-
-            VariableResource {
-                id: u32_from_bytes(&bytes[index..index + 4]),
-                resource_offset: u32_from_bytes(&bytes[index+4..index+8]),
-                resource_size: u32_from_bytes(&bytes[index+8..index+12]),
-                resource_type: u32_from_bytes(&bytes[index+12..index+16]),
-            }
+            let index = index_start + VariableResource::BYTE_SIZE * i;
+
+            Ok(VariableResource {
+                id: checked_u32_from_bytes(bytes, index)?,
+                resource_offset: checked_u32_from_bytes(bytes, index + 4)?,
+                resource_size: checked_u32_from_bytes(bytes, index + 8)?,
+                resource_type: checked_u32_from_bytes(bytes, index + 12)?,
+            })
         })
         .collect()
 }
 
+/// Reads a bif's variable resource table up front and the resources
+/// themselves only when asked for.
+#[derive(Debug)]
+pub struct BifReader<R: Read + Seek> {
+    reader: R,
+    stream_length: usize,
+    pub header: BifHeader,
+    pub variable_resources: Vec<VariableResource>,
+}
+
+impl BifReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(file_path: P)
+        -> Result<Self, MyError>
+    {
+        let f = File::open(file_path)?;
+        BifReader::new(BufReader::new(f))
+    }
+}
+
+impl<R: Read + Seek> BifReader<R> {
+    pub fn new(mut reader: R)
+        -> Result<Self, MyError>
+    {
+        let stream_length = reader.seek(SeekFrom::End(0))? as usize;
+
+        reader.seek_from_start(0)?;
+        let header_bytes = reader.read_bytes(BifHeader::BYTE_SIZE.min(stream_length))?;
+        let header = parse_header(&header_bytes)?;
+
+        let table_offset = header.variable_table_offset as usize;
+        let table_size = header.variable_resource_count as usize * VariableResource::BYTE_SIZE;
+
+        if table_offset + table_size > stream_length {
+            return Err(MyError::OffsetOutOfBounds(table_offset));
+        }
+
+        reader.seek_from_start(table_offset as u64)?;
+        let table_bytes = reader.read_bytes(table_size)?;
+
+        let variable_resources = parse_variable_resources(
+            &table_bytes,
+            0,
+            header.variable_resource_count as usize,
+        )?;
+
+        Ok(BifReader {
+            reader,
+            stream_length,
+            header,
+            variable_resources,
+        })
+    }
+
+    pub fn len(&self)
+        -> usize
+    {
+        self.variable_resources.len()
+    }
+
+    pub fn is_empty(&self)
+        -> bool
+    {
+        self.variable_resources.is_empty()
+    }
+
+    pub fn resource_type(&self, index: usize)
+        -> Option<ResourceType>
+    {
+        self.variable_resources
+            .get(index)
+            .map(|vr| ResourceType::from(vr.resource_type))
+    }
+
+    /// Reads the resource at `index` of the variable resource table, the
+    /// same index a key's `BifResource::resource_index` refers to.
+    pub fn read(&mut self, index: usize)
+        -> Result<Resource, MyError>
+    {
+        let vr = self.variable_resources
+            .get(index)
+            .ok_or(MyError::InvalidResourceIndex(index))?;
+
+        let offset = vr.resource_offset as usize;
+
+        if offset + vr.resource_size as usize > self.stream_length {
+            return Err(MyError::OffsetOutOfBounds(offset));
+        }
+
+        self.reader.seek_from_start(offset as u64)?;
+
+        let bytes = self.reader
+            .read_bytes(vr.resource_size as usize)
+            .or(Err(MyError::OffsetOutOfBounds(offset)))?;
+
+        Ok(Resource {
+            bytes,
+            resource_type: ResourceType::from(vr.resource_type),
+        })
+    }
+
+    pub fn into_inner(self)
+        -> R
+    {
+        self.reader
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl BifReader<std::io::Cursor<memmap2::Mmap>> {
+    /// Maps the whole bif into memory so resources can be borrowed without
+    /// copying them.
+    pub fn open_mmap<P: AsRef<Path>>(file_path: P)
+        -> Result<Self, MyError>
+    {
+        let f = File::open(file_path)?;
+
+        // Safety: the mapping is read only. Like every memory mapped file it
+        // is undefined behaviour if another process truncates the bif while
+        // it is mapped.
+        let map = unsafe { memmap2::Mmap::map(&f)? };
+
+        BifReader::new(std::io::Cursor::new(map))
+    }
+
+    pub fn bytes(&self, index: usize)
+        -> Result<&[u8], MyError>
+    {
+        let vr = self.variable_resources
+            .get(index)
+            .ok_or(MyError::InvalidResourceIndex(index))?;
+
+        checked_slice(
+            self.reader.get_ref(),
+            vr.resource_offset as usize,
+            vr.resource_size as usize,
+        )
+    }
+}
+
+impl StaticByteSize for BifHeader {
+    const BYTE_SIZE: usize = 20;
+}
+
+impl SerializeToBytes for BifHeader {
+    fn serialize_to<F: Write>(self, writer: &mut F)
+        -> Result<(), MyError>
+    {
+        writer.write_all(self.file_type.as_str_ref().as_bytes())?;
+        writer.write_all(self.version.as_short_str_ref().as_bytes())?;
+        writer.write_all(&self.variable_resource_count.to_le_bytes())?;
+        writer.write_all(&self.fixed_resource_count.to_le_bytes())?;
+        writer.write_all(&self.variable_table_offset.to_le_bytes())?;
+
+        Ok(())
+    }
+}
+
+impl StaticByteSize for VariableResource {
+    const BYTE_SIZE: usize = 16;
+}
+
+impl SerializeToBytes for VariableResource {
+    fn serialize_to<F: Write>(self, writer: &mut F)
+        -> Result<(), MyError>
+    {
+        writer.write_all(&self.id.to_le_bytes())?;
+        writer.write_all(&self.resource_offset.to_le_bytes())?;
+        writer.write_all(&self.resource_size.to_le_bytes())?;
+        writer.write_all(&self.resource_type.to_le_bytes())?;
+
+        Ok(())
+    }
+}
+
+pub struct BifBuilder {
+    bif_index: usize,
+    resources: Vec<NamedResource>,
+}
+
+impl BifBuilder {
+    pub fn new() -> Self
+    {
+        BifBuilder {
+            bif_index: 0,
+            resources: Vec::new(),
+        }
+    }
+
+    /// The position of this bif in the key's file table, used for the upper
+    /// 12 bits of every resource id. `write` fails if it doesn't fit.
+    pub fn set_bif_index(&mut self, bif_index: usize)
+        -> &mut Self
+    {
+        self.bif_index = bif_index;
+        self
+    }
+
+    pub fn add_resource(&mut self, resource: NamedResource)
+        -> &mut Self
+    {
+        self.resources.push(resource);
+        self
+    }
+
+    pub fn add_resources(&mut self, resources: &mut Vec<NamedResource>)
+        -> &mut Self
+    {
+        self.resources.append(resources);
+        self
+    }
+
+    /// The key file entry listing this bif's resources, for `KeyBuilder`.
+    pub fn key_bif_file(&self, name: &str)
+        -> KeyBifFile
+    {
+        let resources = self.resources
+            .iter()
+            .enumerate()
+            .map(|(i, r)| BifResource {
+                resource_index: i,
+                resource_type: r.resource_type.clone(),
+                name: r.name.to_string(),
+            })
+            .collect();
+
+        KeyBifFile {
+            name: name.to_owned(),
+            resources,
+        }
+    }
+
+    pub fn byte_size(&self)
+        -> usize
+    {
+        BifHeader::BYTE_SIZE
+            + VariableResource::BYTE_SIZE * self.resources.len()
+            + self.resources.iter().map(|r| r.data.len()).sum::<usize>()
+    }
+
+    pub fn write<W: Write>(self, writer: &mut W)
+        -> Result<(), MyError>
+    {
+        let resource_count = self.resources.len();
+
+        if self.bif_index > MAX_BIF_INDEX {
+            return Err(MyError::BifIndexTooLarge(self.bif_index));
+        }
+
+        if resource_count > MAX_RESOURCE_INDEX + 1 {
+            return Err(MyError::BifResourceIndexTooLarge(resource_count - 1));
+        }
+
+        let variable_table_offset = BifHeader::BYTE_SIZE;
+        let offset_to_resources = variable_table_offset
+            + VariableResource::BYTE_SIZE * resource_count;
+
+        let header = BifHeader {
+            version: Version::V1,
+            file_type: FileType::Bif,
+            variable_resource_count: resource_count as u32,
+            fixed_resource_count: 0,
+            variable_table_offset: variable_table_offset as u32,
+        };
+
+        let bif_id = (self.bif_index as u32) << BIF_INDEX_SHIFT;
+
+        let mut offset = offset_to_resources;
+
+        let variable_resources = self.resources
+            .iter()
+            .enumerate()
+            .map(|(i, resource)| {
+                let resource_size = resource.data.len();
+
+                let item = VariableResource {
+                    id: bif_id | i as u32,
+                    resource_offset: u32::try_from(offset)
+                        .or(Err(MyError::BifTooLarge(offset)))?,
+                    resource_size: u32::try_from(resource_size)
+                        .or(Err(MyError::BifTooLarge(offset + resource_size)))?,
+                    resource_type: resource.resource_type.id() as u32,
+                };
+
+                offset += resource_size;
+
+                Ok(item)
+            })
+            .collect::<Result<Vec<VariableResource>, MyError>>()?;
+
+        let mut writer = BufWriter::new(writer);
+
+        header.serialize_to(&mut writer)?;
+        variable_resources.serialize_to(&mut writer)?;
+
+        for resource in self.resources.iter() {
+            writer.write_all(&resource.data)?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Default for BifBuilder {
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn sample_builder() -> BifBuilder
+    {
+        let mut builder = BifBuilder::new();
+
+        builder
+            .set_bif_index(3)
+            .add_resource(NamedResource {
+                name: ResRef::try_from("appearance").unwrap(),
+                data: b"2DA V2.0".to_vec(),
+                resource_type: ResourceType::x2da,
+            })
+            .add_resource(NamedResource {
+                name: ResRef::try_from("as_an_crow1").unwrap(),
+                data: vec![1, 2, 3, 4, 5],
+                resource_type: ResourceType::wav,
+            });
+
+        builder
+    }
+
+    #[test]
+    fn write_bif_round_trip() {
+        let builder = sample_builder();
+        let expected_size = builder.byte_size();
+        let mut bytes = Vec::new();
+
+        builder.write(&mut bytes).unwrap();
+
+        assert_eq!(expected_size, bytes.len());
+        assert_eq!(b"BIFFV1  ", &bytes[0..8]);
+
+        let parsed = parse_bytes(bytes).unwrap();
+
+        assert_eq!(2, parsed.0.len());
+        assert_eq!(b"2DA V2.0".to_vec(), parsed.0[0].bytes);
+        assert_eq!(ResourceType::wav, parsed.0[1].resource_type);
+    }
+
+    #[test]
+    fn write_bif_ids_match_key() {
+        let builder = sample_builder();
+        let key_bif = builder.key_bif_file("data\\custom.bif");
+        let mut bytes = Vec::new();
+
+        builder.write(&mut bytes).unwrap();
+
+        let header = parse_header(&bytes).unwrap();
+        let variable_resources = parse_variable_resources(
+            &bytes,
+            header.variable_table_offset as usize,
+            header.variable_resource_count as usize,
+        ).unwrap();
+
+        assert_eq!(3 << 20 | 1, variable_resources[1].id);
+        assert_eq!(1, key_bif.resources[1].resource_index);
+        assert_eq!("as_an_crow1", key_bif.resources[1].name);
+    }
+
+    #[test]
+    fn write_bif_index_too_large() {
+        let mut builder = sample_builder();
+        builder.set_bif_index(MAX_BIF_INDEX + 1);
+
+        match builder.write(&mut Vec::new()).unwrap_err() {
+            MyError::BifIndexTooLarge(4096) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_bif_bad_magic() {
+        let mut bytes = Vec::new();
+        sample_builder().write(&mut bytes).unwrap();
+        bytes[0..4].copy_from_slice(b"KEY ");
+
+        match parse_bytes(bytes).unwrap_err() {
+            MyError::BadMagic(FileType::Bif, found) => assert_eq!("KEY ", found),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_bif_resource_out_of_bounds() {
+        let mut bytes = Vec::new();
+        sample_builder().write(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+
+        match parse_bytes(bytes).unwrap_err() {
+            MyError::OffsetOutOfBounds(offset) => assert_eq!(60, offset),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bif_reader_reads_on_demand() {
+        let mut bytes = Vec::new();
+        sample_builder().write(&mut bytes).unwrap();
+
+        let mut reader = BifReader::new(std::io::Cursor::new(bytes)).unwrap();
+
+        assert_eq!(2, reader.len());
+        assert_eq!(Some(ResourceType::wav), reader.resource_type(1));
+        assert_eq!(vec![1, 2, 3, 4, 5], reader.read(1).unwrap().bytes);
+        assert_eq!(b"2DA V2.0".to_vec(), reader.read(0).unwrap().bytes);
+
+        match reader.read(2).unwrap_err() {
+            MyError::InvalidResourceIndex(2) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bif_reader_table_out_of_bounds() {
+        let mut bytes = Vec::new();
+        sample_builder().write(&mut bytes).unwrap();
+        bytes.truncate(30);
+
+        match BifReader::new(std::io::Cursor::new(bytes)).unwrap_err() {
+            MyError::OffsetOutOfBounds(20) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bif_reader_resource_out_of_bounds() {
+        let mut bytes = Vec::new();
+        sample_builder().write(&mut bytes).unwrap();
+        // claim a size far past the end of the stream for the first resource
+        bytes[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut reader = BifReader::new(std::io::Cursor::new(bytes)).unwrap();
+
+        match reader.read(0).unwrap_err() {
+            MyError::OffsetOutOfBounds(52) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn bif_reader_mmap() {
+        let dir = crate::helpers::temp_dir::TempDir::new("mmap");
+        let path = dir.join("sample.bif");
+        let mut f = File::create(&path).unwrap();
+        sample_builder().write(&mut f).unwrap();
+
+        let reader = BifReader::open_mmap(&path).unwrap();
+
+        assert_eq!(&[1, 2, 3, 4, 5], reader.bytes(1).unwrap());
+    }
+}
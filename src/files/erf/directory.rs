@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Component, Path};
+
+use super::types::ErfFile;
+
+use crate::types::{
+    Resource,
+    ResRef,
+    RES_REF_LENGTH_LONG,
+    ResourceType,
+    FileType,
+    Error as MyError,
+};
+
+/// The file name a resource is extracted to. Types without a known
+/// extension use their numeric id, which `resource_type_from_path` reads
+/// back.
+///
+/// Resrefs come straight from the archive, so a name that isn't a single
+/// plain path component, such as one holding a path separator or a drive
+/// prefix, is rejected. Joined to a directory, the name stays inside it.
+pub fn resource_file_name(name: &str, resource_type: &ResourceType)
+    -> Result<String, MyError>
+{
+    let file_name = match resource_type.extension() {
+        Some(extension) => format!("{}.{}", name, extension),
+        None => format!("{}.{}", name, resource_type.id()),
+    };
+
+    let mut components = Path::new(&file_name).components();
+    let plain = matches!((components.next(), components.next()), (Some(Component::Normal(_)), None));
+
+    if name.is_empty() || ! plain || name.contains(['/', '\\', ':', '\0']) {
+        return Err(MyError::UnsafeResourceName(name.to_owned()));
+    }
+
+    Ok(file_name)
+}
+
+fn resource_type_from_path(path: &Path)
+    -> Option<ResourceType>
+{
+    let extension = path.extension()?.to_str()?;
+
+    ResourceType::from_extension(extension)
+        .or_else(|| extension.parse::<u16>().ok().map(ResourceType::from))
+}
+
+impl ErfFile {
+    /// Builds an erf from every `name.ext` file in `dir`, sorted by file
+    /// name. Hidden files and sub directories are skipped. Names can be up
+    /// to 32 chars, any longer than 16 make `write` use V1.1.
+    pub fn from_directory<P: AsRef<Path>>(dir: P)
+        -> Result<Self, MyError>
+    {
+        let mut paths = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        paths.sort();
+
+        let mut seen = HashSet::new();
+        let mut erf = ErfFile::new();
+
+        for path in paths {
+            let hidden = path
+                .file_name()
+                .map(|n| n.to_string_lossy().starts_with('.'))
+                .unwrap_or(true);
+
+            if hidden || ! path.is_file() {
+                continue;
+            }
+
+            let display = path.to_string_lossy().into_owned();
+
+            let resource_type = resource_type_from_path(&path)
+                .ok_or_else(|| MyError::UnknownExtension(display.clone()))?;
+
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            let name = ResRef::with_max_length(stem, RES_REF_LENGTH_LONG)?;
+
+            if ! seen.insert((name.to_lowercase(), resource_type.clone())) {
+                return Err(MyError::DuplicateResource(display));
+            }
+
+            erf.add_resource(Resource {
+                name,
+                data: fs::read(&path)?,
+                resource_type,
+            });
+        }
+
+        Ok(erf)
+    }
+
+    /// Packs `dir` into `output`. The erf, hak, mod or sav file type comes
+    /// from the output's extension, anything else is written as an erf.
+    /// Returns the number of resources packed.
+    pub fn pack_directory<P: AsRef<Path>, Q: AsRef<Path>>(dir: P, output: Q)
+        -> Result<usize, MyError>
+    {
+        let output = output.as_ref();
+        let extension = output
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let file_type = match FileType::from_erf_extension(&extension) {
+            FileType::Unknown => FileType::Erf,
+            file_type => file_type,
+        };
+
+        let mut erf = ErfFile::from_directory(dir)?;
+        let count = erf.resources.len();
+
+        erf.write(&mut File::create(output)?, file_type)?;
+
+        Ok(count)
+    }
+
+    /// Writes every resource to `dir` as `name.ext`, creating `dir` if
+    /// needed. Existing files are only replaced when `overwrite` is set.
+    pub fn extract_to_directory<P: AsRef<Path>>(&self, dir: P, overwrite: bool)
+        -> Result<(), MyError>
+    {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        for resource in self.resources.iter() {
+            let path = dir.join(resource_file_name(&resource.name, &resource.resource_type)?);
+
+            if ! overwrite && path.exists() {
+                return Err(MyError::PathAlreadyExists(path.to_string_lossy().into_owned()));
+            }
+
+            fs::write(&path, &resource.data)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::convert::TryFrom;
+    use std::io::Cursor;
+    use crate::helpers::temp_dir::TempDir;
+
+    fn test_dir(name: &str)
+        -> TempDir
+    {
+        TempDir::new(&format!("erf_dir_{}", name))
+    }
+
+    #[test]
+    fn pack_and_extract_directory() {
+        let dir = test_dir("round_trip");
+        let packed = dir.join("packed");
+        fs::create_dir_all(&packed).unwrap();
+
+        fs::write(packed.join("appearance.2da"), b"2DA V2.0").unwrap();
+        fs::write(packed.join("my_item.UTI"), b"UTI V3.2").unwrap();
+        fs::write(packed.join("mystery.4242"), b"?").unwrap();
+        fs::write(packed.join(".hidden"), b"").unwrap();
+
+        let mut bytes = Vec::new();
+        ErfFile::from_directory(&packed)
+            .unwrap()
+            .write(&mut bytes, FileType::Hak)
+            .unwrap();
+
+        let parsed = ErfFile::parse_from(&mut Cursor::new(bytes)).unwrap();
+
+        assert_eq!(3, parsed.resources.len());
+        assert_eq!(ResourceType::uti, parsed.resources[1].resource_type);
+        assert_eq!(ResourceType::Unknown(4242), parsed.resources[2].resource_type);
+
+        let extracted = dir.join("extracted");
+        parsed.extract_to_directory(&extracted, false).unwrap();
+
+        assert_eq!(b"2DA V2.0".to_vec(), fs::read(extracted.join("appearance.2da")).unwrap());
+        assert_eq!(b"UTI V3.2".to_vec(), fs::read(extracted.join("my_item.uti")).unwrap());
+        assert!(extracted.join("mystery.4242").exists());
+
+        match parsed.extract_to_directory(&extracted, false).unwrap_err() {
+            MyError::PathAlreadyExists(_) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pack_directory_picks_file_type() {
+        let dir = test_dir("file_type");
+        let packed = dir.join("packed");
+        fs::create_dir_all(&packed).unwrap();
+        fs::write(packed.join("my_item.uti"), b"UTI V3.2").unwrap();
+
+        assert_eq!(1, ErfFile::pack_directory(&packed, dir.join("out.hak")).unwrap());
+        assert_eq!(b"HAK ", &fs::read(dir.join("out.hak")).unwrap()[0..4]);
+
+        ErfFile::pack_directory(&packed, dir.join("out.zip")).unwrap();
+        assert_eq!(b"ERF ", &fs::read(dir.join("out.zip")).unwrap()[0..4]);
+    }
+
+    #[test]
+    fn extract_and_pack_long_names() {
+        let dir = test_dir("long_names");
+        let name = "a_resref_longer_than_sixteen_chr";
+
+        let mut erf = ErfFile::new();
+        erf.add_resource(Resource {
+            name: ResRef::with_max_length(name.to_owned(), RES_REF_LENGTH_LONG).unwrap(),
+            data: b"UTI V3.2".to_vec(),
+            resource_type: ResourceType::uti,
+        });
+
+        let extracted = dir.join("extracted");
+        erf.extract_to_directory(&extracted, false).unwrap();
+
+        let output = dir.join("out.hak");
+        assert_eq!(1, ErfFile::pack_directory(&extracted, &output).unwrap());
+
+        let bytes = fs::read(&output).unwrap();
+        assert_eq!(b"HAK V1.1", &bytes[0..8]);
+
+        let parsed = ErfFile::parse_from(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(name, parsed.resources[0].name.as_str());
+    }
+
+    #[test]
+    fn pack_directory_unknown_extension() {
+        let dir = test_dir("unknown");
+        fs::write(dir.join("readme.markdown"), b"").unwrap();
+
+        match ErfFile::from_directory(&dir).unwrap_err() {
+            MyError::UnknownExtension(_) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pack_directory_name_too_long() {
+        let dir = test_dir("too_long");
+        fs::write(dir.join(format!("{}.uti", "a".repeat(33))), b"").unwrap();
+
+        match ErfFile::from_directory(&dir).unwrap_err() {
+            MyError::ResRefError(_) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn extract_rejects_unsafe_names() {
+        let dir = test_dir("unsafe");
+        let extracted = dir.join("extracted");
+
+        for name in ["../../escape", "/etc/escape", "..\\escape", "c:escape"] {
+            let mut erf = ErfFile::new();
+            erf.add_resource(Resource {
+                name: ResRef::try_from(name).unwrap(),
+                data: b"boo".to_vec(),
+                resource_type: ResourceType::txt,
+            });
+
+            match erf.extract_to_directory(&extracted, true).unwrap_err() {
+                MyError::UnsafeResourceName(n) => assert_eq!(name, n),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+
+        assert!(! std::env::temp_dir().join("escape.txt").exists());
+        assert_eq!(0, fs::read_dir(&extracted).unwrap().count());
+    }
+}
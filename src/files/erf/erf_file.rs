@@ -1,5 +1,4 @@
 use std::io::prelude::*;
-use std::io::BufReader;
 
 use super::types::*;
 
@@ -8,9 +7,11 @@ use crate::types::{
     Error as MyError,
     FileType,
     LanguageId,
+    Version,
+    RES_REF_LENGTH,
 };
 
-use super::parser::*;
+use super::erf_reader::ErfReader;
 use super::writer::write as _write;
 
 #[derive(Debug)]
@@ -55,43 +56,44 @@ impl ErfFile {
         self
     }
 
+    /// The version `write` uses: V1.1 if the file was parsed as V1.1 or
+    /// any resource name is too long for V1.0, otherwise V1.0.
+    pub fn version(&self)
+        -> Version
+    {
+        let parsed_long = self.header
+            .as_ref()
+            .map(|h| h.version == Version::V1_1)
+            .unwrap_or(false);
+
+        let long_names = self.resources
+            .iter()
+            .any(|r| r.name.len() > RES_REF_LENGTH);
+
+        if parsed_long || long_names {
+            Version::V1_1
+        } else {
+            Version::V1
+        }
+    }
+
     pub fn write<W: Write>(&mut self, writer:  &mut W, file_type: FileType)
         -> Result<(), MyError>
     {
-        _write(self, writer, file_type)
+        let version = self.version();
+        _write(self, writer, file_type, version)
+    }
+
+    pub fn write_with_version<W: Write>(&mut self, writer: &mut W, file_type: FileType, version: Version)
+        -> Result<(), MyError>
+    {
+        _write(self, writer, file_type, version)
     }
     
     pub fn parse_from<R: Read + Seek>(reader: &mut R)
         -> Result<Self, MyError>
     {
-        // let mut reader = BufReader::new(reader);
-        let mut reader = BufReader::new(reader);
-
-        let header = parse_header(&mut reader)?;
-
-        // dbg!("{:?}", &header);
-    
-        let localized_language_strings =
-            parse_localized_language_strings(&mut reader, &header)?;
-    
-        // dbg!("{:?}", &localized_language_strings);
-    
-        let key_list = parse_key_list(&mut reader, &header)?;
-    
-        // dbg!("{:?}", &key_list[0..2]);
-
-        let resource_list_items = parse_resource_list_items(&mut reader, &header)?;
-
-    
-        let resources = parse_resources(&mut reader, key_list, &resource_list_items)?;
-    
-        // dbg!("{:?}", &resources[0..2]);
-    
-        Ok(ErfFile {
-            header: Some(header),
-            descriptions: localized_language_strings,
-            resources: resources,
-        })
+        ErfReader::new(reader)?.into_erf_file()
     }
 
 }
\ No newline at end of file
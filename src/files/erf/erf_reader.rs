@@ -0,0 +1,255 @@
+use std::io::prelude::*;
+use std::io::{BufReader, SeekFrom};
+
+use super::types::*;
+
+use crate::types::{
+    Resource,
+    ResRef,
+    ResourceType,
+    Error as MyError,
+};
+use crate::helpers::reader::ReaderExt;
+
+use super::parser::*;
+
+/// Reads an erf's header, descriptions, key list and resource list up
+/// front and each resource's data only when asked for.
+#[derive(Debug)]
+pub struct ErfReader<R: Read + Seek> {
+    reader: BufReader<R>,
+    stream_length: usize,
+    pub header: ErfHeader,
+    pub descriptions: Vec<ErfDescription>,
+    pub keys: Vec<ErfKey>,
+    pub resource_list: Vec<ErfResourceListItem>,
+}
+
+impl<R: Read + Seek> ErfReader<R> {
+    pub fn new(reader: R)
+        -> Result<Self, MyError>
+    {
+        let mut reader = BufReader::new(reader);
+
+        let stream_length = reader.seek(SeekFrom::End(0))? as usize;
+        reader.seek_from_start(0)?;
+
+        let header = parse_header(&mut reader)?;
+        let descriptions = parse_localized_language_strings(&mut reader, &header)?;
+        let keys = parse_key_list(&mut reader, &header)?;
+        let resource_list = parse_resource_list_items(&mut reader, &header)?;
+
+        Ok(ErfReader {
+            reader,
+            stream_length,
+            header,
+            descriptions,
+            keys,
+            resource_list,
+        })
+    }
+
+    pub fn len(&self)
+        -> usize
+    {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self)
+        -> bool
+    {
+        self.keys.is_empty()
+    }
+
+    pub fn iter(&self)
+        -> impl Iterator<Item = (&ErfKey, &ErfResourceListItem)>
+    {
+        self.keys
+            .iter()
+            .zip(self.resource_list.iter())
+    }
+
+    /// The index of a resource, comparing names the way the game does,
+    /// without case.
+    pub fn find(&self, name: &ResRef, resource_type: ResourceType)
+        -> Option<usize>
+    {
+        self.keys
+            .iter()
+            .position(|k| {
+                k.resource_type == resource_type
+                    && k.file_name.eq_ignore_ascii_case(name)
+            })
+    }
+
+    pub fn read_data(&mut self, index: usize)
+        -> Result<Vec<u8>, MyError>
+    {
+        let item = self.resource_list
+            .get(index)
+            .ok_or(MyError::InvalidResourceIndex(index))?;
+
+        let offset = item.offset as usize;
+
+        if offset + item.size as usize > self.stream_length {
+            return Err(MyError::OffsetOutOfBounds(offset));
+        }
+
+        self.reader.seek_from_start(offset as u64)?;
+
+        Ok(self.reader.read_bytes(item.size as usize)?)
+    }
+
+    pub fn read(&mut self, index: usize)
+        -> Result<Resource, MyError>
+    {
+        let data = self.read_data(index)?;
+        let key = &self.keys[index];
+
+        Ok(Resource {
+            name: key.file_name.clone(),
+            resource_type: key.resource_type.clone(),
+            data,
+        })
+    }
+
+    /// Reads every resource into an `ErfFile`.
+    pub fn into_erf_file(mut self)
+        -> Result<ErfFile, MyError>
+    {
+        let resources = (0..self.len())
+            .map(|i| self.read(i))
+            .collect::<Result<Vec<_>, MyError>>()?;
+
+        Ok(ErfFile {
+            header: Some(self.header),
+            descriptions: self.descriptions,
+            resources,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::io::Cursor;
+    use std::convert::TryFrom;
+    use crate::types::{FileType, Version};
+
+    fn sample_erf_bytes()
+        -> Vec<u8>
+    {
+        let mut bytes = Vec::new();
+
+        ErfFile::new()
+            .add_resource(Resource {
+                name: ResRef::try_from("appearance").unwrap(),
+                data: b"2DA V2.0".to_vec(),
+                resource_type: ResourceType::x2da,
+            })
+            .add_resource(Resource {
+                name: ResRef::try_from("my_item").unwrap(),
+                data: vec![1, 2, 3],
+                resource_type: ResourceType::uti,
+            })
+            .write(&mut bytes, FileType::Hak)
+            .unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn erf_reader_find_and_read() {
+        let mut reader = ErfReader::new(Cursor::new(sample_erf_bytes())).unwrap();
+        let name = ResRef::try_from("MY_ITEM").unwrap();
+
+        let index = reader.find(&name, ResourceType::uti).unwrap();
+        let resource = reader.read(index).unwrap();
+
+        assert_eq!(1, index);
+        assert_eq!(vec![1, 2, 3], resource.data);
+        assert_eq!(None, reader.find(&name, ResourceType::x2da));
+        assert_eq!(2, reader.iter().count());
+    }
+
+    #[test]
+    fn erf_reader_invalid_index() {
+        let mut reader = ErfReader::new(Cursor::new(sample_erf_bytes())).unwrap();
+
+        match reader.read(2).unwrap_err() {
+            MyError::InvalidResourceIndex(2) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn erf_reader_size_past_end() {
+        let mut reader = ErfReader::new(Cursor::new(sample_erf_bytes())).unwrap();
+        let offset = reader.resource_list[1].offset as usize;
+        reader.resource_list[1].size = u32::MAX;
+
+        match reader.read(1).unwrap_err() {
+            MyError::OffsetOutOfBounds(o) => assert_eq!(offset, o),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn erf_long_names_round_trip() {
+        let name = ResRef::with_max_length("a_very_long_ee_resource_name".to_owned(), 32).unwrap();
+        let mut bytes = Vec::new();
+
+        let mut erf = ErfFile::new();
+        erf.add_resource(Resource {
+            name: name.clone(),
+            data: vec![7],
+            resource_type: ResourceType::uti,
+        });
+
+        assert_eq!(Version::V1_1, erf.version());
+
+        erf.write(&mut bytes, FileType::Mod).unwrap();
+
+        assert_eq!(b"MOD V1.1", &bytes[0..8]);
+
+        let mut reader = ErfReader::new(Cursor::new(bytes)).unwrap();
+
+        assert_eq!(Version::V1_1, reader.header.version);
+        assert_eq!(name, reader.read(0).unwrap().name);
+
+        let parsed = reader.into_erf_file().unwrap();
+
+        assert_eq!(Version::V1_1, parsed.version());
+    }
+
+    #[test]
+    fn erf_long_name_in_v1() {
+        let name = ResRef::with_max_length("a_very_long_ee_resource_name".to_owned(), 32).unwrap();
+
+        let e = ErfFile::new()
+            .add_resource(Resource {
+                name,
+                data: vec![7],
+                resource_type: ResourceType::uti,
+            })
+            .write_with_version(&mut Vec::new(), FileType::Erf, Version::V1)
+            .unwrap_err();
+
+        match e {
+            MyError::ResRefError(_) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn erf_bad_magic() {
+        let mut bytes = sample_erf_bytes();
+        bytes[0..4].copy_from_slice(b"KEY ");
+
+        match ErfReader::new(Cursor::new(bytes)).unwrap_err() {
+            MyError::BadMagic(FileType::Erf, found) => assert_eq!("KEY ", found),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
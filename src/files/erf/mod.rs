@@ -1,4 +1,6 @@
 pub mod types;
 pub mod erf_file;
+pub mod erf_reader;
+pub mod directory;
 pub(in self) mod writer;
 pub(in self) mod parser;
\ No newline at end of file
@@ -1,6 +1,7 @@
 use std::io::prelude::*;
 
 use super::types::{
+    res_ref_length,
     ErfHeader,
     ErfKey,
     ErfDescription,
@@ -11,21 +12,32 @@ use crate::types::{
     ResourceType,
     Version,
     FileType,
-    Resource,
     ResRef,
     LanguageId,
     Error as MyError,
 };
 use crate::helpers::reader::ReaderExt;
 
-use std::convert::TryFrom;
 
 pub fn parse_header<R>(reader: &mut R)
     -> Result<ErfHeader, MyError>
     where R: BufRead + Seek
 {
-    let file_type = FileType::from(reader.read_bytes_to_string(4)?.as_str());
-    let version = Version::from(reader.read_bytes_to_string(4)?.as_str());
+    let file_type_string = reader.read_bytes_to_string(4)?;
+    let file_type = FileType::from(file_type_string.as_str());
+    let version_string = reader.read_bytes_to_string(4)?;
+    let version = Version::from(version_string.as_str());
+
+    match file_type {
+        FileType::Erf | FileType::Hak | FileType::Mod | FileType::Sav => (),
+        _ => return Err(MyError::BadMagic(FileType::Erf, file_type_string)),
+    }
+
+    match version {
+        Version::V1 | Version::V1_1 => (),
+        _ => return Err(MyError::UnsupportedVersion(file_type, version_string)),
+    }
+
     let language_count = reader.read_u32()?;
     let localized_string_size = reader.read_u32()?;
     let entry_count = reader.read_u32()?;
@@ -83,11 +95,12 @@ pub fn parse_key_list<R>(reader: &mut R, header: &ErfHeader)
 {
     reader.seek_from_start(header.offset_to_key_list as u64)?;
 
+    let name_length = res_ref_length(&header.version);
+
     (0..header.entry_count)
-        .into_iter()
         .map(|_| {
-            let file_name = reader.read_bytes(16)?;
-            let file_name = ResRef::try_from(file_name.as_slice())?;
+            let file_name = reader.read_bytes(name_length)?;
+            let file_name = ResRef::from_bytes_with_max_length(&file_name, name_length)?;
 
             let resource_id = reader.read_u32()?;
             let resource_type = ResourceType::from(reader.read_u16()?);
@@ -122,34 +135,3 @@ pub fn parse_resource_list_items<R>(reader: &mut R, header: &ErfHeader)
         })
         .collect::<Result<Vec<_>, MyError>>()
 }
-
-pub fn parse_resources<R>(
-    reader: &mut R,
-    key_list: Vec<ErfKey>,
-    resource_list_items: &Vec<ErfResourceListItem>
-)
-    -> Result<Vec<Resource>, MyError>
-    where R: BufRead + Seek
-{        
-    key_list
-        .into_iter()
-        .zip(resource_list_items)
-        .map(|(key, rli)| {
-            reader.seek_from_start(rli.offset as u64)?;
-            
-            let ErfKey {
-                file_name,
-                resource_type,
-                ..
-            } = key;
-
-            let data = reader.read_bytes(rli.size as usize)?;
-
-            Ok(Resource {
-                name: file_name,
-                resource_type: resource_type,
-                data: data,
-            })
-        })
-        .collect::<Result<Vec<_>, MyError>>()
-}
\ No newline at end of file
@@ -9,6 +9,8 @@ use crate::types::{
     ResourceType,
     ResRef,
     LanguageId,
+    RES_REF_LENGTH,
+    RES_REF_LENGTH_LONG,
     StaticByteSize,
     SerializeToBytes,
     Error as MyError,
@@ -92,22 +94,48 @@ pub struct ErfKey {
     pub resource_type: ResourceType,
 }
 
-impl StaticByteSize for ErfKey {
-    const BYTE_SIZE: usize = 24;
+/// ERF V1.1 keys hold 32 char resrefs, V1.0 keys 16.
+#[inline]
+pub fn res_ref_length(version: &Version)
+    -> usize
+{
+    match version {
+        Version::V1_1 => RES_REF_LENGTH_LONG,
+        _ => RES_REF_LENGTH,
+    }
 }
 
-impl SerializeToBytes for ErfKey {
-    fn serialize_to<F: Write>(self, writer: &mut F)
+impl ErfKey {
+    #[inline]
+    pub fn byte_size(version: &Version)
+        -> usize
+    {
+        res_ref_length(version) + 8
+    }
+
+    pub fn serialize_to_version<F: Write>(self, writer: &mut F, version: &Version)
         -> Result<(), MyError>
     {
         let ErfKey { file_name, resource_id, resource_type } = self;
 
-        writer.write(&file_name.serialize())?;
-        writer.write(&resource_id.to_le_bytes())?;
-        writer.write(&(resource_type as u16).to_le_bytes())?;
-        writer.write(&[0; 2])?;
+        writer.write_all(&file_name.serialize_with_length(res_ref_length(version))?)?;
+        writer.write_all(&resource_id.to_le_bytes())?;
+        writer.write_all(&resource_type.id().to_le_bytes())?;
+        writer.write_all(&[0; 2])?;
 
         Ok(())
+    }
+}
+
+impl StaticByteSize for ErfKey {
+    const BYTE_SIZE: usize = 24;
+}
+
+impl SerializeToBytes for ErfKey {
+    fn serialize_to<F: Write>(self, writer: &mut F)
+        -> Result<(), MyError>
+    {
+        self.serialize_to_version(writer, &Version::V1)
     }    
 }
 
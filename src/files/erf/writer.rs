@@ -5,6 +5,7 @@ use std::mem;
 use crate::helpers::date;
 use crate::types::{
     Error as MyError,
+    ResRefError,
     Version,
     FileType,
     NULL_U32,
@@ -13,6 +14,7 @@ use crate::types::{
 };
 
 use super::types::{
+    res_ref_length,
     ErfFile,
     ErfHeader,
     ErfKey,
@@ -20,15 +22,26 @@ use super::types::{
 };
 
 
-pub fn write<W: Write>(erf_file: &mut ErfFile, writer: &mut W, file_type: FileType)
+pub fn write<W: Write>(erf_file: &mut ErfFile, writer: &mut W, file_type: FileType, version: Version)
     -> Result<(), MyError>
 {
+    match version {
+        Version::V1 | Version::V1_1 => (),
+        _ => Err(MyError::UnsupportedVersion(file_type.clone(), version.as_str_ref().to_owned()))?,
+    }
+
+    let name_length = res_ref_length(&version);
+
+    if erf_file.resources.iter().any(|r| r.name.len() > name_length) {
+        Err(ResRefError::InvalidLengthTooLong)?;
+    }
+
     let descriptions = mem::replace(&mut erf_file.descriptions, Vec::new());
     let resources = mem::replace(&mut erf_file.resources, Vec::new());
     
     let entry_count = resources.len();
     let description_count = descriptions.len();
-    let key_list_size = ErfKey::BYTE_SIZE * entry_count;
+    let key_list_size = ErfKey::byte_size(&version) * entry_count;
     let resource_list_size =  ErfResourceListItem::BYTE_SIZE * entry_count;
     let header_size = ErfHeader::BYTE_SIZE;
     let language_size = descriptions
@@ -41,7 +54,7 @@ pub fn write<W: Write>(erf_file: &mut ErfFile, writer: &mut W, file_type: FileTy
     let offset_to_resources = offset_to_resource_list + resource_list_size;
 
     let header = ErfHeader {
-        version: Version::V1,
+        version: version.clone(),
         file_type: file_type,
         language_count: description_count as u32,
         localized_string_size: language_size as u32,
@@ -86,7 +99,10 @@ pub fn write<W: Write>(erf_file: &mut ErfFile, writer: &mut W, file_type: FileTy
 
     header.serialize_to(&mut writer)?;
     descriptions.serialize_to(&mut writer)?;
-    key_list.serialize_to(&mut writer)?;
+    for key in key_list {
+        key.serialize_to_version(&mut writer, &version)?;
+    }
+
     resource_list.serialize_to(&mut writer)?;
 
     resources
@@ -0,0 +1,59 @@
+use std::io::prelude::*;
+use std::io::BufReader;
+
+use super::types::{
+    GffHeader,
+    GffStruct,
+    GffTables,
+};
+
+use crate::types::Error as MyError;
+
+use super::parser::*;
+use super::writer::write as _write;
+
+#[derive(Debug, Clone)]
+pub struct GffFile {
+    pub header: Option<GffHeader>,
+    pub file_type: String,
+    pub root: GffStruct,
+    /// The tables the file was parsed from, written back as they are
+    /// while `root` still matches them.
+    pub(crate) source: Option<GffTables>,
+}
+
+impl GffFile {
+    pub fn new(file_type: &str)
+        -> Self
+    {
+        GffFile {
+            header: None,
+            file_type: file_type.to_owned(),
+            root: GffStruct::new(u32::MAX),
+            source: None,
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W)
+        -> Result<(), MyError>
+    {
+        _write(self, writer)
+    }
+
+    pub fn parse_from<R: Read + Seek>(reader: &mut R)
+        -> Result<Self, MyError>
+    {
+        let mut reader = BufReader::new(reader);
+
+        let header = parse_header(&mut reader)?;
+        let tables = parse_tables(&mut reader, &header)?;
+        let root = read_root(&tables)?;
+
+        Ok(GffFile {
+            file_type: header.file_type.clone(),
+            header: Some(header),
+            root,
+            source: Some(tables),
+        })
+    }
+}
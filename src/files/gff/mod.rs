@@ -0,0 +1,4 @@
+pub mod types;
+pub mod gff_file;
+mod writer;
+mod parser;
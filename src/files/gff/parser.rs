@@ -0,0 +1,346 @@
+use std::io::prelude::*;
+use std::io::{Cursor, SeekFrom};
+use std::convert::TryFrom;
+
+use encoding_rs::WINDOWS_1252;
+
+use super::types::{
+    GffError,
+    GffHeader,
+    GffTables,
+    GffStructEntry,
+    GffFieldEntry,
+    GffFieldType,
+    GffStruct,
+    GffField,
+    GffValue,
+    CExoLocString,
+    CExoLocSubString,
+    GFF_LABEL_LENGTH,
+    GFF_MAX_DEPTH,
+};
+
+use crate::types::{
+    Version,
+    FileType,
+    ResRef,
+    NULL_U32,
+    StaticByteSize,
+    Error as MyError,
+};
+use crate::helpers::reader::ReaderExt;
+
+pub fn parse_header<R>(reader: &mut R)
+    -> Result<GffHeader, MyError>
+    where R: BufRead + Seek
+{
+    let file_type = reader.read_bytes_to_string(4)?;
+    let file_version = reader.read_bytes_to_string(4)?;
+    let version = Version::from(file_version.as_str());
+
+    // The file type is the resource's own, UTI, IFO and so on, only the
+    // version says it is a gff.
+    if version != Version::V3_2 {
+        return Err(MyError::UnsupportedVersion(FileType::Gff, file_version));
+    }
+
+    Ok(GffHeader {
+        file_type,
+        version,
+        struct_offset: reader.read_u32()?,
+        struct_count: reader.read_u32()?,
+        field_offset: reader.read_u32()?,
+        field_count: reader.read_u32()?,
+        label_offset: reader.read_u32()?,
+        label_count: reader.read_u32()?,
+        field_data_offset: reader.read_u32()?,
+        field_data_count: reader.read_u32()?,
+        field_indices_offset: reader.read_u32()?,
+        field_indices_count: reader.read_u32()?,
+        list_indices_offset: reader.read_u32()?,
+        list_indices_count: reader.read_u32()?,
+    })
+}
+
+pub(crate) fn parse_tables<R>(reader: &mut R, header: &GffHeader)
+    -> Result<GffTables, MyError>
+    where R: BufRead + Seek
+{
+    let stream_length = reader.seek(SeekFrom::End(0))? as usize;
+
+    let sections = [
+        (header.struct_offset, header.struct_count as usize * GffStructEntry::BYTE_SIZE),
+        (header.field_offset, header.field_count as usize * GffFieldEntry::BYTE_SIZE),
+        (header.label_offset, header.label_count as usize * GFF_LABEL_LENGTH),
+        (header.field_data_offset, header.field_data_count as usize),
+        (header.field_indices_offset, header.field_indices_count as usize),
+        (header.list_indices_offset, header.list_indices_count as usize),
+    ];
+
+    for (offset, size) in sections.iter() {
+        if *offset as usize + size > stream_length {
+            return Err(MyError::OffsetOutOfBounds(*offset as usize));
+        }
+    }
+
+    Ok(GffTables {
+        structs: parse_struct_entries(reader, header)?,
+        fields: parse_field_entries(reader, header)?,
+        labels: parse_labels(reader, header)?,
+        field_data: parse_block(reader, header.field_data_offset, header.field_data_count)?,
+        field_indices: parse_block(reader, header.field_indices_offset, header.field_indices_count)?,
+        list_indices: parse_block(reader, header.list_indices_offset, header.list_indices_count)?,
+    })
+}
+
+/// Gff files are trees, so every struct index may only be reached once.
+/// This keeps files that point at the same struct over and over from
+/// making the parser do exponential work.
+pub(crate) fn read_root(tables: &GffTables)
+    -> Result<GffStruct, MyError>
+{
+    let mut visited = vec![false; tables.structs.len()];
+    read_struct(tables, 0, 0, &mut visited)
+}
+
+fn parse_struct_entries<R>(reader: &mut R, header: &GffHeader)
+    -> Result<Vec<GffStructEntry>, MyError>
+    where R: BufRead + Seek
+{
+    reader.seek_from_start(header.struct_offset as u64)?;
+
+    (0..header.struct_count)
+        .map(|_| {
+            Ok(GffStructEntry {
+                struct_type: reader.read_u32()?,
+                data_or_offset: reader.read_u32()?,
+                field_count: reader.read_u32()?,
+            })
+        })
+        .collect::<Result<Vec<_>, MyError>>()
+}
+
+fn parse_field_entries<R>(reader: &mut R, header: &GffHeader)
+    -> Result<Vec<GffFieldEntry>, MyError>
+    where R: BufRead + Seek
+{
+    reader.seek_from_start(header.field_offset as u64)?;
+
+    (0..header.field_count)
+        .map(|_| {
+            let field_type = GffFieldType::try_from(reader.read_u32()?)?;
+
+            Ok(GffFieldEntry {
+                field_type,
+                label_index: reader.read_u32()?,
+                data_or_offset: reader.read_u32()?,
+            })
+        })
+        .collect::<Result<Vec<_>, MyError>>()
+}
+
+fn parse_labels<R>(reader: &mut R, header: &GffHeader)
+    -> Result<Vec<String>, MyError>
+    where R: BufRead + Seek
+{
+    reader.seek_from_start(header.label_offset as u64)?;
+
+    (0..header.label_count)
+        .map(|_| {
+            let label = reader.read_bytes_to_string(GFF_LABEL_LENGTH)?;
+            Ok(label.trim_end_matches(char::from(0)).to_owned())
+        })
+        .collect::<Result<Vec<_>, MyError>>()
+}
+
+fn parse_block<R>(reader: &mut R, offset: u32, size: u32)
+    -> Result<Vec<u8>, MyError>
+    where R: BufRead + Seek
+{
+    reader.seek_from_start(offset as u64)?;
+    Ok(reader.read_bytes(size as usize)?)
+}
+
+fn read_struct(tables: &GffTables, index: u32, depth: usize, visited: &mut [bool])
+    -> Result<GffStruct, MyError>
+{
+    if depth > GFF_MAX_DEPTH {
+        Err(GffError::StructNestedTooDeep)?;
+    }
+
+    let entry = tables.structs
+        .get(index as usize)
+        .ok_or(GffError::InvalidStructIndex(index))?;
+
+    if visited[index as usize] {
+        Err(GffError::StructReused(index))?;
+    }
+    visited[index as usize] = true;
+
+    let field_indices = match entry.field_count {
+        0 => Vec::new(),
+        1 => vec![entry.data_or_offset],
+        count => {
+            let mut cursor = block_cursor(&tables.field_indices, entry.data_or_offset)?;
+
+            (0..count)
+                .map(|_| cursor.read_u32())
+                .collect::<Result<Vec<_>, _>>()
+                .or(Err(GffError::InvalidDataOffset(entry.data_or_offset)))?
+        }
+    };
+
+    let fields = field_indices
+        .into_iter()
+        .map(|i| read_field(tables, i, depth, visited))
+        .collect::<Result<Vec<_>, MyError>>()?;
+
+    Ok(GffStruct {
+        struct_type: entry.struct_type,
+        fields,
+    })
+}
+
+fn read_field(tables: &GffTables, index: u32, depth: usize, visited: &mut [bool])
+    -> Result<GffField, MyError>
+{
+    let entry = tables.fields
+        .get(index as usize)
+        .ok_or(GffError::InvalidFieldIndex(index))?;
+
+    let label = tables.labels
+        .get(entry.label_index as usize)
+        .ok_or(GffError::InvalidLabelIndex(entry.label_index))?
+        .clone();
+
+    let data = entry.data_or_offset;
+
+    let value = match entry.field_type {
+        GffFieldType::Byte => GffValue::Byte(data as u8),
+        GffFieldType::Char => GffValue::Char(data as u8 as i8),
+        GffFieldType::Word => GffValue::Word(data as u16),
+        GffFieldType::Short => GffValue::Short(data as u16 as i16),
+        GffFieldType::Dword => GffValue::Dword(data),
+        GffFieldType::Int => GffValue::Int(data as i32),
+        GffFieldType::Float => GffValue::Float(f32::from_bits(data)),
+        GffFieldType::Struct =>
+            GffValue::Struct(read_struct(tables, data, depth + 1, visited)?),
+        GffFieldType::List => {
+            let mut cursor = block_cursor(&tables.list_indices, data)?;
+
+            let struct_indices = cursor.read_u32()
+                .and_then(|count| {
+                    (0..count)
+                        .map(|_| cursor.read_u32())
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .or(Err(GffError::InvalidDataOffset(data)))?;
+
+            let structs = struct_indices
+                .into_iter()
+                .map(|i| read_struct(tables, i, depth + 1, visited))
+                .collect::<Result<Vec<_>, MyError>>()?;
+
+            GffValue::List(structs)
+        },
+        field_type => {
+            let mut cursor = block_cursor(&tables.field_data, data)?;
+
+            read_complex_value(&mut cursor, field_type)
+                .or(Err(GffError::InvalidDataOffset(data)))?
+        },
+    };
+
+    Ok(GffField {
+        label,
+        value,
+    })
+}
+
+fn read_complex_value(cursor: &mut Cursor<&[u8]>, field_type: GffFieldType)
+    -> Result<GffValue, MyError>
+{
+    let value = match field_type {
+        GffFieldType::Dword64 => GffValue::Dword64(cursor.read_u64()?),
+        GffFieldType::Int64 => GffValue::Int64(cursor.read_u64()? as i64),
+        GffFieldType::Double => GffValue::Double(cursor.read_f64()?),
+        GffFieldType::CExoString => {
+            let size = cursor.read_u32()?;
+            GffValue::CExoString(read_string(cursor, size)?)
+        },
+        GffFieldType::ResRef => {
+            let size = cursor.read_u8()?;
+            let bytes = cursor.read_bytes(size as usize)?;
+            GffValue::ResRef(ResRef::try_from(bytes.as_slice())?)
+        },
+        GffFieldType::CExoLocString => {
+            let _total_size = cursor.read_u32()?;
+            let string_ref = match cursor.read_u32()? {
+                NULL_U32 => None,
+                s => Some(s),
+            };
+            let string_count = cursor.read_u32()?;
+
+            let strings = (0..string_count)
+                .map(|_| {
+                    let string_id = cursor.read_u32()?;
+                    let size = cursor.read_u32()?;
+                    let text = read_string(cursor, size)?;
+
+                    Ok(CExoLocSubString {
+                        string_id,
+                        text,
+                    })
+                })
+                .collect::<Result<Vec<_>, MyError>>()?;
+
+            GffValue::CExoLocString(CExoLocString {
+                string_ref,
+                strings,
+            })
+        },
+        GffFieldType::Void => {
+            let size = cursor.read_u32()?;
+            GffValue::Void(read_sized_bytes(cursor, size)?)
+        },
+        _ => unreachable!("Simple gff field types are read from the field entry."),
+    };
+
+    Ok(value)
+}
+
+/// Gff strings are Windows-1252, which maps every byte, so decoding and
+/// re-encoding gives back the original bytes.
+fn read_string(cursor: &mut Cursor<&[u8]>, size: u32)
+    -> Result<String, MyError>
+{
+    let bytes = read_sized_bytes(cursor, size)?;
+    Ok(WINDOWS_1252.decode_without_bom_handling(&bytes).0.into_owned())
+}
+
+/// Sizes come straight from the file, check them against what is left of
+/// the block before allocating for them.
+fn read_sized_bytes(cursor: &mut Cursor<&[u8]>, size: u32)
+    -> Result<Vec<u8>, MyError>
+{
+    let remaining = (cursor.get_ref().len() as u64).saturating_sub(cursor.position());
+
+    if size as u64 > remaining {
+        return Err(GffError::InvalidDataOffset(cursor.position() as u32).into());
+    }
+
+    Ok(cursor.read_bytes(size as usize)?)
+}
+
+fn block_cursor(block: &[u8], offset: u32)
+    -> Result<Cursor<&[u8]>, GffError>
+{
+    match offset as usize {
+        o if o < block.len() => {
+            let mut cursor = Cursor::new(block);
+            cursor.set_position(o as u64);
+            Ok(cursor)
+        },
+        _ => Err(GffError::InvalidDataOffset(offset)),
+    }
+}
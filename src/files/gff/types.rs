@@ -0,0 +1,342 @@
+pub use super::gff_file::GffFile;
+
+use std::fmt;
+use std::error::Error;
+use std::io::prelude::*;
+use std::convert::TryFrom;
+
+use crate::types::{
+    Version,
+    ResRef,
+    StaticByteSize,
+    SerializeToBytes,
+    Error as MyError,
+};
+
+pub const GFF_LABEL_LENGTH: usize = 16;
+/// Deepest struct nesting the parser follows. Game files stay far below
+/// it, so hitting it means the file is corrupt.
+pub const GFF_MAX_DEPTH: usize = 64;
+
+#[derive(Debug, PartialEq)]
+pub enum GffError
+{
+    InvalidFieldType(u32),
+    InvalidStructIndex(u32),
+    InvalidFieldIndex(u32),
+    InvalidLabelIndex(u32),
+    InvalidDataOffset(u32),
+    StructNestedTooDeep,
+    StructReused(u32),
+    LabelTooLong(String),
+    FileTypeTooLong(String),
+}
+
+impl fmt::Display for GffError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)
+        -> fmt::Result
+    {
+        match self {
+            GffError::InvalidFieldType(t) =>
+                write!(f, "Gff field type <{}> is not a valid type.", t),
+            GffError::InvalidStructIndex(i) =>
+                write!(f, "Gff struct index <{}> is out of bounds.", i),
+            GffError::InvalidFieldIndex(i) =>
+                write!(f, "Gff field index <{}> is out of bounds.", i),
+            GffError::InvalidLabelIndex(i) =>
+                write!(f, "Gff label index <{}> is out of bounds.", i),
+            GffError::InvalidDataOffset(o) =>
+                write!(f, "Gff data offset <{}> is out of bounds.", o),
+            GffError::StructNestedTooDeep =>
+                write!(f, "Gff structs are nested deeper than {} levels.", GFF_MAX_DEPTH),
+            GffError::StructReused(i) =>
+                write!(f, "Gff struct index <{}> is used more than once.", i),
+            GffError::LabelTooLong(label) =>
+                write!(f, "Gff label <{}> needs to be 16 chars or less.", label),
+            GffError::FileTypeTooLong(file_type) =>
+                write!(f, "Gff file type <{}> needs to be 4 chars or less.", file_type),
+        }
+    }
+}
+
+impl Error for GffError {}
+
+/// The tables of a gff file as they are stored on disk.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GffTables {
+    pub structs: Vec<GffStructEntry>,
+    pub fields: Vec<GffFieldEntry>,
+    pub labels: Vec<String>,
+    pub field_data: Vec<u8>,
+    pub field_indices: Vec<u8>,
+    pub list_indices: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GffHeader {
+    pub file_type: String,
+    pub version: Version,
+    pub struct_offset: u32,
+    pub struct_count: u32,
+    pub field_offset: u32,
+    pub field_count: u32,
+    pub label_offset: u32,
+    pub label_count: u32,
+    pub field_data_offset: u32,
+    pub field_data_count: u32,
+    pub field_indices_offset: u32,
+    pub field_indices_count: u32,
+    pub list_indices_offset: u32,
+    pub list_indices_count: u32,
+}
+
+impl StaticByteSize for GffHeader {
+    const BYTE_SIZE: usize = 56;
+}
+
+impl SerializeToBytes for GffHeader {
+    fn serialize_to<F: Write>(self, writer: &mut F)
+        -> Result<(), MyError>
+    {
+        writer.write_all(self.file_type.as_bytes())?;
+        writer.write_all(self.version.as_str_ref().as_bytes())?;
+        writer.write_all(&self.struct_offset.to_le_bytes())?;
+        writer.write_all(&self.struct_count.to_le_bytes())?;
+        writer.write_all(&self.field_offset.to_le_bytes())?;
+        writer.write_all(&self.field_count.to_le_bytes())?;
+        writer.write_all(&self.label_offset.to_le_bytes())?;
+        writer.write_all(&self.label_count.to_le_bytes())?;
+        writer.write_all(&self.field_data_offset.to_le_bytes())?;
+        writer.write_all(&self.field_data_count.to_le_bytes())?;
+        writer.write_all(&self.field_indices_offset.to_le_bytes())?;
+        writer.write_all(&self.field_indices_count.to_le_bytes())?;
+        writer.write_all(&self.list_indices_offset.to_le_bytes())?;
+        writer.write_all(&self.list_indices_count.to_le_bytes())?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GffStructEntry {
+    pub struct_type: u32,
+    pub data_or_offset: u32,
+    pub field_count: u32,
+}
+
+impl StaticByteSize for GffStructEntry {
+    const BYTE_SIZE: usize = 12;
+}
+
+impl SerializeToBytes for GffStructEntry {
+    fn serialize_to<F: Write>(self, writer: &mut F)
+        -> Result<(), MyError>
+    {
+        writer.write_all(&self.struct_type.to_le_bytes())?;
+        writer.write_all(&self.data_or_offset.to_le_bytes())?;
+        writer.write_all(&self.field_count.to_le_bytes())?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GffFieldEntry {
+    pub field_type: GffFieldType,
+    pub label_index: u32,
+    pub data_or_offset: u32,
+}
+
+impl StaticByteSize for GffFieldEntry {
+    const BYTE_SIZE: usize = 12;
+}
+
+impl SerializeToBytes for GffFieldEntry {
+    fn serialize_to<F: Write>(self, writer: &mut F)
+        -> Result<(), MyError>
+    {
+        writer.write_all(&(self.field_type as u32).to_le_bytes())?;
+        writer.write_all(&self.label_index.to_le_bytes())?;
+        writer.write_all(&self.data_or_offset.to_le_bytes())?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GffFieldType {
+    Byte = 0,
+    Char = 1,
+    Word = 2,
+    Short = 3,
+    Dword = 4,
+    Int = 5,
+    Dword64 = 6,
+    Int64 = 7,
+    Float = 8,
+    Double = 9,
+    CExoString = 10,
+    ResRef = 11,
+    CExoLocString = 12,
+    Void = 13,
+    Struct = 14,
+    List = 15,
+}
+
+impl GffFieldType {
+    /// Complex fields store their value in the field data block
+    /// instead of the field entry itself.
+    pub fn is_complex(self)
+        -> bool
+    {
+        matches!(
+            self,
+            GffFieldType::Dword64
+            | GffFieldType::Int64
+            | GffFieldType::Double
+            | GffFieldType::CExoString
+            | GffFieldType::ResRef
+            | GffFieldType::CExoLocString
+            | GffFieldType::Void
+        )
+    }
+}
+
+impl TryFrom<u32> for GffFieldType {
+    type Error = GffError;
+
+    fn try_from(i: u32)
+        -> Result<Self, Self::Error>
+    {
+        let field_type = match i {
+            0 => GffFieldType::Byte,
+            1 => GffFieldType::Char,
+            2 => GffFieldType::Word,
+            3 => GffFieldType::Short,
+            4 => GffFieldType::Dword,
+            5 => GffFieldType::Int,
+            6 => GffFieldType::Dword64,
+            7 => GffFieldType::Int64,
+            8 => GffFieldType::Float,
+            9 => GffFieldType::Double,
+            10 => GffFieldType::CExoString,
+            11 => GffFieldType::ResRef,
+            12 => GffFieldType::CExoLocString,
+            13 => GffFieldType::Void,
+            14 => GffFieldType::Struct,
+            15 => GffFieldType::List,
+            _ => return Err(GffError::InvalidFieldType(i)),
+        };
+
+        Ok(field_type)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CExoLocSubString {
+    pub string_id: u32,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CExoLocString {
+    pub string_ref: Option<u32>,
+    pub strings: Vec<CExoLocSubString>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GffValue {
+    Byte(u8),
+    Char(i8),
+    Word(u16),
+    Short(i16),
+    Dword(u32),
+    Int(i32),
+    Dword64(u64),
+    Int64(i64),
+    Float(f32),
+    Double(f64),
+    CExoString(String),
+    ResRef(ResRef),
+    CExoLocString(CExoLocString),
+    Void(Vec<u8>),
+    Struct(GffStruct),
+    List(Vec<GffStruct>),
+}
+
+impl GffValue {
+    pub fn field_type(&self)
+        -> GffFieldType
+    {
+        match self {
+            GffValue::Byte(_) => GffFieldType::Byte,
+            GffValue::Char(_) => GffFieldType::Char,
+            GffValue::Word(_) => GffFieldType::Word,
+            GffValue::Short(_) => GffFieldType::Short,
+            GffValue::Dword(_) => GffFieldType::Dword,
+            GffValue::Int(_) => GffFieldType::Int,
+            GffValue::Dword64(_) => GffFieldType::Dword64,
+            GffValue::Int64(_) => GffFieldType::Int64,
+            GffValue::Float(_) => GffFieldType::Float,
+            GffValue::Double(_) => GffFieldType::Double,
+            GffValue::CExoString(_) => GffFieldType::CExoString,
+            GffValue::ResRef(_) => GffFieldType::ResRef,
+            GffValue::CExoLocString(_) => GffFieldType::CExoLocString,
+            GffValue::Void(_) => GffFieldType::Void,
+            GffValue::Struct(_) => GffFieldType::Struct,
+            GffValue::List(_) => GffFieldType::List,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GffField {
+    pub label: String,
+    pub value: GffValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GffStruct {
+    pub struct_type: u32,
+    pub fields: Vec<GffField>,
+}
+
+impl GffStruct {
+    pub fn new(struct_type: u32)
+        -> Self
+    {
+        GffStruct {
+            struct_type,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn add_field(&mut self, label: &str, value: GffValue)
+        -> &mut Self
+    {
+        self.fields.push(GffField {
+            label: label.to_owned(),
+            value,
+        });
+        self
+    }
+
+    pub fn get(&self, label: &str)
+        -> Option<&GffValue>
+    {
+        self.fields
+            .iter()
+            .find(|f| f.label == label)
+            .map(|f| &f.value)
+    }
+
+    pub fn get_mut(&mut self, label: &str)
+        -> Option<&mut GffValue>
+    {
+        self.fields
+            .iter_mut()
+            .find(|f| f.label == label)
+            .map(|f| &mut f.value)
+    }
+}
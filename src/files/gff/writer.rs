@@ -0,0 +1,544 @@
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::collections::VecDeque;
+use std::borrow::Cow;
+
+use encoding_rs::WINDOWS_1252;
+
+use crate::types::{
+    Version,
+    NULL_U32,
+    StaticByteSize,
+    SerializeToBytes,
+    Error as MyError,
+};
+
+use super::types::{
+    GffFile,
+    GffError,
+    GffHeader,
+    GffTables,
+    GffStructEntry,
+    GffFieldEntry,
+    GffStruct,
+    GffValue,
+    GFF_LABEL_LENGTH,
+};
+
+use super::parser::read_root;
+
+impl GffTables {
+    fn label_index(&mut self, label: &str)
+        -> Result<u32, GffError>
+    {
+        if label.len() > GFF_LABEL_LENGTH {
+            return Err(GffError::LabelTooLong(label.to_owned()));
+        }
+
+        let index = match self.labels.iter().position(|l| l == label) {
+            Some(i) => i,
+            None => {
+                self.labels.push(label.to_owned());
+                self.labels.len() - 1
+            }
+        };
+
+        Ok(index as u32)
+    }
+
+    fn reserve_struct<'a>(&mut self, gff_struct: &'a GffStruct, queue: &mut VecDeque<(usize, &'a GffStruct)>)
+        -> u32
+    {
+        let index = self.structs.len();
+
+        self.structs.push(GffStructEntry {
+            struct_type: gff_struct.struct_type,
+            data_or_offset: 0,
+            field_count: gff_struct.fields.len() as u32,
+        });
+        queue.push_back((index, gff_struct));
+
+        index as u32
+    }
+
+    fn write_field_data(&mut self, value: &GffValue)
+        -> Result<u32, MyError>
+    {
+        let offset = self.field_data.len() as u32;
+        let data = &mut self.field_data;
+
+        match value {
+            GffValue::Dword64(v) => data.extend_from_slice(&v.to_le_bytes()),
+            GffValue::Int64(v) => data.extend_from_slice(&v.to_le_bytes()),
+            GffValue::Double(v) => data.extend_from_slice(&v.to_le_bytes()),
+            GffValue::CExoString(s) => {
+                let bytes = encode_string(s)?;
+                data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                data.extend_from_slice(&bytes);
+            },
+            GffValue::ResRef(r) => {
+                data.push(r.len() as u8);
+                data.extend_from_slice(r.as_bytes());
+            },
+            GffValue::CExoLocString(s) => {
+                let texts = s.strings
+                    .iter()
+                    .map(|sub| encode_string(&sub.text))
+                    .collect::<Result<Vec<_>, MyError>>()?;
+                let total_size = texts.iter().fold(8, |size, t| size + 8 + t.len());
+
+                data.extend_from_slice(&(total_size as u32).to_le_bytes());
+                data.extend_from_slice(&s.string_ref.unwrap_or(NULL_U32).to_le_bytes());
+                data.extend_from_slice(&(s.strings.len() as u32).to_le_bytes());
+
+                for (sub, text) in s.strings.iter().zip(texts.iter()) {
+                    data.extend_from_slice(&sub.string_id.to_le_bytes());
+                    data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                    data.extend_from_slice(text);
+                }
+            },
+            GffValue::Void(v) => {
+                data.extend_from_slice(&(v.len() as u32).to_le_bytes());
+                data.extend_from_slice(v);
+            },
+            _ => unreachable!("Only complex gff values are written to the field data block."),
+        }
+
+        Ok(offset)
+    }
+}
+
+/// Gff strings are stored as Windows-1252.
+fn encode_string(s: &str)
+    -> Result<Cow<'_, [u8]>, MyError>
+{
+    match WINDOWS_1252.encode(s) {
+        (_, _, true) => Err(MyError::UnencodableString(s.to_owned(), WINDOWS_1252.name())),
+        (bytes, _, false) => Ok(bytes),
+    }
+}
+
+/// Structs are laid out breadth first: every field of a struct is written
+/// before any of its child structs. Labels and field data are written in
+/// the order they are first used.
+fn build_tables(root: &GffStruct)
+    -> Result<GffTables, MyError>
+{
+    let mut tables = GffTables::default();
+    let mut queue = VecDeque::new();
+
+    tables.reserve_struct(root, &mut queue);
+
+    while let Some((index, gff_struct)) = queue.pop_front() {
+        let first_field = tables.fields.len() as u32;
+
+        for field in gff_struct.fields.iter() {
+            let label_index = tables.label_index(&field.label)?;
+            let field_type = field.value.field_type();
+
+            let data_or_offset = match &field.value {
+                GffValue::Byte(v) => *v as u32,
+                GffValue::Char(v) => *v as u8 as u32,
+                GffValue::Word(v) => *v as u32,
+                GffValue::Short(v) => *v as u16 as u32,
+                GffValue::Dword(v) => *v,
+                GffValue::Int(v) => *v as u32,
+                GffValue::Float(v) => v.to_bits(),
+                GffValue::Struct(s) => tables.reserve_struct(s, &mut queue),
+                GffValue::List(structs) => {
+                    let offset = tables.list_indices.len() as u32;
+                    let count = structs.len() as u32;
+                    tables.list_indices.extend_from_slice(&count.to_le_bytes());
+
+                    for s in structs.iter() {
+                        let struct_index = tables.reserve_struct(s, &mut queue);
+                        tables.list_indices.extend_from_slice(&struct_index.to_le_bytes());
+                    }
+
+                    offset
+                },
+                value => tables.write_field_data(value)?,
+            };
+
+            tables.fields.push(GffFieldEntry {
+                field_type,
+                label_index,
+                data_or_offset,
+            });
+        }
+
+        let data_or_offset = match gff_struct.fields.len() {
+            1 => first_field,
+            count => {
+                let offset = tables.field_indices.len() as u32;
+
+                for i in first_field..first_field + count as u32 {
+                    tables.field_indices.extend_from_slice(&i.to_le_bytes());
+                }
+
+                offset
+            },
+        };
+
+        tables.structs[index].data_or_offset = data_or_offset;
+    }
+
+    Ok(tables)
+}
+
+pub fn write<W: Write>(gff_file: &GffFile, writer: &mut W)
+    -> Result<(), MyError>
+{
+    if gff_file.file_type.len() > 4 {
+        Err(GffError::FileTypeTooLong(gff_file.file_type.clone()))?;
+    }
+
+    // A parsed file that wasn't changed keeps its original table order and
+    // indices, so it is written back byte for byte.
+    let tables = match &gff_file.source {
+        Some(source) if read_root(source).ok().as_ref() == Some(&gff_file.root) => source.clone(),
+        _ => build_tables(&gff_file.root)?,
+    };
+
+    let struct_offset = GffHeader::BYTE_SIZE;
+    let field_offset = struct_offset + GffStructEntry::BYTE_SIZE * tables.structs.len();
+    let label_offset = field_offset + GffFieldEntry::BYTE_SIZE * tables.fields.len();
+    let field_data_offset = label_offset + GFF_LABEL_LENGTH * tables.labels.len();
+    let field_indices_offset = field_data_offset + tables.field_data.len();
+    let list_indices_offset = field_indices_offset + tables.field_indices.len();
+
+    let header = GffHeader {
+        file_type: format!("{:<4}", gff_file.file_type),
+        version: Version::V3_2,
+        struct_offset: struct_offset as u32,
+        struct_count: tables.structs.len() as u32,
+        field_offset: field_offset as u32,
+        field_count: tables.fields.len() as u32,
+        label_offset: label_offset as u32,
+        label_count: tables.labels.len() as u32,
+        field_data_offset: field_data_offset as u32,
+        field_data_count: tables.field_data.len() as u32,
+        field_indices_offset: field_indices_offset as u32,
+        field_indices_count: tables.field_indices.len() as u32,
+        list_indices_offset: list_indices_offset as u32,
+        list_indices_count: tables.list_indices.len() as u32,
+    };
+
+    let mut writer = BufWriter::new(writer);
+
+    header.serialize_to(&mut writer)?;
+    tables.structs.serialize_to(&mut writer)?;
+    tables.fields.serialize_to(&mut writer)?;
+
+    for label in tables.labels.iter() {
+        let mut bytes = label.clone().into_bytes();
+        bytes.resize(GFF_LABEL_LENGTH, 0);
+        writer.write_all(&bytes)?;
+    }
+
+    writer.write_all(&tables.field_data)?;
+    writer.write_all(&tables.field_indices)?;
+    writer.write_all(&tables.list_indices)?;
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::io::Cursor;
+    use std::convert::TryFrom;
+    use crate::types::{ResRef, FileType};
+    use super::super::types::{
+        CExoLocString,
+        CExoLocSubString,
+    };
+
+    fn sample_gff() -> GffFile
+    {
+        let mut property = GffStruct::new(0);
+        property
+            .add_field("PropertyName", GffValue::Word(15))
+            .add_field("CostValue", GffValue::Word(3));
+
+        let mut gff = GffFile::new("UTI ");
+        gff.root
+            .add_field("TemplateResRef", GffValue::ResRef(ResRef::try_from("nw_it_gem001").unwrap()))
+            .add_field("LocalizedName", GffValue::CExoLocString(CExoLocString {
+                string_ref: None,
+                strings: vec![CExoLocSubString { string_id: 0, text: String::from("Greenstone") }],
+            }))
+            .add_field("Tag", GffValue::CExoString(String::from("NW_IT_GEM001")))
+            .add_field("Cost", GffValue::Dword(20))
+            .add_field("Charges", GffValue::Byte(0))
+            .add_field("Plot", GffValue::Char(-1))
+            .add_field("Stolen", GffValue::Short(-3))
+            .add_field("Weight", GffValue::Float(0.1))
+            .add_field("BigValue", GffValue::Dword64(u64::MAX))
+            .add_field("Signed64", GffValue::Int64(-42))
+            .add_field("Precise", GffValue::Double(3.25))
+            .add_field("Blob", GffValue::Void(vec![1, 2, 3]))
+            .add_field("Single", GffValue::Struct(GffStruct::new(7)))
+            .add_field("PropertiesList", GffValue::List(vec![property.clone(), property]))
+            .add_field("Empty", GffValue::List(Vec::new()));
+
+        gff
+    }
+
+    #[test]
+    fn gff_round_trip() {
+        let gff = sample_gff();
+        let mut c = Cursor::new(Vec::new());
+
+        gff.write(&mut c).unwrap();
+
+        let bytes = c.into_inner();
+        let parsed = GffFile::parse_from(&mut Cursor::new(&bytes)).unwrap();
+
+        assert_eq!(gff.root, parsed.root);
+        assert_eq!("UTI ", parsed.file_type);
+
+        let mut c = Cursor::new(Vec::new());
+        parsed.write(&mut c).unwrap();
+
+        assert_eq!(bytes, c.into_inner());
+    }
+
+    #[test]
+    fn gff_labels_are_shared() {
+        let gff = sample_gff();
+        let tables = build_tables(&gff.root).unwrap();
+
+        assert_eq!(17, tables.labels.len());
+        assert_eq!(4, tables.structs.len());
+    }
+
+    #[test]
+    fn gff_label_too_long() {
+        let mut gff = GffFile::new("UTI");
+        gff.root.add_field("ThisLabelIsFarTooLong", GffValue::Byte(0));
+
+        let e = gff.write(&mut Cursor::new(Vec::new())).unwrap_err();
+
+        match e {
+            MyError::GffError(GffError::LabelTooLong(_)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn gff_strings_round_trip_as_windows_1252() {
+        let mut gff = GffFile::new("UTI");
+        gff.root
+            .add_field("Tag", GffValue::CExoString(String::from("Caf\u{e9} \u{2013} \u{201c}na\u{ef}ve\u{201d}")))
+            .add_field("Name", GffValue::CExoLocString(CExoLocString {
+                string_ref: Some(42),
+                strings: vec![CExoLocSubString { string_id: 2, text: String::from("\u{c9}p\u{e9}e \u{2022} \u{20ac}5") }],
+            }));
+
+        let mut c = Cursor::new(Vec::new());
+        gff.write(&mut c).unwrap();
+        let bytes = c.into_inner();
+
+        assert!(bytes.windows(4).any(|w| w == b"Caf\xe9"));
+        assert!(bytes.windows(3).any(|w| w == b" \x96 "));
+
+        let parsed = GffFile::parse_from(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(gff.root, parsed.root);
+
+        let mut c = Cursor::new(Vec::new());
+        parsed.write(&mut c).unwrap();
+        assert_eq!(bytes, c.into_inner());
+    }
+
+    #[test]
+    fn gff_unencodable_string() {
+        let mut gff = GffFile::new("UTI");
+        gff.root.add_field("Tag", GffValue::CExoString(String::from("\u{3042}")));
+
+        match gff.write(&mut Cursor::new(Vec::new())).unwrap_err() {
+            MyError::UnencodableString(s, _) => assert_eq!("\u{3042}", s),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn gff_cycle_is_rejected() {
+        let mut gff = GffFile::new("UTI");
+        gff.root.add_field("Child", GffValue::Struct(GffStruct::new(1)));
+
+        let mut c = Cursor::new(Vec::new());
+        gff.write(&mut c).unwrap();
+        let mut bytes = c.into_inner();
+
+        // point the child field back at the root struct
+        let field_offset = u32::from_le_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]) as usize;
+        bytes[field_offset + 8..field_offset + 12].copy_from_slice(&0u32.to_le_bytes());
+
+        match GffFile::parse_from(&mut Cursor::new(&bytes)).unwrap_err() {
+            MyError::GffError(GffError::StructReused(0)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn gff_shared_struct_is_rejected() {
+        let mut gff = GffFile::new("UTI");
+        gff.root
+            .add_field("First", GffValue::Struct(GffStruct::new(1)))
+            .add_field("Second", GffValue::Struct(GffStruct::new(2)));
+
+        let mut c = Cursor::new(Vec::new());
+        gff.write(&mut c).unwrap();
+        let mut bytes = c.into_inner();
+
+        // point the second field at the first child struct
+        let field_offset = u32::from_le_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]) as usize;
+        bytes[field_offset + 20..field_offset + 24].copy_from_slice(&1u32.to_le_bytes());
+
+        match GffFile::parse_from(&mut Cursor::new(&bytes)).unwrap_err() {
+            MyError::GffError(GffError::StructReused(1)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn gff_table_past_end_is_rejected() {
+        let mut c = Cursor::new(Vec::new());
+        sample_gff().write(&mut c).unwrap();
+        let mut bytes = c.into_inner();
+
+        // field data count
+        bytes[36..40].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        match GffFile::parse_from(&mut Cursor::new(&bytes)).unwrap_err() {
+            MyError::OffsetOutOfBounds(_) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn gff_unsupported_version() {
+        let mut c = Cursor::new(Vec::new());
+        sample_gff().write(&mut c).unwrap();
+        let mut bytes = c.into_inner();
+
+        bytes[4..8].copy_from_slice(b"V3.3");
+
+        match GffFile::parse_from(&mut Cursor::new(&bytes)).unwrap_err() {
+            MyError::UnsupportedVersion(FileType::Gff, found) => assert_eq!("V3.3", found),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn gff_string_size_past_end_is_rejected() {
+        let mut gff = GffFile::new("UTI");
+        gff.root.add_field("Tag", GffValue::CExoString(String::from("abc")));
+
+        let mut c = Cursor::new(Vec::new());
+        gff.write(&mut c).unwrap();
+        let mut bytes = c.into_inner();
+
+        // size of the string at the start of the field data
+        let data_offset = u32::from_le_bytes([bytes[32], bytes[33], bytes[34], bytes[35]]) as usize;
+        bytes[data_offset..data_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        match GffFile::parse_from(&mut Cursor::new(&bytes)).unwrap_err() {
+            MyError::GffError(GffError::InvalidDataOffset(0)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    /// Laid out like the toolset does it: structs depth first, fields in
+    /// the order they were added, labels and field data in no particular
+    /// order.
+    fn toolset_gff()
+        -> Vec<u8>
+    {
+        let structs: [[u32; 3]; 4] = [[u32::MAX, 8, 4], [1, 0, 2], [3, 3, 1], [2, 6, 1]];
+        let fields: [[u32; 3]; 8] = [
+            [10, 7, 2], [14, 6, 1], [14, 4, 2], [0, 2, 5],
+            [4, 3, 9], [15, 5, 0], [11, 1, 0], [4, 0, 20],
+        ];
+        let labels = ["Cost", "Y", "X", "N", "C", "B", "A", "Tag"];
+        let field_data = b"\x01r\x03\x00\x00\x00abc";
+        let field_indices: [u32; 6] = [2, 4, 0, 1, 5, 7];
+        let list_indices: [u32; 2] = [1, 3];
+
+        let field_offset = 56 + 12 * structs.len();
+        let label_offset = field_offset + 12 * fields.len();
+        let field_data_offset = label_offset + 16 * labels.len();
+        let field_indices_offset = field_data_offset + field_data.len();
+        let list_indices_offset = field_indices_offset + 4 * field_indices.len();
+
+        let mut bytes = b"UTI V3.2".to_vec();
+        let header = [
+            56, structs.len(), field_offset, fields.len(), label_offset, labels.len(),
+            field_data_offset, field_data.len(), field_indices_offset, 4 * field_indices.len(),
+            list_indices_offset, 4 * list_indices.len(),
+        ];
+        let words = header.iter()
+            .map(|&v| v as u32)
+            .chain(structs.iter().flatten().copied())
+            .chain(fields.iter().flatten().copied());
+
+        for word in words {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        for label in labels.iter() {
+            let mut label = label.as_bytes().to_vec();
+            label.resize(16, 0);
+            bytes.extend_from_slice(&label);
+        }
+        bytes.extend_from_slice(field_data);
+        for word in field_indices.iter().chain(list_indices.iter()) {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn gff_toolset_file_round_trips_byte_for_byte() {
+        let bytes = toolset_gff();
+        let mut gff = GffFile::parse_from(&mut Cursor::new(&bytes)).unwrap();
+
+        let mut nested = GffStruct::new(1);
+        nested
+            .add_field("C", GffValue::Struct({
+                let mut s = GffStruct::new(3);
+                s.add_field("X", GffValue::Byte(5));
+                s
+            }))
+            .add_field("N", GffValue::Dword(9));
+        let mut listed = GffStruct::new(2);
+        listed.add_field("Y", GffValue::ResRef(ResRef::try_from("r").unwrap()));
+
+        let mut root = GffStruct::new(u32::MAX);
+        root
+            .add_field("Tag", GffValue::CExoString(String::from("abc")))
+            .add_field("A", GffValue::Struct(nested))
+            .add_field("B", GffValue::List(vec![listed]))
+            .add_field("Cost", GffValue::Dword(20));
+
+        assert_eq!(root, gff.root);
+
+        let mut c = Cursor::new(Vec::new());
+        gff.write(&mut c).unwrap();
+        assert_eq!(bytes, c.into_inner());
+
+        *gff.root.get_mut("Cost").unwrap() = GffValue::Dword(25);
+        *root.get_mut("Cost").unwrap() = GffValue::Dword(25);
+
+        let mut c = Cursor::new(Vec::new());
+        gff.write(&mut c).unwrap();
+        let changed = c.into_inner();
+
+        assert_ne!(bytes, changed);
+        assert_eq!(root, GffFile::parse_from(&mut Cursor::new(&changed)).unwrap().root);
+    }
+}
@@ -1,5 +1,16 @@
 use crate::helpers::conversion::*;
+use crate::helpers::date;
 use crate::types::*;
+use crate::types::{
+    Error as MyError,
+};
+
+use std::io::{Write, BufWriter};
+use std::convert::TryFrom;
+
+const BIF_INDEX_SHIFT: u32 = 20;
+pub(crate) const MAX_RESOURCE_INDEX: usize = 0xFFFFF;
+pub(crate) const MAX_BIF_INDEX: usize = 0xFFF;
 
 #[derive(Debug)]
 pub struct KeyHeader {
@@ -39,13 +50,13 @@ pub struct KeyFile {
 #[derive(Debug)]
 pub struct KeyFile2(pub Vec<BifFile>);
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BifFile {
     pub name: String,
     pub resources: Vec<BifResource>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BifResource {
     pub resource_index: usize,
     pub resource_type: ResourceType,
@@ -53,20 +64,20 @@ pub struct BifResource {
 }
 
 
-pub fn parse(bytes: Vec<u8>) -> std::io::Result<KeyFile2> {
-    let header = parse_key_header(&bytes);
+pub fn parse(bytes: Vec<u8>) -> Result<KeyFile2, MyError> {
+    let header = parse_key_header(&bytes)?;
 
     // dbg!("{:?}", &header);
 
-    let entries = parse_table_entries(&bytes, &header);
+    let entries = parse_table_entries(&bytes, &header)?;
 
     // dbg!("{:?}", &entries);
 
-    let file_names = parse_file_names(&bytes, &entries);
+    let file_names = parse_file_names(&bytes, &entries)?;
     
     // dbg!("{:?}", &file_names);
     
-    let key_table = parse_key_entries(&bytes, &header);
+    let key_table = parse_key_entries(&bytes, &header)?;
 
     let bif_files = file_names
         .into_iter()
@@ -74,96 +85,422 @@ pub fn parse(bytes: Vec<u8>) -> std::io::Result<KeyFile2> {
         .map(|(i, name)| {
             let resources = key_table
                 .iter()
-                .filter(|k| k.id >> 20 == i as u32)
+                .filter(|k| k.id >> BIF_INDEX_SHIFT == i as u32)
                 .map(|k| {
                     let resource_type = ResourceType::from(k.resource_type);                
                     BifResource {
                         name: k.name.trim_end_matches("\u{0}").to_string(),
-                        resource_index: 0xFFFFF & k.id as usize,
+                        resource_index: MAX_RESOURCE_INDEX & k.id as usize,
                         resource_type: resource_type,
                     }
                 })
                 .collect();
 
-            Ok(BifFile {
+            BifFile {
                 name: name,
                 resources: resources,
-            })
+            }
         })
-        .collect::<Result<Vec<BifFile>, std::io::Error>>()?;
+        .collect::<Vec<BifFile>>();
 
     Ok(KeyFile2(bif_files))
 }
 
-fn parse_key_header(bytes: &Vec<u8>) -> KeyHeader {
-    let _file_type = String::from_utf8_lossy(&bytes[0..4]);
-    let _file_version = String::from_utf8_lossy(&bytes[4..8]);
+fn parse_key_header(bytes: &[u8]) -> Result<KeyHeader, MyError> {
+    let file_type = String::from_utf8_lossy(checked_slice(bytes, 0, 4)?);
+    let file_version = String::from_utf8_lossy(checked_slice(bytes, 4, 4)?);
 
-    let header = KeyHeader {
+    if FileType::from(file_type.as_ref()) != FileType::Key {
+        return Err(MyError::BadMagic(FileType::Key, file_type.into_owned()));
+    }
+
+    if Version::from(file_version.as_ref()) != Version::V1 {
+        return Err(MyError::UnsupportedVersion(FileType::Key, file_version.into_owned()));
+    }
+
+    Ok(KeyHeader {
         version: Version::V1,
         file_type: FileType::Key,
-        bif_count: u32_from_bytes(&bytes[8..12]),
-        key_count: u32_from_bytes(&bytes[12..16]),
-        offset_file_table: u32_from_bytes(&bytes[16..20]),
-        offset_key_table: u32_from_bytes(&bytes[20..24]),
-        build_years: u32_from_bytes(&bytes[24..28]),
-        build_days: u32_from_bytes(&bytes[28..32]),
-    };
-
-    header
+        bif_count: checked_u32_from_bytes(bytes, 8)?,
+        key_count: checked_u32_from_bytes(bytes, 12)?,
+        offset_file_table: checked_u32_from_bytes(bytes, 16)?,
+        offset_key_table: checked_u32_from_bytes(bytes, 20)?,
+        build_years: checked_u32_from_bytes(bytes, 24)?,
+        build_days: checked_u32_from_bytes(bytes, 28)?,
+    })
 }
 
-fn parse_file_names(bytes: &Vec<u8>, entries: &Vec<TableEntry>) -> Vec<String>
+fn parse_file_names(bytes: &[u8], entries: &[TableEntry]) -> Result<Vec<String>, MyError>
 {
     entries
-        .into_iter()
+        .iter()
         .map(|entry| {
             let o = entry.file_name_offset as usize;
-            String::from_utf8_lossy(
-                &bytes[o..o + entry.file_name_size as usize]
-            ).to_string()
+            let name = checked_slice(bytes, o, entry.file_name_size as usize)?;
+
+            Ok(String::from_utf8_lossy(name).trim_end_matches('\u{0}').to_string())
         })
         .collect()
 }
 
-fn parse_table_entries(bytes: &Vec<u8>, header: &KeyHeader) -> Vec<TableEntry> {
-    let offset_table_entries = 64;
+fn parse_table_entries(bytes: &[u8], header: &KeyHeader) -> Result<Vec<TableEntry>, MyError> {
+    let offset_table_entries = header.offset_file_table as usize;
 
     (0..header.bif_count as usize)
-        .into_iter()
         .map(|i| {
-            let o = i * 12 + offset_table_entries;
+            let o = i * TableEntry::BYTE_SIZE + offset_table_entries;
 
-            TableEntry {
-                file_size: u32_from_bytes(&bytes[o..o+4]),
-                file_name_offset: u32_from_bytes(&bytes[o+4..o+8]),
-                file_name_size: u16_from_bytes(&bytes[o+8..o+10]),
-                drives: u16_from_bytes(&bytes[o+10..o+12]),
-            }
+            Ok(TableEntry {
+                file_size: checked_u32_from_bytes(bytes, o)?,
+                file_name_offset: checked_u32_from_bytes(bytes, o + 4)?,
+                file_name_size: checked_u16_from_bytes(bytes, o + 8)?,
+                drives: checked_u16_from_bytes(bytes, o + 10)?,
+            })
         })
         .collect()
 }
 
 
-fn parse_key_entries(bytes: &Vec<u8>, header: &KeyHeader) -> Vec<KeyEntry> {
-    let offset_key_table = header.offset_key_table as u64;
-    const KEY_TABLE_SIZE: usize = 22;
+fn parse_key_entries(bytes: &[u8], header: &KeyHeader) -> Result<Vec<KeyEntry>, MyError> {
+    let offset_key_table = header.offset_key_table as usize;
 
     (0..header.key_count as usize)
-        .into_iter()
         .map(|i| {
-            let o = offset_key_table as usize + KEY_TABLE_SIZE * i;
+            let o = offset_key_table + KeyEntry::BYTE_SIZE * i;
 
-            KeyEntry {
-                name: String::from_utf8_lossy(&bytes[o..o+16]).into_owned(),
-                resource_type: u16_from_bytes(&bytes[o+16..o+18]),
-                id: u32_from_bytes(&bytes[o+18..o+22]),
-            }
+            Ok(KeyEntry {
+                name: String::from_utf8_lossy(checked_slice(bytes, o, 16)?).into_owned(),
+                resource_type: checked_u16_from_bytes(bytes, o + 16)?,
+                id: checked_u32_from_bytes(bytes, o + 18)?,
+            })
         })
         .collect()
 }
 
+impl StaticByteSize for KeyHeader {
+    const BYTE_SIZE: usize = 64;
+}
+
+impl SerializeToBytes for KeyHeader {
+    fn serialize_to<F: Write>(self, writer: &mut F)
+        -> Result<(), MyError>
+    {
+        writer.write_all(self.file_type.as_str_ref().as_bytes())?;
+        writer.write_all(self.version.as_short_str_ref().as_bytes())?;
+        writer.write_all(&self.bif_count.to_le_bytes())?;
+        writer.write_all(&self.key_count.to_le_bytes())?;
+        writer.write_all(&self.offset_file_table.to_le_bytes())?;
+        writer.write_all(&self.offset_key_table.to_le_bytes())?;
+        writer.write_all(&self.build_years.to_le_bytes())?;
+        writer.write_all(&self.build_days.to_le_bytes())?;
+        writer.write_all(&[0; 32])?;
+
+        Ok(())
+    }
+}
+
+impl StaticByteSize for TableEntry {
+    const BYTE_SIZE: usize = 12;
+}
+
+impl SerializeToBytes for TableEntry {
+    fn serialize_to<F: Write>(self, writer: &mut F)
+        -> Result<(), MyError>
+    {
+        writer.write_all(&self.file_size.to_le_bytes())?;
+        writer.write_all(&self.file_name_offset.to_le_bytes())?;
+        writer.write_all(&self.file_name_size.to_le_bytes())?;
+        writer.write_all(&self.drives.to_le_bytes())?;
+
+        Ok(())
+    }
+}
+
+impl StaticByteSize for KeyEntry {
+    const BYTE_SIZE: usize = 22;
+}
+
+impl SerializeToBytes for KeyEntry {
+    fn serialize_to<F: Write>(self, writer: &mut F)
+        -> Result<(), MyError>
+    {
+        let name = ResRef::try_from(self.name)?;
+
+        writer.write_all(&name.serialize()?)?;
+        writer.write_all(&self.resource_type.to_le_bytes())?;
+        writer.write_all(&self.id.to_le_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// Bifs that live in the game's data directory.
+const DRIVES_DATA: u16 = 1;
+
+pub struct KeyBuilder {
+    bifs: Vec<(BifFile, u32)>,
+}
+
+impl KeyBuilder {
+    pub fn new() -> Self
+    {
+        KeyBuilder {
+            bifs: Vec::new()
+        }
+    }
+
+    /// Adds a bif with an unknown file size, which is written as 0.
+    pub fn add_bif(&mut self, bif: BifFile)
+        -> &mut Self
+    {
+        self.add_bif_with_size(bif, 0)
+    }
+
+    pub fn add_bif_with_size(&mut self, bif: BifFile, file_size: u32)
+        -> &mut Self
+    {
+        self.bifs.push((bif, file_size));
+        self
+    }
+
+    pub fn add_bifs(&mut self, bifs: Vec<BifFile>)
+        -> &mut Self
+    {
+        self.bifs.extend(bifs.into_iter().map(|bif| (bif, 0)));
+        self
+    }
+
+    pub fn write<W: Write>(self, writer: &mut W)
+        -> Result<(), MyError>
+    {
+        let bif_count = self.bifs.len();
+
+        if bif_count > MAX_BIF_INDEX + 1 {
+            return Err(MyError::BifIndexTooLarge(bif_count - 1));
+        }
+
+        let offset_file_table = KeyHeader::BYTE_SIZE;
+        let offset_file_names = offset_file_table + TableEntry::BYTE_SIZE * bif_count;
+
+        let file_names = self.bifs
+            .iter()
+            .map(|(bif, _)| {
+                let mut name = bif.name.trim_end_matches('\u{0}').to_owned();
+                name.push('\u{0}');
+                name
+            })
+            .collect::<Vec<String>>();
+
+        let table_entries = self.bifs
+            .iter()
+            .zip(file_names.iter())
+            .scan(offset_file_names, |state, ((_, file_size), name)| {
+                let entry = TableEntry {
+                    file_size: *file_size,
+                    file_name_offset: *state as u32,
+                    file_name_size: name.len() as u16,
+                    drives: DRIVES_DATA,
+                };
+
+                *state += name.len();
+
+                Some(entry)
+            })
+            .collect::<Vec<TableEntry>>();
+
+        let offset_key_table = offset_file_names + file_names
+            .iter()
+            .map(|name| name.len())
+            .sum::<usize>();
+
+        let key_entries = self.bifs
+            .into_iter()
+            .enumerate()
+            .flat_map(|(bif_index, (bif, _))| {
+                bif.resources
+                    .into_iter()
+                    .map(move |r| {
+                        if r.resource_index > MAX_RESOURCE_INDEX {
+                            return Err(MyError::BifResourceIndexTooLarge(r.resource_index));
+                        }
+
+                        Ok(KeyEntry {
+                            name: r.name,
+                            resource_type: r.resource_type.id(),
+                            id: (bif_index as u32) << BIF_INDEX_SHIFT | r.resource_index as u32,
+                        })
+                    })
+            })
+            .collect::<Result<Vec<KeyEntry>, MyError>>()?;
+
+        let header = KeyHeader {
+            version: Version::V1,
+            file_type: FileType::Key,
+            bif_count: bif_count as u32,
+            key_count: key_entries.len() as u32,
+            offset_file_table: offset_file_table as u32,
+            offset_key_table: offset_key_table as u32,
+            build_years: date::years_since_1990(),
+            build_days: date::days_since_jan_1(),
+        };
+
+        let mut writer = BufWriter::new(writer);
+
+        header.serialize_to(&mut writer)?;
+        table_entries.serialize_to(&mut writer)?;
+
+        for name in file_names {
+            writer.write_all(name.as_bytes())?;
+        }
+
+        key_entries.serialize_to(&mut writer)?;
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Default for KeyBuilder {
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
-mod tests {
+mod test {
+    use super::*;
+
+    fn sample_bifs() -> Vec<BifFile>
+    {
+        vec![
+            BifFile {
+                name: String::from("data\\2da.bif"),
+                resources: vec![
+                    BifResource {
+                        resource_index: 0,
+                        resource_type: ResourceType::x2da,
+                        name: String::from("appearance"),
+                    },
+                    BifResource {
+                        resource_index: 1,
+                        resource_type: ResourceType::x2da,
+                        name: String::from("baseitems"),
+                    },
+                ],
+            },
+            BifFile {
+                name: String::from("data\\sounds.bif"),
+                resources: vec![
+                    BifResource {
+                        resource_index: 0,
+                        resource_type: ResourceType::wav,
+                        name: String::from("as_an_crow1"),
+                    },
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn write_key_round_trip() {
+        let mut bytes = Vec::new();
+
+        let mut builder = KeyBuilder::new();
+        builder.add_bifs(sample_bifs());
+        builder.write(&mut bytes).unwrap();
+
+        let parsed = parse(bytes).unwrap();
+
+        assert_eq!(2, parsed.0.len());
+        assert_eq!("data\\2da.bif", parsed.0[0].name);
+        assert_eq!("baseitems", parsed.0[0].resources[1].name);
+        assert_eq!(1, parsed.0[0].resources[1].resource_index);
+        assert_eq!(ResourceType::wav, parsed.0[1].resources[0].resource_type);
+    }
+
+    #[test]
+    fn write_key_resource_ids() {
+        let mut bytes = Vec::new();
+
+        let mut builder = KeyBuilder::new();
+        builder.add_bifs(sample_bifs());
+        builder.write(&mut bytes).unwrap();
+
+        let header = parse_key_header(&bytes).unwrap();
+        let entries = parse_key_entries(&bytes, &header).unwrap();
+
+        assert_eq!(1, entries[1].id);
+        assert_eq!(1 << 20, entries[2].id);
+    }
+
+    #[test]
+    fn write_key_resource_index_too_large() {
+        let mut bifs = sample_bifs();
+        bifs[0].resources[0].resource_index = MAX_RESOURCE_INDEX + 1;
+
+        let mut builder = KeyBuilder::new();
+        builder.add_bifs(bifs);
+
+        match builder.write(&mut Vec::new()).unwrap_err() {
+            MyError::BifResourceIndexTooLarge(_) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn write_key_too_many_bifs() {
+        let bif = BifFile {
+            name: String::from("data\\empty.bif"),
+            resources: Vec::new(),
+        };
+
+        let mut builder = KeyBuilder::new();
+        builder.add_bifs(vec![bif; MAX_BIF_INDEX + 2]);
+
+        match builder.write(&mut Vec::new()).unwrap_err() {
+            MyError::BifIndexTooLarge(index) => assert_eq!(MAX_BIF_INDEX + 1, index),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_key_bad_magic() {
+        let mut bytes = Vec::new();
+        KeyBuilder::new().write(&mut bytes).unwrap();
+        bytes[0..4].copy_from_slice(b"ERF ");
+
+        match parse(bytes).unwrap_err() {
+            MyError::BadMagic(FileType::Key, found) => assert_eq!("ERF ", found),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_key_bad_version() {
+        let mut bytes = Vec::new();
+        KeyBuilder::new().write(&mut bytes).unwrap();
+        bytes[4..8].copy_from_slice(b"V9.9");
+
+        match parse(bytes).unwrap_err() {
+            MyError::UnsupportedVersion(FileType::Key, found) => assert_eq!("V9.9", found),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_key_truncated() {
+        let mut bytes = Vec::new();
+
+        let mut builder = KeyBuilder::new();
+        builder.add_bifs(sample_bifs());
+        builder.write(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 10);
 
+        match parse(bytes).unwrap_err() {
+            MyError::OffsetOutOfBounds(_) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
 }
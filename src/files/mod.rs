@@ -1,5 +1,6 @@
 pub mod bif;
 pub mod erf;
+pub mod gff;
 pub mod key;
 pub mod ssf;
 pub mod x2da;
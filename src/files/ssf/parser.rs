@@ -58,7 +58,7 @@ fn parse_entries(bytes: &Vec<u8>, entry_offsets: &Vec<usize>)
         .map(|offset| {
             let str_ref = match u32_from_bytes(&bytes[offset+16..offset+20]) {
                 NULL_U32 => None,
-                t => Some(t),
+                t => Some(StrRef(t)),
             };
 
             SsfEntry {
@@ -5,10 +5,15 @@ use super::parser;
 use super::types::{
     SsfHeader,
     SsfEntry,
+    SsfSlot,
 };
 
+use super::writer::SsfBuilder;
+
 use crate::types::{
     Error as MyError,
+    ResRef,
+    StrRef,
 };
 
 #[derive(Debug)]
@@ -18,6 +23,16 @@ pub struct SsfFile {
 }
 
 impl SsfFile {
+    /// A soundset with every slot empty.
+    pub fn new()
+        -> Self
+    {
+        SsfFile {
+            header: None,
+            entries: vec![SsfEntry::default(); SsfSlot::COUNT],
+        }
+    }
+
     pub fn parse_from<R: Read>(reader: &mut R)
         -> Result<Self, MyError>
     {
@@ -25,4 +40,150 @@ impl SsfFile {
         reader.read_to_end(&mut bytes)?;
         parser::parse(bytes)
     }
-}
\ No newline at end of file
+
+    /// The entry for `slot`, `None` if the file stops before it.
+    pub fn get(&self, slot: SsfSlot)
+        -> Option<&SsfEntry>
+    {
+        self.entries.get(slot.index())
+    }
+
+    /// Fills `slot`, growing a short file to the full slot count first.
+    pub fn set(&mut self, slot: SsfSlot, res_ref: ResRef, string_ref: Option<StrRef>)
+        -> &mut Self
+    {
+        if self.entries.len() < SsfSlot::COUNT {
+            self.entries.resize_with(SsfSlot::COUNT, Default::default);
+        }
+
+        self.entries[slot.index()] = SsfEntry::new(res_ref, string_ref);
+        self
+    }
+
+    pub fn write<W: Write>(self, writer: &mut W)
+        -> Result<(), MyError>
+    {
+        let mut builder = SsfBuilder::new();
+        builder.add_entries(self.entries);
+        builder.write(writer)
+    }
+}
+
+impl Default for SsfFile {
+    fn default()
+        -> Self
+    {
+        SsfFile::new()
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::convert::TryFrom;
+    use std::io::Cursor;
+
+    fn res_ref(s: &str)
+        -> ResRef
+    {
+        ResRef::try_from(s).unwrap()
+    }
+
+    #[test]
+    fn slot_indices() {
+        assert_eq!(0, SsfSlot::Attack.index());
+        assert_eq!(34, SsfSlot::Hello.index());
+        assert_eq!(40, SsfSlot::Goodbye.index());
+        assert_eq!(48, SsfSlot::Threaten.index());
+        assert_eq!(Some(SsfSlot::Taunt), SsfSlot::from_index(8));
+        assert_eq!(None, SsfSlot::from_index(SsfSlot::COUNT));
+
+        for (i, slot) in SsfSlot::ALL.iter().enumerate() {
+            assert_eq!(i, slot.index());
+        }
+    }
+
+    #[test]
+    fn set_and_get_round_trip() {
+        let mut soundset = SsfFile::new();
+        soundset
+            .set(SsfSlot::Hello, res_ref("vs_hello"), Some(StrRef(1234)))
+            .set(SsfSlot::Threaten, res_ref("vs_threat"), None);
+
+        let mut bytes = Vec::new();
+        soundset.write(&mut bytes).unwrap();
+
+        let parsed = SsfFile::parse_from(&mut Cursor::new(bytes)).unwrap();
+
+        assert_eq!(SsfSlot::COUNT, parsed.entries.len());
+        assert_eq!(
+            Some(&SsfEntry::new(res_ref("vs_hello"), Some(StrRef(1234)))),
+            parsed.get(SsfSlot::Hello)
+        );
+        assert_eq!(Some(&SsfEntry::default()), parsed.get(SsfSlot::Goodbye));
+        assert_eq!(res_ref("vs_threat"), parsed.get(SsfSlot::Threaten).unwrap().res_ref);
+    }
+
+    #[test]
+    fn set_grows_short_files() {
+        let mut soundset = SsfFile {
+            header: None,
+            entries: vec![SsfEntry::new(res_ref("vs_attack"), None)],
+        };
+
+        assert_eq!(None, soundset.get(SsfSlot::Hello));
+
+        soundset.set(SsfSlot::Hello, res_ref("vs_hello"), None);
+
+        assert_eq!(SsfSlot::COUNT, soundset.entries.len());
+        assert_eq!(res_ref("vs_attack"), soundset.get(SsfSlot::Attack).unwrap().res_ref);
+    }
+
+    #[test]
+    fn builder_writes_every_slot() {
+        let mut builder = SsfBuilder::new();
+        builder.set(SsfSlot::Battlecry2, res_ref("vs_bc2"), Some(StrRef(7)));
+
+        let mut bytes = Vec::new();
+        builder.write(&mut bytes).unwrap();
+
+        let parsed = SsfFile::parse_from(&mut Cursor::new(bytes)).unwrap();
+
+        assert_eq!(SsfSlot::COUNT, parsed.entries.len());
+        assert_eq!(Some(StrRef(7)), parsed.get(SsfSlot::Battlecry2).unwrap().string_ref);
+        assert_eq!(None, parsed.get(SsfSlot::Battlecry1).unwrap().string_ref);
+    }
+
+    #[test]
+    fn builder_pads_to_every_slot() {
+        let mut bytes = Vec::new();
+        SsfBuilder::new().write(&mut bytes).unwrap();
+
+        let parsed = SsfFile::parse_from(&mut Cursor::new(bytes)).unwrap();
+
+        assert_eq!(vec![SsfEntry::default(); SsfSlot::COUNT], parsed.entries);
+
+        let mut builder = SsfBuilder::new();
+        builder.add_entry(SsfEntry::new(res_ref("vs_attack"), Some(StrRef(3))));
+
+        let mut bytes = Vec::new();
+        builder.write(&mut bytes).unwrap();
+
+        let parsed = SsfFile::parse_from(&mut Cursor::new(bytes)).unwrap();
+
+        assert_eq!(SsfSlot::COUNT, parsed.entries.len());
+        assert_eq!(Some(StrRef(3)), parsed.get(SsfSlot::Attack).unwrap().string_ref);
+    }
+
+    #[test]
+    fn builder_rejects_extra_entries() {
+        let mut builder = SsfBuilder::new();
+        builder.add_entries(vec![SsfEntry::default(); SsfSlot::COUNT + 1]);
+
+        match builder.write(&mut Vec::new()).unwrap_err() {
+            MyError::TooManySsfEntries(count) => assert_eq!(SsfSlot::COUNT + 1, count),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
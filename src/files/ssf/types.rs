@@ -7,14 +7,150 @@ use crate::types::{
     NULL_U32,
     StaticByteSize,
     ResRef,
+    StrRef,
     SerializeToBytes,
     Error as MyError,
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct SsfEntry {
     pub res_ref: ResRef,
-    pub string_ref: Option<u32>,
+    pub string_ref: Option<StrRef>,
+}
+
+impl SsfEntry {
+    pub fn new(res_ref: ResRef, string_ref: Option<StrRef>)
+        -> Self
+    {
+        SsfEntry {
+            res_ref,
+            string_ref,
+        }
+    }
+}
+
+/// The sound slots of a soundset in file order, the game looks them up by
+/// position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SsfSlot {
+    Attack = 0,
+    Battlecry1 = 1,
+    Battlecry2 = 2,
+    Battlecry3 = 3,
+    HealMe = 4,
+    Help = 5,
+    EnemiesSighted = 6,
+    Flee = 7,
+    Taunt = 8,
+    GuardMe = 9,
+    Hold = 10,
+    AttackGrunt1 = 11,
+    AttackGrunt2 = 12,
+    AttackGrunt3 = 13,
+    PainGrunt1 = 14,
+    PainGrunt2 = 15,
+    PainGrunt3 = 16,
+    NearDeath = 17,
+    Death = 18,
+    Poisoned = 19,
+    SpellFailed = 20,
+    WeaponIneffective = 21,
+    FollowMe = 22,
+    LookHere = 23,
+    GroupParty = 24,
+    MoveOver = 25,
+    PickLock = 26,
+    Search = 27,
+    GoStealthy = 28,
+    CanDo = 29,
+    CannotDo = 30,
+    TaskComplete = 31,
+    Encumbered = 32,
+    Selected = 33,
+    Hello = 34,
+    Yes = 35,
+    No = 36,
+    Stop = 37,
+    Rest = 38,
+    Bored = 39,
+    Goodbye = 40,
+    ThankYou = 41,
+    Laugh = 42,
+    Cuss = 43,
+    Cheer = 44,
+    SomethingToSay = 45,
+    GoodIdea = 46,
+    BadIdea = 47,
+    Threaten = 48,
+}
+
+impl SsfSlot {
+    /// How many slots a complete soundset has.
+    pub const COUNT: usize = 49;
+
+    pub const ALL: [SsfSlot; SsfSlot::COUNT] = [
+        SsfSlot::Attack,
+        SsfSlot::Battlecry1,
+        SsfSlot::Battlecry2,
+        SsfSlot::Battlecry3,
+        SsfSlot::HealMe,
+        SsfSlot::Help,
+        SsfSlot::EnemiesSighted,
+        SsfSlot::Flee,
+        SsfSlot::Taunt,
+        SsfSlot::GuardMe,
+        SsfSlot::Hold,
+        SsfSlot::AttackGrunt1,
+        SsfSlot::AttackGrunt2,
+        SsfSlot::AttackGrunt3,
+        SsfSlot::PainGrunt1,
+        SsfSlot::PainGrunt2,
+        SsfSlot::PainGrunt3,
+        SsfSlot::NearDeath,
+        SsfSlot::Death,
+        SsfSlot::Poisoned,
+        SsfSlot::SpellFailed,
+        SsfSlot::WeaponIneffective,
+        SsfSlot::FollowMe,
+        SsfSlot::LookHere,
+        SsfSlot::GroupParty,
+        SsfSlot::MoveOver,
+        SsfSlot::PickLock,
+        SsfSlot::Search,
+        SsfSlot::GoStealthy,
+        SsfSlot::CanDo,
+        SsfSlot::CannotDo,
+        SsfSlot::TaskComplete,
+        SsfSlot::Encumbered,
+        SsfSlot::Selected,
+        SsfSlot::Hello,
+        SsfSlot::Yes,
+        SsfSlot::No,
+        SsfSlot::Stop,
+        SsfSlot::Rest,
+        SsfSlot::Bored,
+        SsfSlot::Goodbye,
+        SsfSlot::ThankYou,
+        SsfSlot::Laugh,
+        SsfSlot::Cuss,
+        SsfSlot::Cheer,
+        SsfSlot::SomethingToSay,
+        SsfSlot::GoodIdea,
+        SsfSlot::BadIdea,
+        SsfSlot::Threaten,
+    ];
+
+    pub fn index(&self)
+        -> usize
+    {
+        *self as usize
+    }
+
+    pub fn from_index(index: usize)
+        -> Option<Self>
+    {
+        SsfSlot::ALL.get(index).copied()
+    }
 }
 
 
@@ -31,8 +167,8 @@ impl SerializeToBytes for SsfHeader
     fn serialize_to<F: Write>(self, writer: &mut F)
         -> Result<(), MyError>
     {
-        writer.write(self.version.as_str_ref().as_bytes())?;
         writer.write(self.file_type.as_str_ref().as_bytes())?;
+        writer.write(self.version.as_str_ref().as_bytes())?;
         writer.write(&self.entry_count.to_le_bytes())?;
         writer.write(&self.table_offset.to_le_bytes())?;
         writer.write(&[0; 24])?;
@@ -51,9 +187,10 @@ impl SerializeToBytes for SsfEntry
     fn serialize_to<F: Write>(self, writer: &mut F)
         -> Result<(), MyError>
     {
-        writer.write(&self.res_ref.serialize())?;
+        writer.write(&self.res_ref.serialize()?)?;
 
         let str_ref = self.string_ref
+            .map(u32::from)
             .unwrap_or(NULL_U32);
 
         writer.write(&str_ref.to_le_bytes())?;
@@ -1,7 +1,9 @@
-use super::types::{ SsfHeader };
+use super::types::{ SsfHeader, SsfSlot };
 
 use crate::types::{
     SsfEntry,
+    ResRef,
+    StrRef,
     Version,
     FileType,
     Error as MyError,
@@ -39,9 +41,29 @@ impl SsfBuilder {
         self
     }
 
-    pub fn write<W: Write>(self, writer: &mut W)
+    /// Fills `slot`, the slots nobody set stay empty.
+    pub fn set(&mut self, slot: SsfSlot, res_ref: ResRef, string_ref: Option<StrRef>)
+        -> &mut Self
+    {
+        if self.entries.len() < SsfSlot::COUNT {
+            self.entries.resize_with(SsfSlot::COUNT, Default::default);
+        }
+
+        self.entries[slot.index()] = SsfEntry::new(res_ref, string_ref);
+        self
+    }
+
+    /// Writes the full slot count in slot order, padding with empty
+    /// entries. More entries than slots is an error.
+    pub fn write<W: Write>(mut self, writer: &mut W)
         -> Result<(), MyError>
     {
+        if self.entries.len() > SsfSlot::COUNT {
+            return Err(MyError::TooManySsfEntries(self.entries.len()));
+        }
+
+        self.entries.resize_with(SsfSlot::COUNT, Default::default);
+
         let entry_count = self.entries.len();
         
         let entry_size = entry_count * 4;
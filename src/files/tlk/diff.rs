@@ -0,0 +1,193 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::types::StrRef;
+
+use super::tlk_file::TlkFile;
+use super::types::TlkEntry;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TlkChange {
+    Added {
+        strref: StrRef,
+        entry: TlkEntry,
+    },
+    Removed {
+        strref: StrRef,
+        entry: TlkEntry,
+    },
+    /// The string, the sound or both changed.
+    Changed {
+        strref: StrRef,
+        old: TlkEntry,
+        new: TlkEntry,
+    },
+}
+
+/// Entry by entry differences between two versions of a tlk. Entries are
+/// matched by index, an empty entry without a sound counts the same as a
+/// missing one. StrRefs follow the new tlk's `alternative` flag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlkDiff {
+    pub changes: Vec<TlkChange>,
+}
+
+/// Whether the entry holds neither a string nor a sound.
+pub(crate) fn is_blank(entry: Option<&TlkEntry>)
+    -> bool
+{
+    entry
+        .map(|e| e.string.is_empty() && e.sound.is_none())
+        .unwrap_or(true)
+}
+
+impl TlkDiff {
+    pub fn new(old: &TlkFile, new: &TlkFile)
+        -> Self
+    {
+        let indices = old
+            .iter()
+            .chain(new.iter())
+            .map(|(i, _)| i)
+            .collect::<BTreeSet<usize>>();
+
+        let changes = indices
+            .into_iter()
+            .filter_map(|i| {
+                let strref = new.strref(i);
+                let o = old.entry(i);
+                let n = new.entry(i);
+
+                match (is_blank(o), is_blank(n)) {
+                    (true, true) => None,
+                    (true, false) => Some(TlkChange::Added {
+                        strref,
+                        entry: n?.clone(),
+                    }),
+                    (false, true) => Some(TlkChange::Removed {
+                        strref,
+                        entry: o?.clone(),
+                    }),
+                    (false, false) if o != n => Some(TlkChange::Changed {
+                        strref,
+                        old: o?.clone(),
+                        new: n?.clone(),
+                    }),
+                    (false, false) => None,
+                }
+            })
+            .collect();
+
+        TlkDiff {
+            changes,
+        }
+    }
+
+    pub fn is_empty(&self)
+        -> bool
+    {
+        self.changes.is_empty()
+    }
+}
+
+/// The string on a single line, followed by the sound if there is one.
+fn entry_str(entry: &TlkEntry)
+    -> String
+{
+    let string = entry.string
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+
+    match &entry.sound {
+        Some(sound) => match sound.sound_length {
+            Some(length) => format!("{} <sound {} {}>", string, *sound.res_ref, length),
+            None => format!("{} <sound {}>", string, *sound.res_ref),
+        },
+        None => string,
+    }
+}
+
+impl fmt::Display for TlkChange
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)
+        -> fmt::Result
+    {
+        match self {
+            TlkChange::Added { strref, entry } =>
+                write!(f, "+ {}: {}", strref, entry_str(entry)),
+            TlkChange::Removed { strref, entry } =>
+                write!(f, "- {}: {}", strref, entry_str(entry)),
+            TlkChange::Changed { strref, old, new } =>
+                write!(f, "~ {}: {} -> {}", strref, entry_str(old), entry_str(new)),
+        }
+    }
+}
+
+/// One change per line.
+impl fmt::Display for TlkDiff
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)
+        -> fmt::Result
+    {
+        for change in self.changes.iter() {
+            writeln!(f, "{}", change)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::convert::TryFrom;
+    use crate::types::{ResRef, TlkSound};
+
+    fn tlk(strings: &[&str])
+        -> TlkFile
+    {
+        let mut tlk = TlkFile::new();
+
+        for s in strings {
+            tlk.add_entry(TlkEntry {
+                string: String::from(*s),
+                sound: None,
+            });
+        }
+
+        tlk
+    }
+
+    #[test]
+    fn diff_identical_ignores_trailing_blanks() {
+        assert!(TlkDiff::new(&tlk(&["a", "b"]), &tlk(&["a", "b", ""])).is_empty());
+    }
+
+    #[test]
+    fn diff_entries() {
+        let old = tlk(&["a", "b", "", "d"]);
+        let mut new = tlk(&["a", "b2", "c"]);
+
+        new.entries[0].sound = Some(TlkSound {
+            res_ref: ResRef::try_from("vs_a").unwrap(),
+            sound_length: Some(2.0),
+        });
+
+        let diff = TlkDiff::new(&old, &new);
+
+        assert_eq!(4, diff.changes.len());
+        assert!(matches!(diff.changes[2], TlkChange::Added { strref, .. } if strref == StrRef::custom(2)));
+        assert_eq!(
+            concat!(
+                "~ 16777216: a -> a <sound vs_a 2>\n",
+                "~ 16777217: b -> b2\n",
+                "+ 16777218: c\n",
+                "- 16777219: d\n",
+            ),
+            diff.to_string()
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::files::gff::types::{GffStruct, GffValue};
+use crate::files::x2da::dynamic_x2da::Dynamic2da;
+
+use crate::types::{
+    StrRef,
+    X2daError,
+    Error as MyError,
+};
+
+use super::diff::is_blank;
+use super::tlk_file::{TlkFile, MAX_ENTRY_INDEX};
+use super::types::TlkEntry;
+
+/// Both tlks have a different entry at the same index, `strref` is theirs'
+/// reference to it. Ours is kept there, theirs is either dropped or moved
+/// to `remapped`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlkCollision {
+    pub strref: StrRef,
+    pub ours: TlkEntry,
+    pub theirs: TlkEntry,
+    pub remapped: Option<StrRef>,
+}
+
+impl fmt::Display for TlkCollision
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)
+        -> fmt::Result
+    {
+        match self.remapped {
+            Some(to) => write!(f, "StrRef {} is used by both tlks, theirs was moved to {}.", self.strref, to),
+            None => write!(f, "StrRef {} is used by both tlks, theirs was dropped.", self.strref),
+        }
+    }
+}
+
+/// Where the entries of a merged tlk moved to. References that aren't in
+/// the table stay as they are.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TlkRemap {
+    map: BTreeMap<StrRef, StrRef>,
+}
+
+impl TlkRemap {
+    pub fn new()
+        -> Self
+    {
+        TlkRemap {
+            map: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, from: StrRef, to: StrRef)
+        -> &mut Self
+    {
+        self.map.insert(from, to);
+        self
+    }
+
+    pub fn len(&self)
+        -> usize
+    {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self)
+        -> bool
+    {
+        self.map.is_empty()
+    }
+
+    /// The new reference for `strref`.
+    pub fn get(&self, strref: StrRef)
+        -> StrRef
+    {
+        self.map.get(&strref).copied().unwrap_or(strref)
+    }
+
+    pub fn iter(&self)
+        -> impl Iterator<Item = (StrRef, StrRef)> + '_
+    {
+        self.map.iter().map(|(from, to)| (*from, *to))
+    }
+
+    /// Rewrites the StrRefs in `columns` of `table`, `Name` and
+    /// `Description` for example. Returns how many cells changed. Every
+    /// cell is checked first, a cell that isn't a number fails without
+    /// changing the table.
+    pub fn apply_to_2da(&self, table: &mut Dynamic2da, columns: &[&str])
+        -> Result<usize, X2daError>
+    {
+        let mut changes = Vec::new();
+
+        for column in columns {
+            for row in 0..table.len() {
+                let strref = match table.get_strref(row, column)? {
+                    Some(strref) => strref,
+                    None => continue,
+                };
+
+                let remapped = self.get(strref);
+
+                if remapped != strref {
+                    changes.push((row, *column, remapped));
+                }
+            }
+        }
+
+        for (row, column, remapped) in changes.iter() {
+            table.set(*row, column, Some(remapped.to_string()))?;
+        }
+
+        Ok(changes.len())
+    }
+
+    /// Rewrites the StrRef of every localized string in `root` and the
+    /// structs and lists below it. Returns how many changed.
+    pub fn apply_to_gff(&self, root: &mut GffStruct)
+        -> usize
+    {
+        root.fields
+            .iter_mut()
+            .map(|field| match &mut field.value {
+                GffValue::CExoLocString(s) => match s.string_ref {
+                    Some(string_ref) => {
+                        let remapped = self.get(StrRef(string_ref));
+                        s.string_ref = Some(remapped.0);
+
+                        (remapped.0 != string_ref) as usize
+                    },
+                    None => 0,
+                },
+                GffValue::Struct(s) => self.apply_to_gff(s),
+                GffValue::List(list) => list.iter_mut().map(|s| self.apply_to_gff(s)).sum(),
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+/// One `from <tab> to` line per remapped StrRef.
+impl fmt::Display for TlkRemap
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)
+        -> fmt::Result
+    {
+        for (from, to) in self.iter() {
+            writeln!(f, "{}\t{}", from, to)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct TlkMergeResult {
+    pub merged: TlkFile,
+    pub collisions: Vec<TlkCollision>,
+    /// How theirs' StrRefs changed, empty unless remapping is on.
+    pub remap: TlkRemap,
+}
+
+impl TlkMergeResult {
+    pub fn has_collisions(&self)
+        -> bool
+    {
+        !self.collisions.is_empty()
+    }
+}
+
+/// Merges two tlks, usually the custom tlks of two haks, entry by entry.
+///
+/// Entries only one side has are kept. Where both have a different entry at
+/// the same index ours wins, and theirs is dropped or, with remapping on,
+/// moved to a free slot. Empty entries count as free. The merged tlk takes
+/// ours' language, encoding and `alternative` flag, StrRefs follow that
+/// flag.
+#[derive(Debug, Default)]
+pub struct TlkMerge {
+    remap_from: Option<usize>,
+}
+
+impl TlkMerge {
+    pub fn new()
+        -> Self
+    {
+        TlkMerge {
+            remap_from: None,
+        }
+    }
+
+    /// Moves colliding entries of theirs to the first free slots at or past
+    /// `index`. Use `0` to fill gaps, or the end of the tlks to keep the
+    /// moved entries together. `merge` fails with `TlkFull` when the slots
+    /// run out before the 24 bit StrRef limit.
+    pub fn remap_from(&mut self, index: usize)
+        -> &mut Self
+    {
+        self.remap_from = Some(index);
+        self
+    }
+
+    pub fn merge(&self, ours: &TlkFile, theirs: &TlkFile)
+        -> Result<TlkMergeResult, MyError>
+    {
+        let mut merged = TlkFile::new();
+        merged.language_id = ours.language_id;
+        merged.alternative = ours.alternative;
+        merged.encoding = ours.encoding;
+        merged.entries = ours.entries.clone();
+        merged.sparse_entries = ours.sparse_entries.clone();
+        merged.entry_count = ours.entry_count;
+
+        let mut collisions = Vec::new();
+
+        for (i, entry) in theirs.iter() {
+            if is_blank(Some(entry)) {
+                continue;
+            }
+
+            match merged.entry(i) {
+                Some(ours) if !is_blank(Some(ours)) => {
+                    if ours != entry {
+                        collisions.push(TlkCollision {
+                            strref: theirs.strref(i),
+                            ours: ours.clone(),
+                            theirs: entry.clone(),
+                            remapped: None,
+                        });
+                    }
+                },
+                _ => merged.insert(i, entry.clone()),
+            }
+        }
+
+        let mut remap = TlkRemap::new();
+
+        // Slots are only handed out once every entry that keeps its index
+        // is in place.
+        if let Some(remap_from) = self.remap_from {
+            let mut next = remap_from;
+
+            for collision in collisions.iter_mut() {
+                while !is_blank(merged.entry(next)) {
+                    next += 1;
+                }
+
+                if next > MAX_ENTRY_INDEX as usize {
+                    return Err(MyError::TlkFull);
+                }
+
+                merged.insert(next, collision.theirs.clone());
+
+                let to = merged.strref(next);
+                collision.remapped = Some(to);
+                remap.insert(collision.strref, to);
+            }
+        }
+
+        Ok(TlkMergeResult {
+            merged,
+            collisions,
+            remap,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::io::Cursor;
+    use crate::types::CExoLocString;
+
+    fn tlk(strings: &[&str])
+        -> TlkFile
+    {
+        let mut tlk = TlkFile::new();
+
+        for s in strings {
+            tlk.add_entry(TlkEntry {
+                string: String::from(*s),
+                sound: None,
+            });
+        }
+
+        tlk
+    }
+
+    fn strings(tlk: &TlkFile)
+        -> Vec<&str>
+    {
+        (0..tlk.entry_count)
+            .map(|i| tlk.entry(i).map(|e| e.string.as_str()).unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn merge_without_collisions() {
+        let result = TlkMerge::new().merge(&tlk(&["a", ""]), &tlk(&["a", "b", "c"])).unwrap();
+
+        assert!(!result.has_collisions());
+        assert_eq!(vec!["a", "b", "c"], strings(&result.merged));
+        assert_eq!(3, result.merged.entry_count);
+    }
+
+    #[test]
+    fn merge_collisions_keep_ours() {
+        let result = TlkMerge::new().merge(&tlk(&["a", "b"]), &tlk(&["x", "b", "c"])).unwrap();
+
+        assert_eq!(vec!["a", "b", "c"], strings(&result.merged));
+        assert_eq!(
+            vec![TlkCollision {
+                strref: StrRef::custom(0),
+                ours: tlk(&["a"]).entries.remove(0),
+                theirs: tlk(&["x"]).entries.remove(0),
+                remapped: None,
+            }],
+            result.collisions
+        );
+        assert!(result.remap.is_empty());
+    }
+
+    #[test]
+    fn merge_remaps_into_free_slots() {
+        let ours = tlk(&["a", "b", "", "d"]);
+        let theirs = tlk(&["x", "y", "", "d", "e"]);
+
+        let result = TlkMerge::new().remap_from(0).merge(&ours, &theirs).unwrap();
+
+        assert_eq!(vec!["a", "b", "x", "d", "e", "y"], strings(&result.merged));
+        assert_eq!(StrRef::custom(2), result.remap.get(StrRef::custom(0)));
+        assert_eq!(StrRef::custom(5), result.remap.get(StrRef::custom(1)));
+        assert_eq!(StrRef::custom(4), result.remap.get(StrRef::custom(4)));
+        assert_eq!("16777216\t16777218\n16777217\t16777221\n", result.remap.to_string());
+
+        let result = TlkMerge::new().remap_from(10).merge(&ours, &theirs).unwrap();
+
+        assert_eq!(12, result.merged.entry_count);
+        assert_eq!(Some(StrRef::custom(11)), result.collisions[1].remapped);
+    }
+
+    #[test]
+    fn merge_remaps_theirs_strrefs() {
+        let ours = tlk(&["a"]);
+        let mut theirs = tlk(&["x"]);
+        theirs.alternative = false;
+
+        let result = TlkMerge::new().remap_from(1).merge(&ours, &theirs).unwrap();
+
+        assert_eq!(StrRef(0), result.collisions[0].strref);
+        assert_eq!(StrRef::custom(1), result.remap.get(StrRef(0)));
+    }
+
+    #[test]
+    fn merge_fails_without_free_slots() {
+        let ours = tlk(&["a"]);
+        let theirs = tlk(&["x"]);
+
+        match TlkMerge::new().remap_from(MAX_ENTRY_INDEX as usize + 1).merge(&ours, &theirs) {
+            Err(MyError::TlkFull) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn remap_2da_and_gff() {
+        let mut remap = TlkRemap::new();
+        remap.insert(StrRef::custom(0), StrRef::custom(7));
+
+        let text = "2DA V2.0\n\nLabel Name\n0 a 16777216\n1 b 16777217\n2 c ****\n";
+        let mut table = Dynamic2da::parse_from(&mut Cursor::new(text.as_bytes())).unwrap();
+
+        assert_eq!(1, remap.apply_to_2da(&mut table, &["name"]).unwrap());
+        assert_eq!(Some("16777223"), table.get(0, "Name"));
+        assert_eq!(Some("16777217"), table.get(1, "Name"));
+
+        let text = "2DA V2.0\n\nLabel Name\n0 a 16777223\n1 b 16777216\n2 c x\n";
+        let mut table = Dynamic2da::parse_from(&mut Cursor::new(text.as_bytes())).unwrap();
+
+        assert!(remap.apply_to_2da(&mut table, &["Name"]).is_err());
+        assert_eq!(Some("16777216"), table.get(1, "Name"));
+
+        let loc_string = |string_ref| GffValue::CExoLocString(CExoLocString {
+            string_ref: Some(string_ref),
+            strings: Vec::new(),
+        });
+
+        let mut inner = GffStruct::new(1);
+        inner.add_field("Name", loc_string(16777216));
+
+        let mut root = GffStruct::new(u32::MAX);
+        root.add_field("Name", loc_string(16777216))
+            .add_field("Other", loc_string(5))
+            .add_field("List", GffValue::List(vec![inner]));
+
+        assert_eq!(2, remap.apply_to_gff(&mut root));
+        assert_eq!(Some(&loc_string(16777223)), root.get("Name"));
+    }
+}
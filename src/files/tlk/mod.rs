@@ -1,3 +1,8 @@
 pub mod parser;
 pub mod types;
-pub mod tlk_file;
\ No newline at end of file
+pub mod tlk_file;
+pub mod talk_table;
+pub mod tlk_reader;
+pub mod translation;
+pub mod diff;
+pub mod merge;
@@ -1,6 +1,7 @@
 use std::io;
-use io::{BufRead, Seek};
+use io::{BufRead, Read, Seek};
 use std::convert::TryFrom;
+use std::collections::BTreeMap;
 
 use crate::types::{
     Error as MyError,
@@ -8,6 +9,7 @@ use crate::types::{
     LanguageId,
     Version,
     ResRef,
+    Encoding,
 };
 
 use super::tlk_file::TlkFile;
@@ -22,45 +24,23 @@ use super::types::{
 
 use crate::helpers::reader::ReaderExt;
 
-pub fn parse<R>(reader: &mut R, alternative: bool)
+/// Strings are decoded with `encoding`, or the code page of the tlk's
+/// language if there is none.
+pub fn parse<R>(reader: &mut R, alternative: bool, encoding: Option<&'static Encoding>)
     -> Result<TlkFile, MyError>
     where R: BufRead + Seek
 {
     let header = parse_header(reader)?;
 
     let string_data_table = parse_string_data_table(reader, &header)?;
-        
-    let strings = parse_strings(reader, &header, &string_data_table)?;
+
+    let string_encoding = encoding.unwrap_or_else(|| header.language_id.encoding());
+    let strings = parse_strings(reader, &header, &string_data_table, string_encoding)?;
     
     let tlk_entries = string_data_table
         .iter()
         .zip(strings)
-        .map(|(dt, string)| {
-            let flag_sound = TlkFlags::SoundPresent as u8;
-            let flag_sound_length = TlkFlags::SoundLengthPresent as u8;
-            let sound_present = dt.flags & flag_sound == flag_sound;
-            let sound_length_present = dt.flags & flag_sound_length == flag_sound_length;
-
-            let sound = if sound_present {
-                let sound_length = if sound_length_present {
-                    Some(dt.sound_length)
-                } else {
-                    None
-                };
-
-                Some(TlkSound {
-                    res_ref: dt.sound_res_ref.clone(),
-                    sound_length: sound_length,
-                })
-            } else {
-                None
-            };
-            
-            TlkEntry {
-                string,
-                sound,
-            }
-        })
+        .map(|(dt, string)| entry_from_data(dt, string))
         .collect::<Vec<TlkEntry>>();
 
     
@@ -69,13 +49,15 @@ pub fn parse<R>(reader: &mut R, alternative: bool)
         language_id: header.language_id.clone(),
         header: Some(header),
         entries: tlk_entries,
+        sparse_entries: BTreeMap::new(),
         alternative: alternative,
+        encoding,
     })
 }
 
-fn parse_header<R>(reader: &mut R)
+pub(crate) fn parse_header<R>(reader: &mut R)
     -> Result<TlkHeader, io::Error>
-    where R: BufRead + Seek
+    where R: Read + Seek
 {
     let file_type = FileType::from(reader.read_bytes_to_string(4)?.as_str());
     let version = Version::from(reader.read_bytes_to_string(4)?.as_str());
@@ -92,14 +74,14 @@ fn parse_header<R>(reader: &mut R)
     })
 }
 
-fn parse_string_data_table<R>(reader: &mut R, header: &TlkHeader)
+pub(crate) fn parse_string_data_table<R>(reader: &mut R, header: &TlkHeader)
     -> Result<Vec<TlkData>, MyError>
-    where R: BufRead + Seek
+    where R: Read + Seek
 {
     let data = (0..header.string_count)
         .map(|_| {
             let flags = reader.read_u32()? as u8;
-            let sound_res_ref = ResRef::try_from(reader.read_bytes_to_string(16)?)?;
+            let sound_res_ref = ResRef::try_from(reader.read_bytes(16)?.as_slice())?;
             reader.seek_from_current(8)?;
             let offset_to_string = reader.read_u32()? as usize;
             let string_size = reader.read_u32()? as usize;
@@ -118,7 +100,48 @@ fn parse_string_data_table<R>(reader: &mut R, header: &TlkHeader)
     Ok(data)
 }
 
-fn parse_strings<R>(reader: &mut R, header: &TlkHeader, data_table: &Vec<TlkData>)
+/// Builds the entry for a string data table element from its string.
+pub(crate) fn entry_from_data(dt: &TlkData, string: String)
+    -> TlkEntry
+{
+    let flag_sound = TlkFlags::SoundPresent as u8;
+    let flag_sound_length = TlkFlags::SoundLengthPresent as u8;
+    let sound_present = dt.flags & flag_sound == flag_sound;
+    let sound_length_present = dt.flags & flag_sound_length == flag_sound_length;
+
+    let sound = if sound_present {
+        let sound_length = if sound_length_present {
+            Some(dt.sound_length)
+        } else {
+            None
+        };
+
+        Some(TlkSound {
+            res_ref: dt.sound_res_ref.clone(),
+            sound_length,
+        })
+    } else {
+        None
+    };
+
+    TlkEntry {
+        string,
+        sound,
+    }
+}
+
+pub(crate) fn decode_string(bytes: &[u8], encoding: &'static Encoding)
+    -> String
+{
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+fn parse_strings<R>(
+    reader: &mut R,
+    header: &TlkHeader,
+    data_table: &[TlkData],
+    encoding: &'static Encoding
+)
     -> Result<Vec<String>, MyError>
     where R: BufRead + Seek
 {
@@ -128,7 +151,7 @@ fn parse_strings<R>(reader: &mut R, header: &TlkHeader, data_table: &Vec<TlkData
         .map(|dt| {
             let offset = header.string_offset + dt.offset_to_string;
             reader.seek_from_start(offset as u64)?;
-            reader.read_bytes_to_string(dt.string_size)
+            reader.read_bytes(dt.string_size).map(|b| decode_string(&b, encoding))
         })
         .collect::<Result<Vec<_>, io::Error>>()?;
 
@@ -0,0 +1,214 @@
+use crate::types::{
+    CExoLocString,
+    StrRef,
+    TlkEntry,
+};
+
+use super::tlk_file::TlkFile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlkGender {
+    Male = 0,
+    Female = 1,
+}
+
+/// The talk tables the game resolves strings against: dialog.tlk, the
+/// module's custom tlk for references with the custom flag, and the
+/// optional female variants of both (dialogf.tlk).
+///
+/// Female lookups fall back to the male tlk when there's no female tlk or
+/// it lacks the entry.
+#[derive(Debug)]
+pub struct TalkTable {
+    base: TlkFile,
+    base_female: Option<TlkFile>,
+    custom: Option<TlkFile>,
+    custom_female: Option<TlkFile>,
+}
+
+impl TalkTable {
+    pub fn new(mut base: TlkFile)
+        -> Self
+    {
+        base.alternative = false;
+
+        TalkTable {
+            base,
+            base_female: None,
+            custom: None,
+            custom_female: None,
+        }
+    }
+
+    pub fn set_base_female(&mut self, mut tlk: TlkFile)
+        -> &mut Self
+    {
+        tlk.alternative = false;
+        self.base_female = Some(tlk);
+        self
+    }
+
+    pub fn set_custom(&mut self, mut tlk: TlkFile)
+        -> &mut Self
+    {
+        tlk.alternative = true;
+        self.custom = Some(tlk);
+        self
+    }
+
+    pub fn set_custom_female(&mut self, mut tlk: TlkFile)
+        -> &mut Self
+    {
+        tlk.alternative = true;
+        self.custom_female = Some(tlk);
+        self
+    }
+
+    pub fn base(&self)
+        -> &TlkFile
+    {
+        &self.base
+    }
+
+    pub fn custom(&self)
+        -> Option<&TlkFile>
+    {
+        self.custom.as_ref()
+    }
+
+    /// The entry with its text and sound resref and length.
+    pub fn get(&self, strref: StrRef, gender: TlkGender)
+        -> Option<&TlkEntry>
+    {
+        let (male, female) = if strref.is_custom() {
+            (self.custom.as_ref()?, self.custom_female.as_ref())
+        } else {
+            (&self.base, self.base_female.as_ref())
+        };
+
+        if gender == TlkGender::Female {
+            if let Some(entry) = female.and_then(|tlk| tlk.get(strref)) {
+                return Some(entry);
+            }
+        }
+
+        male.get(strref)
+    }
+
+    pub fn string(&self, strref: StrRef, gender: TlkGender)
+        -> Option<&str>
+    {
+        self.get(strref, gender).map(|e| e.string.as_str())
+    }
+
+    /// Resolves a gff localized string the way the game does: an embedded
+    /// string in the base tlk's language wins over the string reference.
+    pub fn loc_string<'a>(&'a self, loc_string: &'a CExoLocString, gender: TlkGender)
+        -> Option<&'a str>
+    {
+        let string_id = self.base.language_id as u32 * 2 + gender as u32;
+
+        loc_string.strings
+            .iter()
+            .find(|s| s.string_id == string_id)
+            .map(|s| s.text.as_str())
+            .or_else(|| {
+                loc_string.string_ref
+                    .and_then(|r| self.string(StrRef(r), gender))
+            })
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::types::{CExoLocSubString, LanguageId, ResRef, TlkSound};
+    use std::convert::TryFrom;
+
+    fn entry(s: &str)
+        -> TlkEntry
+    {
+        TlkEntry {
+            string: String::from(s),
+            sound: None,
+        }
+    }
+
+    fn tlk(strings: &[&str])
+        -> TlkFile
+    {
+        let mut tlk = TlkFile::new();
+        tlk.add_entries(strings.iter().map(|s| entry(s)).collect());
+        tlk
+    }
+
+    fn table()
+        -> TalkTable
+    {
+        let mut base = tlk(&["Bad Strref", "Sword"]);
+        base.entries[1].sound = Some(TlkSound {
+            res_ref: ResRef::try_from("vs_sword").unwrap(),
+            sound_length: Some(1.5),
+        });
+
+        let mut table = TalkTable::new(base);
+        table
+            .set_base_female(tlk(&["Bad Strref", "Sword (f)"]))
+            .set_custom(tlk(&["Custom", "Custom two"]))
+            .set_custom_female(tlk(&["Custom (f)"]));
+
+        table
+    }
+
+    #[test]
+    fn get_base_and_custom() {
+        let table = table();
+
+        assert_eq!(Some("Sword"), table.string(StrRef(1), TlkGender::Male));
+        assert_eq!(Some("Custom two"), table.string(StrRef::custom(1), TlkGender::Male));
+        assert_eq!(None, table.string(StrRef(5), TlkGender::Male));
+        assert_eq!(None, table.string(StrRef::NONE, TlkGender::Male));
+
+        let sound = table.get(StrRef(1), TlkGender::Male).unwrap().sound.as_ref().unwrap();
+        assert_eq!("vs_sword", sound.res_ref.as_str());
+        assert_eq!(Some(1.5), sound.sound_length);
+    }
+
+    #[test]
+    fn get_female_falls_back() {
+        let table = table();
+
+        assert_eq!(Some("Sword (f)"), table.string(StrRef(1), TlkGender::Female));
+        assert_eq!(Some("Custom (f)"), table.string(StrRef::custom(0), TlkGender::Female));
+        assert_eq!(Some("Custom two"), table.string(StrRef::custom(1), TlkGender::Female));
+    }
+
+    #[test]
+    fn tlk_file_only_resolves_its_own_strrefs() {
+        let mut custom = tlk(&["Custom"]);
+
+        assert_eq!(Some("Custom"), custom.get(StrRef::custom(0)).map(|e| e.string.as_str()));
+        assert_eq!(None, custom.get(StrRef(0)));
+
+        custom.alternative = false;
+        assert_eq!(Some("Custom"), custom.get(StrRef(0)).map(|e| e.string.as_str()));
+    }
+
+    #[test]
+    fn loc_string_prefers_embedded_text() {
+        let mut table = table();
+        table.base.language_id = LanguageId::French;
+
+        let mut loc = CExoLocString {
+            string_ref: Some(1),
+            strings: vec![CExoLocSubString { string_id: 3, text: String::from("Épée") }],
+        };
+
+        assert_eq!(Some("Épée"), table.loc_string(&loc, TlkGender::Female));
+        assert_eq!(Some("Sword"), table.loc_string(&loc, TlkGender::Male));
+
+        loc.string_ref = None;
+        assert_eq!(None, table.loc_string(&loc, TlkGender::Male));
+    }
+}
@@ -4,6 +4,9 @@ use crate::types::{
     Version,
     FileType,
     LanguageId,
+    StrRef,
+    Encoding,
+    CUSTOM_TLK_FLAG,
     Error as MyError,
     StaticByteSize,
     SerializeToBytes,
@@ -13,19 +16,31 @@ use super::types::{
     TlkHeader,
     TlkEntry,
     TlkData,
+    serialize_entries,
 };
 
+use std::collections::BTreeMap;
 use std::io::prelude::*;
+use std::mem;
 
-const ALT_ENTRY: usize = 0x01000000;
+/// The highest entry index a StrRef can hold below the custom tlk flag.
+pub(crate) const MAX_ENTRY_INDEX: u32 = CUSTOM_TLK_FLAG - 1;
 
+/// `entries` holds the tlk from index 0 on. Entries set past its end are
+/// kept in `sparse_entries` until the gap before them is filled, so a far
+/// away StrRef doesn't need millions of placeholders. Gaps are written as
+/// empty entries. `entry_count` is one past the highest index.
 #[derive(Debug)]
 pub struct TlkFile {
     pub header: Option<TlkHeader>,
     pub entries: Vec<TlkEntry>,
+    pub sparse_entries: BTreeMap<usize, TlkEntry>,
     pub language_id: LanguageId,
     pub entry_count: usize,
     pub alternative: bool,
+    /// Overrides the code page of `language_id` when reading and writing
+    /// strings.
+    pub encoding: Option<&'static Encoding>,
 }
 
 impl TlkFile {
@@ -35,49 +50,210 @@ impl TlkFile {
         TlkFile {
             header: None,
             entries: Vec::new(),
+            sparse_entries: BTreeMap::new(),
             language_id: LanguageId::English,
             entry_count: 0,
             alternative: true,
+            encoding: None,
         }
     }
     
     pub fn parse_from<R: BufRead + Seek>(reader: &mut R, alternative: bool)
         -> Result<Self, MyError>
     {
-        parser::parse(reader, alternative)
+        parser::parse(reader, alternative, None)
+    }
+
+    /// Like `parse_from` but decodes strings with `encoding` whatever the
+    /// language of the tlk. The override is kept for writing.
+    pub fn parse_with_encoding<R: BufRead + Seek>(
+        reader: &mut R,
+        alternative: bool,
+        encoding: &'static Encoding
+    )
+        -> Result<Self, MyError>
+    {
+        parser::parse(reader, alternative, Some(encoding))
+    }
+
+    /// The encoding strings are read and written with.
+    pub fn encoding(&self)
+        -> &'static Encoding
+    {
+        self.encoding.unwrap_or_else(|| self.language_id.encoding())
     }
 
 
     pub fn next_id(&mut self) -> usize
     {
         if self.alternative {
-            self.entry_count + ALT_ENTRY
+            self.entry_count + CUSTOM_TLK_FLAG as usize
         } else {
             self.entry_count
         }
     }
     
+    /// The entry `strref` points to, if it belongs to this tlk. Custom
+    /// references only resolve against alternative tlks and the other way
+    /// round.
+    pub fn get(&self, strref: StrRef)
+        -> Option<&TlkEntry>
+    {
+        if !self.owns(strref) {
+            return None;
+        }
+
+        self.entry(strref.index() as usize)
+    }
+
+    /// The entry at `index`, whether it is in `entries` or `sparse_entries`.
+    pub fn entry(&self, index: usize)
+        -> Option<&TlkEntry>
+    {
+        self.entries
+            .get(index)
+            .or_else(|| self.sparse_entries.get(&index))
+    }
+
+    /// Every entry with its index, in ascending order. Gaps are skipped.
+    pub fn iter(&self)
+        -> impl Iterator<Item = (usize, &TlkEntry)>
+    {
+        self.entries
+            .iter()
+            .enumerate()
+            .chain(self.sparse_entries.iter().map(|(i, e)| (*i, e)))
+    }
+
+    fn owns(&self, strref: StrRef)
+        -> bool
+    {
+        strref != StrRef::NONE
+            && strref.is_custom() == self.alternative
+            && strref.index() <= MAX_ENTRY_INDEX
+    }
+
+    /// Puts `entry` at `strref`, replacing the entry there. Setting past the
+    /// end leaves a gap rather than filling it with placeholders. StrRefs of
+    /// the other kind of tlk and ones past the 24 bit index range fail with
+    /// `InvalidStrRef`.
+    pub fn set(&mut self, strref: StrRef, entry: TlkEntry)
+        -> Result<&mut Self, MyError>
+    {
+        if !self.owns(strref) {
+            return Err(MyError::InvalidStrRef(strref.0));
+        }
+
+        self.insert(strref.index() as usize, entry);
+
+        Ok(self)
+    }
+
+    /// Puts `entry` at `index`, taking it out of `sparse_entries` once the
+    /// entries before it are filled.
+    pub(crate) fn insert(&mut self, index: usize, entry: TlkEntry)
+    {
+        match index {
+            i if i < self.entries.len() => self.entries[i] = entry,
+            i if i == self.entries.len() => {
+                self.entries.push(entry);
+
+                while let Some(next) = self.sparse_entries.remove(&self.entries.len()) {
+                    self.entries.push(next);
+                }
+            },
+            i => {
+                self.sparse_entries.insert(i, entry);
+            },
+        }
+
+        self.entry_count = self.entry_count.max(index + 1);
+    }
+
+    /// Takes out the entry at `strref`. Removing the last entry shrinks the
+    /// tlk, any other leaves a gap.
+    pub fn remove(&mut self, strref: StrRef)
+        -> Option<TlkEntry>
+    {
+        if !self.owns(strref) {
+            return None;
+        }
+
+        let index = strref.index() as usize;
+
+        let entry = match index {
+            i if i + 1 == self.entries.len() => self.entries.pop()?,
+            i if i < self.entries.len() => mem::take(&mut self.entries[i]),
+            i => self.sparse_entries.remove(&i)?,
+        };
+
+        if index + 1 >= self.entry_count {
+            self.entry_count = self.last_index().map(|i| i + 1).unwrap_or(0);
+        }
+
+        Some(entry)
+    }
+
+    fn last_index(&self)
+        -> Option<usize>
+    {
+        self.sparse_entries
+            .keys()
+            .next_back()
+            .copied()
+            .or_else(|| self.entries.len().checked_sub(1))
+    }
+
+    /// The reference the game uses for entry `index` of this tlk.
+    pub fn strref(&self, index: usize)
+        -> StrRef
+    {
+        if self.alternative {
+            StrRef::custom(index as u32)
+        } else {
+            StrRef(index as u32)
+        }
+    }
+
     pub fn add_entry(&mut self, entry: TlkEntry)
         -> &mut Self
     {
-        self.entry_count += 1;
-        self.entries.push(entry);
+        self.insert(self.entry_count, entry);
         self
     }
 
-    pub fn add_entries(&mut self, mut entries: Vec<TlkEntry>)
+    pub fn add_entries(&mut self, entries: Vec<TlkEntry>)
         -> &mut Self
     {
-        self.entry_count += entries.len();
-        self.entries.append(&mut entries);
+        for entry in entries {
+            self.add_entry(entry);
+        }
+
         self
     }
 
+    /// How many entries a written tlk has, gaps included.
+    fn string_count(&self)
+        -> usize
+    {
+        let last = self.last_index().map(|i| i + 1).unwrap_or(0);
+
+        self.entry_count.max(last)
+    }
+
     pub fn write<W: Write>(self, writer: &mut W)
         -> Result<(), MyError>
     {
         self.write_header(writer)?;
-        self.entries.serialize_to(writer)?;
+        let encoding = self.encoding();
+        let string_count = self.string_count();
+
+        let entries = self.entries
+            .into_iter()
+            .enumerate()
+            .chain(self.sparse_entries);
+
+        serialize_entries(entries, string_count, encoding, writer)?;
 
         Ok(())
     }
@@ -85,7 +261,7 @@ impl TlkFile {
     fn write_header<W: Write>(&self, writer: &mut W)
         -> Result<(), MyError>
     {
-        let string_count = self.entries.len();
+        let string_count = self.string_count();
         let string_offset = TlkHeader::BYTE_SIZE + TlkData::BYTE_SIZE * string_count;
 
         let header = TlkHeader {
@@ -100,4 +276,207 @@ impl TlkFile {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::io::Cursor;
+
+    fn entry(s: &str)
+        -> TlkEntry
+    {
+        TlkEntry {
+            string: String::from(s),
+            sound: None,
+        }
+    }
+
+    fn round_trip(tlk: TlkFile)
+        -> (Vec<u8>, TlkFile)
+    {
+        let mut bytes = Vec::new();
+        tlk.write(&mut bytes).unwrap();
+
+        let parsed = TlkFile::parse_from(&mut Cursor::new(bytes.clone()), false).unwrap();
+
+        (bytes, parsed)
+    }
+
+    #[test]
+    fn write_uses_language_code_page() {
+        let mut tlk = TlkFile::new();
+        tlk.language_id = LanguageId::Polish;
+        tlk.add_entry(entry("Zażółć gęślą jaźń"));
+
+        let (bytes, parsed) = round_trip(tlk);
+
+        // Single byte cp1250, not two byte UTF-8.
+        assert_eq!(TlkHeader::BYTE_SIZE + TlkData::BYTE_SIZE + 17, bytes.len());
+        assert_eq!("Zażółć gęślą jaźń", parsed.entries[0].string);
+    }
+
+    #[test]
+    fn round_trip_multi_byte_languages() {
+        for (language_id, s) in [
+            (LanguageId::Korean, "안녕하세요"),
+            (LanguageId::Japanese, "こんにちは"),
+            (LanguageId::ChineseTraditional, "你好嗎"),
+            (LanguageId::ChineseSimplified, "你好吗"),
+        ] {
+            let mut tlk = TlkFile::new();
+            tlk.language_id = language_id;
+            tlk.add_entry(entry(s));
+
+            let (_, parsed) = round_trip(tlk);
+
+            assert_eq!(language_id as u32, parsed.language_id as u32);
+            assert_eq!(s, parsed.entries[0].string);
+        }
+    }
+
+    #[test]
+    fn vec_serializes_as_windows_1252() {
+        let mut bytes = Vec::new();
+        vec![entry("Caf\u{e9}"), entry("")].serialize_to(&mut bytes).unwrap();
+
+        assert_eq!(TlkData::BYTE_SIZE * 2 + 4, bytes.len());
+        assert!(bytes.ends_with(b"Caf\xe9"));
+        assert_eq!(1, bytes[0]);
+        assert_eq!(0, bytes[TlkData::BYTE_SIZE]);
+    }
+
+    #[test]
+    fn encoding_override() {
+        let cp1251 = Encoding::for_label(b"windows-1251").unwrap();
+
+        let mut tlk = TlkFile::new();
+        tlk.encoding = Some(cp1251);
+        tlk.add_entry(entry("Привет"));
+
+        let mut bytes = Vec::new();
+        tlk.write(&mut bytes).unwrap();
+
+        let parsed = TlkFile::parse_with_encoding(&mut Cursor::new(bytes.clone()), false, cp1251)
+            .unwrap();
+
+        assert_eq!("Привет", parsed.entries[0].string);
+        assert_eq!(cp1251, parsed.encoding());
+
+        // Read as the English default it is cp1252 mojibake.
+        let parsed = TlkFile::parse_from(&mut Cursor::new(bytes), false).unwrap();
+        assert_ne!("Привет", parsed.entries[0].string);
+    }
+
+    #[test]
+    fn write_unencodable() {
+        let mut tlk = TlkFile::new();
+        tlk.add_entry(entry("Привет"));
+
+        match tlk.write(&mut Vec::new()) {
+            Err(MyError::UnencodableString(s, "windows-1252")) => assert_eq!("Привет", s),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn set_and_remove() {
+        let mut tlk = TlkFile::new();
+        tlk.alternative = false;
+        tlk.add_entries(vec![entry("a"), entry("b")]);
+
+        tlk.set(StrRef(1), entry("B")).unwrap();
+        tlk.set(StrRef(16_000_123), entry("far")).unwrap();
+
+        assert_eq!(2, tlk.entries.len());
+        assert_eq!(1, tlk.sparse_entries.len());
+        assert_eq!(16_000_124, tlk.entry_count);
+        assert_eq!(Some(&entry("B")), tlk.get(StrRef(1)));
+        assert_eq!(16_000_124, tlk.next_id());
+
+        for strref in [StrRef::custom(2), StrRef(0x02000000), StrRef::NONE] {
+            match tlk.set(strref, entry("c")) {
+                Err(MyError::InvalidStrRef(s)) => assert_eq!(strref.0, s),
+                other => panic!("unexpected {:?}", other.map(|_| ())),
+            }
+        }
+
+        assert_eq!(None, tlk.remove(StrRef::custom(1)));
+        assert_eq!(Some(entry("far")), tlk.remove(StrRef(16_000_123)));
+        assert_eq!(2, tlk.entry_count);
+
+        assert_eq!(Some(entry("a")), tlk.remove(StrRef(0)));
+        assert_eq!(2, tlk.entry_count);
+        assert_eq!(Some(&TlkEntry::default()), tlk.get(StrRef(0)));
+    }
+
+    #[test]
+    fn set_fills_entries_in_order() {
+        let mut tlk = TlkFile::new();
+        tlk.alternative = false;
+
+        tlk.set(StrRef(2), entry("c")).unwrap();
+        tlk.set(StrRef(1), entry("b")).unwrap();
+        assert!(tlk.entries.is_empty());
+
+        tlk.set(StrRef(0), entry("a")).unwrap();
+        assert_eq!(vec![entry("a"), entry("b"), entry("c")], tlk.entries);
+        assert!(tlk.sparse_entries.is_empty());
+
+        tlk.add_entry(entry("d"));
+        assert_eq!(4, tlk.entries.len());
+    }
+
+    #[test]
+    fn write_fills_gaps() {
+        let mut tlk = TlkFile::new();
+        tlk.alternative = false;
+        tlk.set(StrRef(1), entry("a")).unwrap();
+        tlk.set(StrRef(4), entry("bc")).unwrap();
+
+        let (bytes, parsed) = round_trip(tlk);
+
+        assert_eq!(TlkHeader::BYTE_SIZE + TlkData::BYTE_SIZE * 5 + 3, bytes.len());
+        assert_eq!(5, parsed.entry_count);
+        assert_eq!(
+            vec!["", "a", "", "", "bc"],
+            parsed.entries.iter().map(|e| e.string.as_str()).collect::<Vec<_>>()
+        );
+
+        // Gaps have no flags and point at the end of the previous string.
+        let gap = TlkHeader::BYTE_SIZE + TlkData::BYTE_SIZE * 2;
+        assert_eq!(&[0, 0, 0, 0], &bytes[gap..gap + 4]);
+        assert_eq!(&[1, 0, 0, 0], &bytes[gap + 28..gap + 32]);
+        assert_eq!(&[0, 0, 0, 0], &bytes[gap + 32..gap + 36]);
+    }
+
+    #[test]
+    fn write_overlapping_sparse_entry() {
+        let mut tlk = TlkFile::new();
+        tlk.alternative = false;
+        tlk.add_entries(vec![entry("a"), entry("b")]);
+        tlk.sparse_entries.insert(1, entry("B"));
+
+        match tlk.write(&mut Vec::new()) {
+            Err(MyError::TlkEntryOutOfOrder(1)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn write_entry_past_count() {
+        let entries = vec![(0, entry("a")), (2, entry("b"))];
+
+        match serialize_entries(entries, 2, LanguageId::English.encoding(), &mut Vec::new()) {
+            Err(MyError::TlkEntryOutOfOrder(2)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn language_id_from_u32() {
+        assert!(matches!(LanguageId::from(131), LanguageId::Japanese));
+        assert!(matches!(LanguageId::from(121), LanguageId::Unknown));
+    }
+}
@@ -0,0 +1,316 @@
+use std::fs::File;
+use std::io::{BufReader, Cursor, SeekFrom};
+use std::io::prelude::*;
+use std::num::NonZeroUsize;
+use std::path::Path;
+
+use lru::LruCache;
+
+use crate::helpers::reader::ReaderExt;
+
+use crate::types::{
+    Error as MyError,
+    LanguageId,
+    StrRef,
+    Encoding,
+    StaticByteSize,
+};
+
+use super::parser::{
+    parse_header,
+    parse_string_data_table,
+    entry_from_data,
+    decode_string,
+};
+
+use super::types::{
+    TlkHeader,
+    TlkData,
+    TlkEntry,
+};
+
+/// Reads a tlk lazily. Only the header and the string data table are loaded
+/// up front, strings are read when they are asked for, which keeps lookups
+/// in dialog.tlk cheap.
+pub struct TlkReader<R: Read + Seek> {
+    reader: R,
+    pub header: TlkHeader,
+    pub alternative: bool,
+    encoding: &'static Encoding,
+    data_table: Vec<TlkData>,
+    stream_length: usize,
+    cache: Option<LruCache<usize, TlkEntry>>,
+}
+
+impl TlkReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(file_path: P, alternative: bool)
+        -> Result<Self, MyError>
+    {
+        let f = File::open(file_path)?;
+        TlkReader::new(BufReader::new(f), alternative)
+    }
+}
+
+impl<R: Read + Seek> TlkReader<R> {
+    pub fn new(mut reader: R, alternative: bool)
+        -> Result<Self, MyError>
+    {
+        let stream_length = reader.seek(SeekFrom::End(0))? as usize;
+
+        if stream_length < TlkHeader::BYTE_SIZE {
+            return Err(MyError::OffsetOutOfBounds(0));
+        }
+
+        reader.seek_from_start(0)?;
+        let header = parse_header(&mut reader)?;
+
+        let table_size = header.string_count * TlkData::BYTE_SIZE;
+
+        if TlkHeader::BYTE_SIZE + table_size > stream_length {
+            return Err(MyError::OffsetOutOfBounds(TlkHeader::BYTE_SIZE));
+        }
+
+        // One read for the whole table instead of one per entry.
+        let table_bytes = reader.read_bytes(table_size)?;
+        let data_table = parse_string_data_table(&mut Cursor::new(table_bytes), &header)?;
+
+        Ok(TlkReader {
+            reader,
+            encoding: header.language_id.encoding(),
+            header,
+            alternative,
+            data_table,
+            stream_length,
+            cache: None,
+        })
+    }
+
+    /// Keeps up to `capacity` of the most recently read entries in memory,
+    /// `0` turns the cache off again.
+    pub fn set_cache_capacity(&mut self, capacity: usize)
+        -> &mut Self
+    {
+        self.cache = NonZeroUsize::new(capacity).map(LruCache::new);
+        self
+    }
+
+    /// Decodes strings with `encoding` instead of the code page of the tlk's
+    /// language. Clears the cache.
+    pub fn set_encoding(&mut self, encoding: &'static Encoding)
+        -> &mut Self
+    {
+        self.encoding = encoding;
+
+        if let Some(cache) = self.cache.as_mut() {
+            cache.clear();
+        }
+
+        self
+    }
+
+    pub fn encoding(&self)
+        -> &'static Encoding
+    {
+        self.encoding
+    }
+
+    pub fn len(&self)
+        -> usize
+    {
+        self.data_table.len()
+    }
+
+    pub fn is_empty(&self)
+        -> bool
+    {
+        self.data_table.is_empty()
+    }
+
+    pub fn language_id(&self)
+        -> LanguageId
+    {
+        self.header.language_id
+    }
+
+    /// Reads the entry at `index` of the string data table.
+    pub fn read(&mut self, index: usize)
+        -> Result<TlkEntry, MyError>
+    {
+        if let Some(entry) = self.cache.as_mut().and_then(|c| c.get(&index)) {
+            return Ok(entry.clone());
+        }
+
+        let dt = self.data_table
+            .get(index)
+            .ok_or(MyError::InvalidResourceIndex(index))?;
+
+        let offset = self.header.string_offset + dt.offset_to_string;
+
+        if offset + dt.string_size > self.stream_length {
+            return Err(MyError::OffsetOutOfBounds(offset));
+        }
+
+        self.reader.seek_from_start(offset as u64)?;
+
+        let string = self.reader
+            .read_bytes(dt.string_size)
+            .map(|b| decode_string(&b, self.encoding))
+            .or(Err(MyError::OffsetOutOfBounds(offset)))?;
+
+        let entry = entry_from_data(dt, string);
+
+        if let Some(cache) = self.cache.as_mut() {
+            cache.put(index, entry.clone());
+        }
+
+        Ok(entry)
+    }
+
+    /// The entry `strref` points to, `None` if it doesn't belong to this tlk.
+    /// Follows the same rules as `TlkFile::get`.
+    pub fn get(&mut self, strref: StrRef)
+        -> Result<Option<TlkEntry>, MyError>
+    {
+        if strref == StrRef::NONE || strref.is_custom() != self.alternative {
+            return Ok(None);
+        }
+
+        let index = strref.index() as usize;
+
+        if index >= self.len() {
+            return Ok(None);
+        }
+
+        self.read(index).map(Some)
+    }
+
+    pub fn into_inner(self)
+        -> R
+    {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::TlkFile;
+
+    fn tlk_bytes(strings: &[&str])
+        -> Vec<u8>
+    {
+        let mut tlk = TlkFile::new();
+        tlk.language_id = LanguageId::French;
+
+        for s in strings {
+            tlk.add_entry(TlkEntry {
+                string: String::from(*s),
+                sound: None,
+            });
+        }
+
+        let mut bytes = Vec::new();
+        tlk.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn read_on_demand() {
+        let bytes = tlk_bytes(&["Bad Strref", "Hello", "World"]);
+        let mut reader = TlkReader::new(Cursor::new(bytes), false).unwrap();
+
+        assert_eq!(3, reader.len());
+        assert_eq!(LanguageId::French as u32, reader.language_id() as u32);
+        assert_eq!("World", reader.read(2).unwrap().string);
+        assert_eq!("Hello", reader.get(StrRef(1)).unwrap().unwrap().string);
+        assert_eq!(None, reader.get(StrRef(3)).unwrap());
+        assert_eq!(None, reader.get(StrRef::custom(1)).unwrap());
+
+        match reader.read(3) {
+            Err(MyError::InvalidResourceIndex(3)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn read_alternative() {
+        let bytes = tlk_bytes(&["Custom"]);
+        let mut reader = TlkReader::new(Cursor::new(bytes), true).unwrap();
+
+        assert_eq!(None, reader.get(StrRef(0)).unwrap());
+        assert_eq!("Custom", reader.get(StrRef::custom(0)).unwrap().unwrap().string);
+    }
+
+    #[test]
+    fn read_cached() {
+        let bytes = tlk_bytes(&["a", "b", "c"]);
+        let mut reader = TlkReader::new(Cursor::new(bytes), false).unwrap();
+        reader.set_cache_capacity(2);
+
+        reader.read(0).unwrap();
+        reader.read(1).unwrap();
+        reader.read(2).unwrap();
+
+        let cache = reader.cache.as_ref().unwrap();
+        assert_eq!(2, cache.len());
+        assert!(!cache.contains(&0));
+
+        // Served from the cache even though the strings are gone.
+        let mut reader = TlkReader {
+            reader: Cursor::new(Vec::new()),
+            ..reader
+        };
+
+        assert_eq!("c", reader.read(2).unwrap().string);
+        assert!(reader.read(0).is_err());
+    }
+
+    #[test]
+    fn read_with_encoding() {
+        let mut tlk = TlkFile::new();
+        tlk.language_id = LanguageId::Korean;
+        tlk.add_entry(TlkEntry {
+            string: String::from("안녕"),
+            sound: None,
+        });
+
+        let mut bytes = Vec::new();
+        tlk.write(&mut bytes).unwrap();
+
+        let mut reader = TlkReader::new(Cursor::new(bytes), false).unwrap();
+        reader.set_cache_capacity(1);
+
+        assert_eq!("안녕", reader.read(0).unwrap().string);
+
+        reader.set_encoding(encoding_rs::UTF_8);
+        assert_ne!("안녕", reader.read(0).unwrap().string);
+    }
+
+    #[test]
+    fn read_truncated() {
+        let mut bytes = tlk_bytes(&["a", "b"]);
+        bytes.truncate(30);
+
+        match TlkReader::new(Cursor::new(bytes), false) {
+            Err(MyError::OffsetOutOfBounds(_)) => {},
+            other => panic!("unexpected {:?}", other.map(|r| r.len())),
+        }
+    }
+
+    #[test]
+    fn read_string_out_of_bounds() {
+        let mut bytes = tlk_bytes(&["a"]);
+
+        // string_size of the first data table entry.
+        let size_offset = TlkHeader::BYTE_SIZE + 32;
+        bytes[size_offset..size_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut reader = TlkReader::new(Cursor::new(bytes), false).unwrap();
+
+        match reader.read(0) {
+            Err(MyError::OffsetOutOfBounds(_)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
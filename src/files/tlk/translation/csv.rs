@@ -0,0 +1,207 @@
+use std::io::prelude::*;
+
+use crate::types::{
+    Error as MyError,
+    LanguageId,
+};
+
+use super::{
+    TlkTranslation,
+    TranslationUnit,
+    line_error,
+    parse_sound,
+    parse_strref,
+};
+
+const HEADER: [&str; 5] = ["strref", "sound_resref", "sound_length", "source", "target"];
+
+/// Quotes fields the way RFC 4180 wants, only when they need it.
+fn field(s: &str)
+    -> String
+{
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        String::from(s)
+    }
+}
+
+pub fn write<W: Write>(translation: &TlkTranslation, writer: &mut W)
+    -> Result<(), MyError>
+{
+    writeln!(writer, "{}", HEADER.join(","))?;
+
+    for unit in translation.units.iter() {
+        let (res_ref, sound_length) = match &unit.sound {
+            Some(sound) => (
+                sound.res_ref.to_string(),
+                sound.sound_length.map(|l| l.to_string()).unwrap_or_default(),
+            ),
+            None => (String::new(), String::new()),
+        };
+
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            unit.strref.0,
+            field(&res_ref),
+            sound_length,
+            field(&unit.source),
+            field(unit.target.as_deref().unwrap_or_default())
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Splits csv text into records of fields, each with the line it starts on.
+/// Quoted fields can span lines.
+fn records(text: &str)
+    -> Result<Vec<(usize, Vec<String>)>, MyError>
+{
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut current = String::new();
+    let mut line = 0;
+    let mut start = 0;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    current.push('"');
+                } else {
+                    quoted = false;
+                }
+            },
+            '"' if current.is_empty() => quoted = true,
+            '\n' if quoted => {
+                line += 1;
+                current.push(c);
+            },
+            c if quoted => current.push(c),
+            ',' => record.push(std::mem::take(&mut current)),
+            '\r' if chars.peek() == Some(&'\n') => {},
+            '\n' => {
+                record.push(std::mem::take(&mut current));
+                records.push((start, std::mem::take(&mut record)));
+                line += 1;
+                start = line;
+            },
+            c => current.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(line_error(start, "Unterminated quoted field."));
+    }
+
+    if !current.is_empty() || !record.is_empty() {
+        record.push(current);
+        records.push((start, record));
+    }
+
+    Ok(records)
+}
+
+/// The header row is required and names the columns, their order is free.
+/// Csv has nowhere to keep the languages, they are left unknown.
+pub fn parse(text: &str)
+    -> Result<TlkTranslation, MyError>
+{
+    let mut records = records(text)?.into_iter();
+
+    let header = match records.next() {
+        Some((_, header)) => header,
+        None => return Err(line_error(0, "Missing the header row.")),
+    };
+
+    let columns = HEADER
+        .iter()
+        .map(|name| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name)))
+        .collect::<Vec<Option<usize>>>();
+
+    if columns[0].is_none() || columns[3].is_none() {
+        return Err(line_error(0, "The header needs at least strref and source columns."));
+    }
+
+    let mut translation = TlkTranslation {
+        source_language: LanguageId::Unknown,
+        target_language: LanguageId::Unknown,
+        units: Vec::new(),
+    };
+
+    for (line, record) in records {
+        if record.iter().all(|f| f.is_empty()) {
+            continue;
+        }
+
+        let get = |column: usize| {
+            columns[column]
+                .and_then(|i| record.get(i))
+                .map(String::as_str)
+                .unwrap_or_default()
+        };
+
+        translation.units.push(TranslationUnit {
+            strref: parse_strref(get(0), line)?,
+            source: String::from(get(3)),
+            target: Some(String::from(get(4))).filter(|t| !t.is_empty()),
+            sound: parse_sound(get(1).trim(), get(2).trim(), line)?,
+        });
+    }
+
+    Ok(translation)
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::types::StrRef;
+
+    #[test]
+    fn write_csv() {
+        let mut bytes = Vec::new();
+        write(&super::super::test::sample(), &mut bytes).unwrap();
+
+        assert_eq!(
+            concat!(
+                "strref,sound_resref,sound_length,source,target\n",
+                "0,,,Bad Strref,Zły Strref\n",
+                "2,vs_hi,1.5,\"Say \"\"hi\"\",\n",
+                "\tfriend\\\",\n",
+                "3,vs_b,,<b>&amp;</b>,\"a, b\"\n",
+            ),
+            String::from_utf8(bytes).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_csv_from_spreadsheet() {
+        let text = "Target,StrRef,Source\r\n\"Hallo\r\nWelt\",5,Hello\r\n,,\r\n,6,Bye";
+
+        let translation = parse(text).unwrap();
+
+        assert_eq!(2, translation.units.len());
+        assert_eq!(StrRef(5), translation.units[0].strref);
+        assert_eq!(Some(String::from("Hallo\r\nWelt")), translation.units[0].target);
+        assert_eq!(None, translation.units[1].target);
+        assert_eq!("Bye", translation.units[1].source);
+    }
+
+    #[test]
+    fn parse_csv_errors() {
+        let e = parse("strref,source\n1,a\nx,b\n").unwrap_err();
+        assert!(matches!(e, MyError::InvalidTranslation(3, _)), "{:?}", e);
+
+        let e = parse("strref,source\n1,\"a\n").unwrap_err();
+        assert!(matches!(e, MyError::InvalidTranslation(2, _)), "{:?}", e);
+
+        let e = parse("id,text\n").unwrap_err();
+        assert!(matches!(e, MyError::InvalidTranslation(1, _)), "{:?}", e);
+    }
+}
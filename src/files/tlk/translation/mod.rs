@@ -0,0 +1,371 @@
+mod csv;
+mod po;
+mod xliff;
+
+use std::convert::TryFrom;
+use std::io::prelude::*;
+
+use crate::types::{
+    Error as MyError,
+    LanguageId,
+    ResRef,
+    StrRef,
+};
+
+use super::tlk_file::TlkFile;
+use super::types::TlkSound;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranslationFormat {
+    /// gettext PO, the StrRef is the `msgctxt` of each message.
+    Po,
+    /// XLIFF 1.2, the StrRef is the `id` of each `trans-unit`.
+    Xliff,
+    /// `strref,sound_resref,sound_length,source,target` with a header row.
+    Csv,
+}
+
+impl TranslationFormat {
+    /// The format of a file extension: `po`, `xlf`, `xliff` or `csv`.
+    pub fn from_extension(extension: &str)
+        -> Option<Self>
+    {
+        match extension.to_ascii_lowercase().as_str() {
+            "po" | "pot" => Some(TranslationFormat::Po),
+            "xlf" | "xliff" => Some(TranslationFormat::Xliff),
+            "csv" => Some(TranslationFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranslationMerge {
+    /// Every translated unit replaces the tlk's string.
+    All,
+    /// Only entries that are missing or empty in the tlk are filled in.
+    Untranslated,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationUnit {
+    pub strref: StrRef,
+    pub source: String,
+    /// `None` while the unit is untranslated.
+    pub target: Option<String>,
+    pub sound: Option<TlkSound>,
+}
+
+/// The strings of a tlk as they are handed to translators, each one paired
+/// with its translation if there is one yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlkTranslation {
+    pub source_language: LanguageId,
+    pub target_language: LanguageId,
+    pub units: Vec<TranslationUnit>,
+}
+
+impl TlkTranslation {
+    /// One unit per non empty string of `source`. Strings of `target` at the
+    /// same index become the translations.
+    pub fn from_tlk(source: &TlkFile, target: Option<&TlkFile>)
+        -> Self
+    {
+        let units = source
+            .iter()
+            .filter_map(|(i, entry)| {
+                let translated = target
+                    .and_then(|t| t.entry(i))
+                    .map(|e| e.string.clone())
+                    .filter(|s| !s.is_empty());
+
+                if entry.string.is_empty() && translated.is_none() {
+                    return None;
+                }
+
+                Some(TranslationUnit {
+                    strref: source.strref(i),
+                    source: entry.string.clone(),
+                    target: translated,
+                    sound: entry.sound.clone(),
+                })
+            })
+            .collect();
+
+        TlkTranslation {
+            source_language: source.language_id,
+            target_language: target.map(|t| t.language_id).unwrap_or(LanguageId::Unknown),
+            units,
+        }
+    }
+
+    pub fn parse_from<R: Read>(reader: &mut R, format: TranslationFormat)
+        -> Result<Self, MyError>
+    {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        // Spreadsheets like to save with a byte order mark.
+        let text = text.trim_start_matches('\u{feff}');
+
+        match format {
+            TranslationFormat::Po => po::parse(text),
+            TranslationFormat::Xliff => xliff::parse(text),
+            TranslationFormat::Csv => csv::parse(text),
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W, format: TranslationFormat)
+        -> Result<(), MyError>
+    {
+        match format {
+            TranslationFormat::Po => po::write(self, writer),
+            TranslationFormat::Xliff => xliff::write(self, writer),
+            TranslationFormat::Csv => csv::write(self, writer),
+        }
+    }
+
+    /// Writes the translated units into `tlk`. Units whose StrRef belongs to
+    /// the other kind of tlk are skipped. Returns how many entries were
+    /// written.
+    pub fn apply(&self, tlk: &mut TlkFile, merge: TranslationMerge)
+        -> usize
+    {
+        let mut applied = 0;
+
+        for unit in self.units.iter() {
+            let target = match &unit.target {
+                Some(target) => target,
+                None => continue,
+            };
+
+            let existing = tlk.get(unit.strref);
+
+            if merge == TranslationMerge::Untranslated && existing.map(|e| !e.string.is_empty()).unwrap_or(false) {
+                continue;
+            }
+
+            let mut entry = existing.cloned().unwrap_or_default();
+            entry.string = target.clone();
+
+            if unit.sound.is_some() {
+                entry.sound = unit.sound.clone();
+            }
+
+            if tlk.set(unit.strref, entry).is_ok() {
+                applied += 1;
+            }
+        }
+
+        applied
+    }
+
+    /// A tlk in the target language. Untranslated units keep their source
+    /// string, and it's an alternative tlk if the units use custom StrRefs.
+    pub fn to_tlk(&self)
+        -> TlkFile
+    {
+        let mut tlk = TlkFile::new();
+        tlk.language_id = self.target_language;
+        tlk.alternative = self.units.iter().any(|u| u.strref.is_custom());
+
+        let filled = TlkTranslation {
+            units: self.units
+                .iter()
+                .cloned()
+                .map(|mut u| {
+                    if u.target.is_none() {
+                        u.target = Some(u.source.clone());
+                    }
+
+                    u
+                })
+                .collect(),
+            ..self.clone()
+        };
+
+        filled.apply(&mut tlk, TranslationMerge::All);
+
+        tlk
+    }
+}
+
+/// `Line <n>:` errors for the text based formats, `line` starting at 0.
+fn line_error(line: usize, message: &str)
+    -> MyError
+{
+    MyError::InvalidTranslation(line + 1, String::from(message))
+}
+
+fn parse_strref(s: &str, line: usize)
+    -> Result<StrRef, MyError>
+{
+    s.trim()
+        .parse::<u32>()
+        .map(StrRef)
+        .map_err(|_| line_error(line, &format!("Invalid StrRef <{}>.", s)))
+}
+
+fn parse_sound(res_ref: &str, sound_length: &str, line: usize)
+    -> Result<Option<TlkSound>, MyError>
+{
+    if res_ref.is_empty() {
+        return Ok(None);
+    }
+
+    let sound_length = if sound_length.is_empty() {
+        None
+    } else {
+        Some(
+            sound_length
+                .parse::<f32>()
+                .map_err(|_| line_error(line, &format!("Invalid sound length <{}>.", sound_length)))?
+        )
+    };
+
+    Ok(Some(TlkSound {
+        res_ref: ResRef::try_from(res_ref).map_err(|e| line_error(line, &e.to_string()))?,
+        sound_length,
+    }))
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::io::Cursor;
+    use crate::types::TlkEntry;
+
+    pub fn sample()
+        -> TlkTranslation
+    {
+        TlkTranslation {
+            source_language: LanguageId::English,
+            target_language: LanguageId::Polish,
+            units: vec![
+                TranslationUnit {
+                    strref: StrRef(0),
+                    source: String::from("Bad Strref"),
+                    target: Some(String::from("Zły Strref")),
+                    sound: None,
+                },
+                TranslationUnit {
+                    strref: StrRef(2),
+                    source: String::from("Say \"hi\",\n\tfriend\\"),
+                    target: None,
+                    sound: Some(TlkSound {
+                        res_ref: ResRef::try_from("vs_hi").unwrap(),
+                        sound_length: Some(1.5),
+                    }),
+                },
+                TranslationUnit {
+                    strref: StrRef(3),
+                    source: String::from("<b>&amp;</b>"),
+                    target: Some(String::from("a, b")),
+                    sound: Some(TlkSound {
+                        res_ref: ResRef::try_from("vs_b").unwrap(),
+                        sound_length: None,
+                    }),
+                },
+            ],
+        }
+    }
+
+    /// Every string up to the end of `tlk`, gaps as empty ones.
+    fn strings(tlk: &TlkFile)
+        -> Vec<&str>
+    {
+        (0..tlk.entry_count)
+            .map(|i| tlk.entry(i).map(|e| e.string.as_str()).unwrap_or_default())
+            .collect()
+    }
+
+    fn entry(s: &str)
+        -> TlkEntry
+    {
+        TlkEntry {
+            string: String::from(s),
+            sound: None,
+        }
+    }
+
+    #[test]
+    fn round_trip_every_format() {
+        for format in [TranslationFormat::Po, TranslationFormat::Xliff, TranslationFormat::Csv] {
+            let mut bytes = Vec::new();
+            sample().write(&mut bytes, format).unwrap();
+
+            let parsed = TlkTranslation::parse_from(&mut Cursor::new(bytes), format).unwrap();
+
+            if format == TranslationFormat::Csv {
+                // Csv has nowhere to keep the languages.
+                assert_eq!(sample().units, parsed.units, "{:?}", format);
+            } else {
+                assert_eq!(sample(), parsed, "{:?}", format);
+            }
+        }
+    }
+
+    #[test]
+    fn from_tlk_skips_empty_strings() {
+        let mut source = TlkFile::new();
+        source.alternative = false;
+        source.add_entries(vec![entry("a"), entry(""), entry("c")]);
+
+        let mut target = TlkFile::new();
+        target.language_id = LanguageId::German;
+        target.add_entries(vec![entry(""), entry("b2")]);
+
+        let translation = TlkTranslation::from_tlk(&source, Some(&target));
+
+        assert_eq!(LanguageId::German, translation.target_language);
+        assert_eq!(
+            vec![(StrRef(0), None), (StrRef(1), Some("b2")), (StrRef(2), None)],
+            translation.units
+                .iter()
+                .map(|u| (u.strref, u.target.as_deref()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn apply_fills_untranslated_only() {
+        let mut tlk = TlkFile::new();
+        tlk.alternative = false;
+        tlk.add_entries(vec![entry("Już przetłumaczone"), entry(""), entry("")]);
+
+        let applied = sample().apply(&mut tlk, TranslationMerge::Untranslated);
+
+        assert_eq!(1, applied);
+        assert_eq!(
+            vec!["Już przetłumaczone", "", "", "a, b"],
+            strings(&tlk)
+        );
+        assert_eq!(4, tlk.entry_count);
+        assert_eq!(sample().units[2].sound, tlk.entries[3].sound);
+
+        sample().apply(&mut tlk, TranslationMerge::All);
+        assert_eq!("Zły Strref", tlk.entries[0].string);
+    }
+
+    #[test]
+    fn apply_skips_other_tlk() {
+        let mut tlk = TlkFile::new();
+        assert!(tlk.alternative);
+
+        assert_eq!(0, sample().apply(&mut tlk, TranslationMerge::All));
+        assert!(tlk.entries.is_empty());
+    }
+
+    #[test]
+    fn to_tlk_falls_back_to_source() {
+        let tlk = sample().to_tlk();
+
+        assert!(!tlk.alternative);
+        assert_eq!(LanguageId::Polish, tlk.language_id);
+        assert_eq!(
+            vec!["Zły Strref", "", "Say \"hi\",\n\tfriend\\", "a, b"],
+            strings(&tlk)
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use std::io::prelude::*;
+
+use crate::types::{
+    Error as MyError,
+    LanguageId,
+};
+
+use super::{
+    TlkTranslation,
+    TranslationUnit,
+    line_error,
+    parse_sound,
+    parse_strref,
+};
+
+const SOUND_RESREF: &str = "sound_resref:";
+const SOUND_LENGTH: &str = "sound_length:";
+const SOURCE_LANGUAGE: &str = "X-Source-Language:";
+const LANGUAGE: &str = "Language:";
+
+fn escape(s: &str)
+    -> String
+{
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+fn unescape(s: &str)
+    -> String
+{
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+/// Strings spanning several lines are written the way gettext does, one
+/// quoted line per line of text after an empty first one.
+fn write_field<W: Write>(writer: &mut W, keyword: &str, s: &str)
+    -> Result<(), MyError>
+{
+    let lines = s.split_inclusive('\n').collect::<Vec<&str>>();
+
+    if lines.len() < 2 {
+        writeln!(writer, "{} \"{}\"", keyword, escape(s))?;
+        return Ok(());
+    }
+
+    writeln!(writer, "{} \"\"", keyword)?;
+
+    for line in lines {
+        writeln!(writer, "\"{}\"", escape(line))?;
+    }
+
+    Ok(())
+}
+
+pub fn write<W: Write>(translation: &TlkTranslation, writer: &mut W)
+    -> Result<(), MyError>
+{
+    writeln!(writer, "msgid \"\"")?;
+    writeln!(writer, "msgstr \"\"")?;
+    writeln!(writer, "\"MIME-Version: 1.0\\n\"")?;
+    writeln!(writer, "\"Content-Type: text/plain; charset=UTF-8\\n\"")?;
+    writeln!(writer, "\"Content-Transfer-Encoding: 8bit\\n\"")?;
+    writeln!(writer, "\"{} {}\\n\"", LANGUAGE, translation.target_language.code())?;
+    writeln!(writer, "\"{} {}\\n\"", SOURCE_LANGUAGE, translation.source_language.code())?;
+
+    for unit in translation.units.iter() {
+        writeln!(writer)?;
+
+        if let Some(sound) = &unit.sound {
+            writeln!(writer, "#. {} {}", SOUND_RESREF, *sound.res_ref)?;
+
+            if let Some(sound_length) = sound.sound_length {
+                writeln!(writer, "#. {} {}", SOUND_LENGTH, sound_length)?;
+            }
+        }
+
+        writeln!(writer, "msgctxt \"{}\"", unit.strref.0)?;
+        write_field(writer, "msgid", &unit.source)?;
+        write_field(writer, "msgstr", unit.target.as_deref().unwrap_or_default())?;
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Context,
+    Id,
+    Str,
+}
+
+#[derive(Default)]
+struct Message {
+    line: usize,
+    sound_res_ref: String,
+    sound_length: String,
+    fuzzy: bool,
+    context: Option<String>,
+    id: Option<String>,
+    string: Option<String>,
+}
+
+impl Message {
+    fn is_empty(&self)
+        -> bool
+    {
+        self.context.is_none() && self.id.is_none() && self.string.is_none()
+    }
+
+    fn field(&mut self, field: Field)
+        -> &mut Option<String>
+    {
+        match field {
+            Field::Context => &mut self.context,
+            Field::Id => &mut self.id,
+            Field::Str => &mut self.string,
+        }
+    }
+}
+
+fn quoted(s: &str, line: usize)
+    -> Result<String, MyError>
+{
+    let s = s.trim();
+
+    if s.len() < 2 || !s.starts_with('"') || !s.ends_with('"') {
+        return Err(line_error(line, "Expected a quoted string."));
+    }
+
+    Ok(unescape(&s[1..s.len() - 1]))
+}
+
+/// Header fields this tlk cares about, the languages.
+fn parse_header(header: &str, translation: &mut TlkTranslation)
+{
+    for field in header.lines() {
+        if let Some(code) = field.strip_prefix(SOURCE_LANGUAGE) {
+            translation.source_language = LanguageId::from_code(code);
+        } else if let Some(code) = field.strip_prefix(LANGUAGE) {
+            translation.target_language = LanguageId::from_code(code);
+        }
+    }
+}
+
+fn finish(message: Message, translation: &mut TlkTranslation)
+    -> Result<(), MyError>
+{
+    if message.is_empty() {
+        return Ok(());
+    }
+
+    let (id, string) = match (message.id, message.string) {
+        (Some(id), Some(string)) => (id, string),
+        _ => return Err(line_error(message.line, "A message needs both msgid and msgstr.")),
+    };
+
+    let context = match message.context {
+        Some(context) => context,
+        None if id.is_empty() => {
+            parse_header(&string, translation);
+            return Ok(());
+        },
+        None => return Err(line_error(message.line, "Message has no StrRef msgctxt.")),
+    };
+
+    let target = if message.fuzzy || string.is_empty() {
+        None
+    } else {
+        Some(string)
+    };
+
+    translation.units.push(TranslationUnit {
+        strref: parse_strref(&context, message.line)?,
+        source: id,
+        target,
+        sound: parse_sound(&message.sound_res_ref, &message.sound_length, message.line)?,
+    });
+
+    Ok(())
+}
+
+/// Fuzzy messages count as untranslated, obsolete `#~` ones are dropped.
+pub fn parse(text: &str)
+    -> Result<TlkTranslation, MyError>
+{
+    let mut translation = TlkTranslation {
+        source_language: LanguageId::Unknown,
+        target_language: LanguageId::Unknown,
+        units: Vec::new(),
+    };
+
+    let mut message = Message::default();
+    let mut last = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        // Anything but a continuation after msgstr starts the next message.
+        if message.string.is_some() && !line.starts_with('"') {
+            finish(std::mem::take(&mut message), &mut translation)?;
+            last = None;
+        }
+
+        if message.is_empty() {
+            message.line = i;
+        }
+
+        if line.is_empty() || line.starts_with("#~") {
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix("#.") {
+            let comment = comment.trim();
+
+            if let Some(res_ref) = comment.strip_prefix(SOUND_RESREF) {
+                message.sound_res_ref = String::from(res_ref.trim());
+            } else if let Some(sound_length) = comment.strip_prefix(SOUND_LENGTH) {
+                message.sound_length = String::from(sound_length.trim());
+            }
+        } else if let Some(flags) = line.strip_prefix("#,") {
+            message.fuzzy |= flags.split(',').any(|f| f.trim() == "fuzzy");
+        } else if line.starts_with('#') {
+            continue;
+        } else if line.starts_with("msgid_plural") || line.starts_with("msgstr[") {
+            return Err(line_error(i, "Plural forms aren't supported."));
+        } else if line.starts_with('"') {
+            let field = last.ok_or_else(|| line_error(i, "String without a keyword."))?;
+            let continued = quoted(line, i)?;
+
+            if let Some(value) = message.field(field) {
+                value.push_str(&continued);
+            }
+        } else {
+            let (field, rest) = if let Some(rest) = line.strip_prefix("msgctxt") {
+                (Field::Context, rest)
+            } else if let Some(rest) = line.strip_prefix("msgid") {
+                (Field::Id, rest)
+            } else if let Some(rest) = line.strip_prefix("msgstr") {
+                (Field::Str, rest)
+            } else {
+                return Err(line_error(i, &format!("Unexpected <{}>.", line)));
+            };
+
+            if message.field(field).is_some() {
+                return Err(line_error(i, "Duplicate keyword in a message."));
+            }
+
+            *message.field(field) = Some(quoted(rest, i)?);
+            last = Some(field);
+        }
+    }
+
+    finish(message, &mut translation)?;
+
+    Ok(translation)
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::types::StrRef;
+
+    #[test]
+    fn write_po() {
+        let mut bytes = Vec::new();
+        write(&super::super::test::sample(), &mut bytes).unwrap();
+
+        let text = String::from_utf8(bytes).unwrap();
+
+        assert!(text.contains("\"Language: pl\\n\"\n\"X-Source-Language: en\\n\"\n"));
+        assert!(text.contains(concat!(
+            "#. sound_resref: vs_hi\n",
+            "#. sound_length: 1.5\n",
+            "msgctxt \"2\"\n",
+            "msgid \"\"\n",
+            "\"Say \\\"hi\\\",\\n\"\n",
+            "\"\\tfriend\\\\\"\n",
+            "msgstr \"\"\n",
+        )));
+    }
+
+    #[test]
+    fn parse_po_from_tools() {
+        let text = concat!(
+            "# translator comment\n",
+            "msgid \"\"\n",
+            "msgstr \"\"\n",
+            "\"Language: ko_KR\\n\"\n",
+            "#, fuzzy\n",
+            "msgctxt \"16777217\"\n",
+            "msgid \"Hello\"\n",
+            "msgstr \"안녕\"\n",
+            "msgctxt \"16777218\"\n",
+            "msgid \"\"\n",
+            "\"Good\"\n",
+            "\"bye\"\n",
+            "msgstr \"잘\"\n",
+            "\"가\"\n",
+            "\n",
+            "#~ msgctxt \"5\"\n",
+            "#~ msgid \"Old\"\n",
+        );
+
+        let translation = parse(text).unwrap();
+
+        assert_eq!(LanguageId::Korean, translation.target_language);
+        assert_eq!(LanguageId::Unknown, translation.source_language);
+        assert_eq!(2, translation.units.len());
+        assert_eq!(StrRef::custom(1), translation.units[0].strref);
+        assert_eq!(None, translation.units[0].target);
+        assert_eq!("Goodbye", translation.units[1].source);
+        assert_eq!(Some(String::from("잘가")), translation.units[1].target);
+    }
+
+    #[test]
+    fn parse_po_errors() {
+        let e = parse("msgid \"a\"\nmsgstr \"b\"\n").unwrap_err();
+        assert!(matches!(e, MyError::InvalidTranslation(1, _)), "{:?}", e);
+
+        let e = parse("msgctxt \"1\"\nmsgid \"a\"\nmsgid_plural \"as\"\n").unwrap_err();
+        assert!(matches!(e, MyError::InvalidTranslation(3, _)), "{:?}", e);
+
+        let e = parse("msgctxt \"x\"\nmsgid \"a\"\nmsgstr \"b\"\n").unwrap_err();
+        assert!(matches!(e, MyError::InvalidTranslation(1, _)), "{:?}", e);
+    }
+}
@@ -0,0 +1,209 @@
+use std::io::prelude::*;
+
+use crate::types::{
+    Error as MyError,
+    LanguageId,
+};
+
+use super::{
+    TlkTranslation,
+    TranslationUnit,
+    line_error,
+    parse_sound,
+    parse_strref,
+};
+
+const SOUND_RESREF: &str = "x-sound-resref";
+const SOUND_LENGTH: &str = "x-sound-length";
+
+/// Escapes text for element content and attributes. Carriage returns are
+/// written as references since parsers normalize raw ones away, control
+/// characters can't be written in XML 1.0 at all.
+fn escape(s: &str)
+    -> Result<String, MyError>
+{
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' | '\n' => escaped.push(c),
+            c if (c as u32) < 0x20 => return Err(MyError::UnencodableString(String::from(s), "XML")),
+            c => escaped.push(c),
+        }
+    }
+
+    Ok(escaped)
+}
+
+pub fn write<W: Write>(translation: &TlkTranslation, writer: &mut W)
+    -> Result<(), MyError>
+{
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">"#)?;
+    writeln!(
+        writer,
+        r#"  <file original="tlk" datatype="plaintext" source-language="{}" target-language="{}">"#,
+        translation.source_language.code(),
+        translation.target_language.code()
+    )?;
+    writeln!(writer, "    <body>")?;
+
+    for unit in translation.units.iter() {
+        writeln!(writer, r#"      <trans-unit id="{}" xml:space="preserve">"#, unit.strref.0)?;
+        writeln!(writer, "        <source>{}</source>", escape(&unit.source)?)?;
+
+        if let Some(target) = &unit.target {
+            writeln!(writer, "        <target>{}</target>", escape(target)?)?;
+        }
+
+        if let Some(sound) = &unit.sound {
+            writeln!(writer, r#"        <context-group purpose="information">"#)?;
+            writeln!(
+                writer,
+                r#"          <context context-type="{}">{}</context>"#,
+                SOUND_RESREF,
+                escape(&sound.res_ref.to_string())?
+            )?;
+
+            if let Some(sound_length) = sound.sound_length {
+                writeln!(
+                    writer,
+                    r#"          <context context-type="{}">{}</context>"#,
+                    SOUND_LENGTH,
+                    sound_length
+                )?;
+            }
+
+            writeln!(writer, "        </context-group>")?;
+        }
+
+        writeln!(writer, "      </trans-unit>")?;
+    }
+
+    writeln!(writer, "    </body>")?;
+    writeln!(writer, "  </file>")?;
+    writeln!(writer, "</xliff>")?;
+
+    Ok(())
+}
+
+/// All the text inside `node`, inline markup like `<g>` is dropped.
+fn inner_text(node: roxmltree::Node)
+    -> String
+{
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect()
+}
+
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str)
+    -> Option<roxmltree::Node<'a, 'input>>
+{
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+/// Targets that are empty or still in the `new` or `needs-translation`
+/// state count as untranslated.
+pub fn parse(text: &str)
+    -> Result<TlkTranslation, MyError>
+{
+    let document = roxmltree::Document::parse(text)
+        .map_err(|e| MyError::InvalidTranslation(e.pos().row as usize, e.to_string()))?;
+
+    let mut translation = TlkTranslation {
+        source_language: LanguageId::Unknown,
+        target_language: LanguageId::Unknown,
+        units: Vec::new(),
+    };
+
+    if let Some(file) = document.descendants().find(|n| n.has_tag_name("file")) {
+        translation.source_language = LanguageId::from_code(file.attribute("source-language").unwrap_or_default());
+        translation.target_language = LanguageId::from_code(file.attribute("target-language").unwrap_or_default());
+    }
+
+    for unit in document.descendants().filter(|n| n.has_tag_name("trans-unit")) {
+        // Zero based like the lines of the other formats.
+        let line = document.text_pos_at(unit.range().start).row as usize - 1;
+
+        let id = unit
+            .attribute("id")
+            .ok_or_else(|| line_error(line, "trans-unit has no id."))?;
+
+        let source = child(unit, "source")
+            .map(inner_text)
+            .ok_or_else(|| line_error(line, "trans-unit has no source."))?;
+
+        let target = child(unit, "target")
+            .filter(|t| !matches!(t.attribute("state"), Some("new") | Some("needs-translation")))
+            .map(inner_text)
+            .filter(|t| !t.is_empty());
+
+        let context = |context_type: &str| {
+            unit.descendants()
+                .find(|n| n.has_tag_name("context") && n.attribute("context-type") == Some(context_type))
+                .map(inner_text)
+                .unwrap_or_default()
+        };
+
+        translation.units.push(TranslationUnit {
+            strref: parse_strref(id, line)?,
+            source,
+            target,
+            sound: parse_sound(context(SOUND_RESREF).trim(), context(SOUND_LENGTH).trim(), line)?,
+        });
+    }
+
+    Ok(translation)
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::types::StrRef;
+
+    #[test]
+    fn parse_xliff_from_tools() {
+        let text = r#"<?xml version="1.0"?>
+<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">
+ <file original="dialog.tlk" source-language="en-US" target-language="zh-TW" datatype="plaintext">
+  <body>
+   <group>
+    <trans-unit id="7"><source>Hello <g id="1">there</g></source><target state="translated">你好</target></trans-unit>
+    <trans-unit id="8"><source>Bye</source><target state="needs-translation">Bye</target></trans-unit>
+   </group>
+  </body>
+ </file>
+</xliff>"#;
+
+        let translation = parse(text).unwrap();
+
+        assert_eq!(LanguageId::English, translation.source_language);
+        assert_eq!(LanguageId::ChineseTraditional, translation.target_language);
+        assert_eq!(StrRef(7), translation.units[0].strref);
+        assert_eq!("Hello there", translation.units[0].source);
+        assert_eq!(Some(String::from("你好")), translation.units[0].target);
+        assert_eq!(None, translation.units[1].target);
+    }
+
+    #[test]
+    fn parse_xliff_errors() {
+        let e = parse("<xliff>\n<trans-unit id=\"1\"></trans-unit>\n</xliff>").unwrap_err();
+        assert!(matches!(e, MyError::InvalidTranslation(2, _)), "{:?}", e);
+
+        let e = parse("<xliff>\n<file>\n</xliff>").unwrap_err();
+        assert!(matches!(e, MyError::InvalidTranslation(3, _)), "{:?}", e);
+    }
+
+    #[test]
+    fn write_xliff_control_characters() {
+        assert_eq!("a&#13;\n&lt;&amp;&gt;", escape("a\r\n<&>").unwrap());
+        assert!(escape("\u{1}").is_err());
+    }
+}
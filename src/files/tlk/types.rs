@@ -1,11 +1,11 @@
 use std::io::prelude::*;
-use std::mem;
 
 use crate::types::{
     Version,
     FileType,
     LanguageId,
     ResRef,
+    Encoding,
     StaticByteSize,
     SerializeToBytes,
     Error as MyError,
@@ -80,7 +80,7 @@ impl SerializeToBytes for TlkData {
         -> Result<(), MyError>
     {
         writer.write(&(self.flags as u32).to_le_bytes())?;
-        writer.write(&self.sound_res_ref.serialize())?;
+        writer.write(&self.sound_res_ref.serialize()?)?;
         writer.write(&[0; 8])?;
         writer.write(&(self.offset_to_string as u32).to_le_bytes())?;
         writer.write(&(self.string_size as u32).to_le_bytes())?;
@@ -99,60 +99,112 @@ pub struct TlkFile {
     pub entries: Vec<TlkEntry>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TlkSound {
     pub res_ref: ResRef,
     pub sound_length: Option<f32>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct TlkEntry {
     pub string: String,
     pub sound: Option<TlkSound>
 }
 
+/// Strings are encoded with Windows-1252, the code page of English tlks.
+/// `serialize_entries` writes them with another encoding.
 impl SerializeToBytes for Vec<TlkEntry>
 {
-    fn serialize_to<F: Write>(mut self, writer: &mut F)
+    fn serialize_to<F: Write>(self, writer: &mut F)
         -> Result<(), MyError>
     {
-        let string_data_list = self
-            .iter_mut()
-            .scan(0, |state, entry| {
-                let flags = TlkFlags::u8_from_tlk_entry(&entry);
-                let offset_to_string = *state;
-                let string_size = entry.string.len();
-
-                let sound = mem::replace(&mut entry.sound, None);
-                
-                let sound_length = sound
+        let count = self.len();
+        serialize_entries(self.into_iter().enumerate(), count, LanguageId::English.encoding(), writer)
+    }
+}
+
+/// Writes the string data table for `count` entries followed by the
+/// strings, encoded with `encoding`. `entries` holds indices in strictly
+/// ascending order below `count`, missing ones are written as empty entries
+/// with no flags. Indices out of order are `TlkEntryOutOfOrder`. Strings
+/// with characters the encoding can't represent are an error rather than
+/// being written as something else.
+pub fn serialize_entries<I, F>(
+    entries: I,
+    count: usize,
+    encoding: &'static Encoding,
+    writer: &mut F
+)
+    -> Result<(), MyError>
+    where I: IntoIterator<Item = (usize, TlkEntry)>, F: Write
+{
+    let mut next_index = 0;
+
+    let entries = entries
+        .into_iter()
+        .map(|(index, entry)| {
+            if index < next_index || index >= count {
+                return Err(MyError::TlkEntryOutOfOrder(index));
+            }
+
+            next_index = index + 1;
+
+            let (bytes, _, unmappable) = encoding.encode(&entry.string);
+
+            if unmappable {
+                return Err(MyError::UnencodableString(entry.string.clone(), encoding.name()));
+            }
+
+            let string = bytes.into_owned();
+
+            Ok((index, entry, string))
+        })
+        .collect::<Result<Vec<_>, MyError>>()?;
+
+    let mut present = entries.iter().peekable();
+    let mut offset_to_string = 0;
+
+    // Written one at a time, a sparse tlk can have millions of gaps.
+    for index in 0..count {
+        let data = match present.next_if(|(i, _, _)| *i == index) {
+            Some((_, entry, string)) => {
+                let flags = TlkFlags::u8_from_tlk_entry(entry);
+                let string_size = string.len();
+
+                let sound_length = entry.sound
                     .as_ref()
                     .and_then(|s| s.sound_length)
                     .unwrap_or(0.0);
 
-                let sound_res_ref = sound
-                    .map(|s| s.res_ref)
-                    .unwrap_or(ResRef::default());
-                
-
-                *state = *state + string_size;
+                let sound_res_ref = entry.sound
+                    .as_ref()
+                    .map(|s| s.res_ref.clone())
+                    .unwrap_or_default();
 
-                Some(TlkData {
+                TlkData {
                     flags,
                     sound_res_ref,
                     string_size,
                     offset_to_string,
                     sound_length,
-                })
-            })
-            .collect::<Vec<TlkData>>();
-
-        string_data_list.serialize_to(writer)?;
+                }
+            },
+            None => TlkData {
+                flags: 0,
+                sound_res_ref: ResRef::default(),
+                string_size: 0,
+                offset_to_string,
+                sound_length: 0.0,
+            },
+        };
+
+        offset_to_string += data.string_size;
+        data.serialize_to(writer)?;
+    }
 
-        for entry in self {
-            write!(writer, "{}", entry.string)?;
-        }
-            
-        Ok(())
+    for (_, _, string) in entries {
+        writer.write_all(&string)?;
     }
-}
\ No newline at end of file
+        
+    Ok(())
+}
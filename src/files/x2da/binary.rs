@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::io::prelude::*;
+
+use crate::helpers::conversion::{
+    checked_slice,
+    checked_u16_from_bytes,
+    checked_u32_from_bytes,
+};
+
+use crate::types::{
+    Error as MyError,
+    Version,
+    NULL_STRING,
+};
+
+use super::parser::{X2daLines, X2daLine};
+use super::types::X2daError;
+
+/// The header plus the newline that always follows it.
+const MAGIC: &[u8] = b"2DA V2.b\n";
+
+pub fn is_binary(bytes: &[u8])
+    -> bool
+{
+    bytes.starts_with(&MAGIC[..8])
+}
+
+fn read_until(bytes: &[u8], offset: usize, end: u8)
+    -> Result<(String, usize), MyError>
+{
+    let rest = bytes.get(offset..).ok_or(MyError::OffsetOutOfBounds(offset))?;
+    let len = rest
+        .iter()
+        .position(|b| *b == end)
+        .ok_or(MyError::OffsetOutOfBounds(bytes.len()))?;
+
+    Ok((String::from_utf8_lossy(&rest[..len]).into_owned(), offset + len + 1))
+}
+
+/// Layout: the magic line, tab terminated column names closed by a null,
+/// the row count, tab terminated row labels, a u16 offset per cell into the
+/// string data, the size of the string data and finally the null terminated
+/// strings themselves. Empty strings are the binary form of `****`.
+pub fn parse(bytes: &[u8])
+    -> Result<X2daLines, MyError>
+{
+    let mut offset = MAGIC.len();
+
+    let (column_string, next) = read_until(bytes, offset, 0)?;
+    offset = next;
+
+    let columns = column_string
+        .split('\t')
+        .filter(|c| !c.is_empty())
+        .map(String::from)
+        .collect::<Vec<String>>();
+
+    let row_count = checked_u32_from_bytes(bytes, offset)? as usize;
+    offset += 4;
+
+    // every label takes at least its tab, so a count past the remaining
+    // bytes comes from a corrupt header
+    if row_count > bytes.len() - offset {
+        return Err(MyError::OffsetOutOfBounds(bytes.len()));
+    }
+
+    let mut labels = Vec::with_capacity(row_count);
+
+    for _ in 0..row_count {
+        let (label, next) = read_until(bytes, offset, b'\t')?;
+        labels.push(label);
+        offset = next;
+    }
+
+    let cell_count = row_count * columns.len();
+    let offsets = checked_slice(bytes, offset, cell_count * 2)?;
+    offset += cell_count * 2;
+
+    let data_size = checked_u16_from_bytes(bytes, offset)? as usize;
+    offset += 2;
+
+    let data = checked_slice(bytes, offset, data_size)?;
+
+    let rows = labels
+        .into_iter()
+        .enumerate()
+        .map(|(row, label)| {
+            let cells = (0..columns.len())
+                .map(|column| {
+                    let i = (row * columns.len() + column) * 2;
+                    let cell_offset = checked_u16_from_bytes(offsets, i)? as usize;
+                    let (cell, _) = read_until(data, cell_offset, 0)?;
+
+                    Ok(match cell.as_str() {
+                        "" | NULL_STRING => None,
+                        _ => Some(cell),
+                    })
+                })
+                .collect::<Result<Vec<_>, MyError>>()?;
+
+            Ok(X2daLine {
+                line: row + 1,
+                label,
+                cells,
+            })
+        })
+        .collect::<Result<Vec<_>, MyError>>()?;
+
+    Ok(X2daLines {
+        version: Version::V2B,
+        default: None,
+        columns,
+        rows,
+    })
+}
+
+/// Takes rows serialized for the text form, `****` and the quotes around
+/// strings are dropped. Equal strings share their data.
+pub fn write<W: Write>(
+    writer: &mut W,
+    columns: &[String],
+    labels: &[String],
+    string_rows: &[Vec<String>]
+)
+    -> Result<(), MyError>
+{
+    writer.write_all(MAGIC)?;
+
+    for column in columns.iter() {
+        writer.write_all(column.as_bytes())?;
+        writer.write_all(b"\t")?;
+    }
+
+    writer.write_all(&[0])?;
+    writer.write_all(&(string_rows.len() as u32).to_le_bytes())?;
+
+    for i in 0..string_rows.len() {
+        match labels.get(i) {
+            Some(label) => writer.write_all(label.as_bytes())?,
+            None => writer.write_all(i.to_string().as_bytes())?,
+        }
+
+        writer.write_all(b"\t")?;
+    }
+
+    let mut data = Vec::new();
+    let mut data_offsets: HashMap<&str, u16> = HashMap::new();
+    let mut offsets = Vec::with_capacity(string_rows.len() * columns.len() * 2);
+
+    for cell in string_rows.iter().flat_map(|r| r.iter()) {
+        let value = match cell.as_str() {
+            NULL_STRING => "",
+            c if c.len() >= 2 && c.starts_with('"') && c.ends_with('"') => &c[1..c.len() - 1],
+            c => c,
+        };
+
+        let offset = match data_offsets.get(value) {
+            Some(offset) => *offset,
+            None => {
+                let offset = data.len();
+
+                if offset > u16::MAX as usize {
+                    Err(X2daError::X2daBinaryTooLarge)?;
+                }
+
+                data.extend_from_slice(value.as_bytes());
+                data.push(0);
+                data_offsets.insert(value, offset as u16);
+
+                offset as u16
+            },
+        };
+
+        offsets.extend_from_slice(&offset.to_le_bytes());
+    }
+
+    if data.len() > u16::MAX as usize {
+        Err(X2daError::X2daBinaryTooLarge)?;
+    }
+
+    writer.write_all(&offsets)?;
+    writer.write_all(&(data.len() as u16).to_le_bytes())?;
+    writer.write_all(&data)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn strings(s: &[&str])
+        -> Vec<String>
+    {
+        s.iter().map(|s| String::from(*s)).collect()
+    }
+
+    #[test]
+    fn write_binary_layout() {
+        let mut bytes = Vec::new();
+
+        write(
+            &mut bytes,
+            &strings(&["A", "B"]),
+            &strings(&["0", "5"]),
+            &[strings(&["\"x y\"", "****"]), strings(&["z", "x y"])],
+        ).unwrap();
+
+        let mut expected = b"2DA V2.b\nA\tB\t\0".to_vec();
+        expected.extend_from_slice(&2u32.to_le_bytes());
+        expected.extend_from_slice(b"0\t5\t");
+        expected.extend_from_slice(&[0, 0, 4, 0, 5, 0, 0, 0]);
+        expected.extend_from_slice(&7u16.to_le_bytes());
+        expected.extend_from_slice(b"x y\0\0z\0");
+
+        assert_eq!(expected, bytes);
+    }
+
+    #[test]
+    fn parse_binary_round_trip() {
+        let mut bytes = Vec::new();
+
+        write(
+            &mut bytes,
+            &strings(&["A", "B"]),
+            &strings(&["0", "5"]),
+            &[strings(&["\"x y\"", "****"]), strings(&["z", "x y"])],
+        ).unwrap();
+
+        assert!(is_binary(&bytes));
+
+        let lines = parse(&bytes).unwrap();
+
+        assert_eq!(Version::V2B, lines.version);
+        assert_eq!(strings(&["A", "B"]), lines.columns);
+        assert_eq!("5", lines.rows[1].label);
+        assert_eq!(vec![Some(String::from("x y")), None], lines.rows[0].cells);
+        assert_eq!(vec![Some(String::from("z")), Some(String::from("x y"))], lines.rows[1].cells);
+    }
+
+    #[test]
+    fn parse_binary_truncated() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, &strings(&["A"]), &[], &[strings(&["a"])]).unwrap();
+        bytes.truncate(bytes.len() - 3);
+
+        match parse(&bytes) {
+            Err(MyError::OffsetOutOfBounds(_)) => {},
+            other => panic!("unexpected {:?}", other.map(|l| l.columns)),
+        }
+    }
+
+    #[test]
+    fn parse_binary_huge_row_count() {
+        let mut bytes = b"2DA V2.b\nA\t\0".to_vec();
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(b"0\t");
+
+        match parse(&bytes) {
+            Err(MyError::OffsetOutOfBounds(_)) => {},
+            other => panic!("unexpected {:?}", other.map(|l| l.columns)),
+        }
+    }
+
+    #[test]
+    fn write_binary_too_large() {
+        let row = vec![String::from("a").repeat(70000)];
+
+        match write(&mut Vec::new(), &strings(&["A"]), &[], &[row]) {
+            Err(MyError::X2daError(X2daError::X2daBinaryTooLarge)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use std::fmt;
+
+use crate::types::NULL_STRING;
+
+use super::dynamic_x2da::Dynamic2da;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum X2daChange {
+    ColumnAdded {
+        column: String,
+    },
+    ColumnRemoved {
+        column: String,
+    },
+    ColumnRenamed {
+        from: String,
+        to: String,
+    },
+    RowAdded {
+        row: usize,
+        cells: Vec<Option<String>>,
+    },
+    RowRemoved {
+        row: usize,
+        cells: Vec<Option<String>>,
+    },
+    /// `column` is the name in the new table.
+    CellChanged {
+        row: usize,
+        column: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+}
+
+/// Structural differences between two versions of a 2da, independent of how
+/// either one is laid out as text.
+///
+/// Columns are matched by case insensitive name. A removed and an added
+/// column at the same position count as a rename when at least half of the
+/// rows both tables have keep their value. Rows are matched by index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct X2daDiff {
+    pub changes: Vec<X2daChange>,
+}
+
+impl X2daDiff {
+    pub fn new(old: &Dynamic2da, new: &Dynamic2da)
+        -> Self
+    {
+        let mut changes = Vec::new();
+
+        // (old index, new index) of every column both tables have.
+        let mut pairs = Vec::new();
+
+        for (n, column) in new.columns.iter().enumerate() {
+            if let Some(o) = old.column_index(column) {
+                pairs.push((o, n));
+            }
+        }
+
+        let unmatched_old = (0..old.columns.len())
+            .filter(|o| !pairs.iter().any(|(p, _)| p == o))
+            .collect::<Vec<usize>>();
+
+        let unmatched_new = (0..new.columns.len())
+            .filter(|n| !pairs.iter().any(|(_, p)| p == n))
+            .collect::<Vec<usize>>();
+
+        let mut renamed = Vec::new();
+
+        for o in unmatched_old.iter() {
+            if unmatched_new.contains(o) && same_values(old, *o, new, *o) {
+                renamed.push(*o);
+                pairs.push((*o, *o));
+
+                changes.push(X2daChange::ColumnRenamed {
+                    from: old.columns[*o].clone(),
+                    to: new.columns[*o].clone(),
+                });
+            }
+        }
+
+        for o in unmatched_old.iter().filter(|o| !renamed.contains(o)) {
+            changes.push(X2daChange::ColumnRemoved { column: old.columns[*o].clone() });
+        }
+
+        for n in unmatched_new.iter().filter(|n| !renamed.contains(n)) {
+            changes.push(X2daChange::ColumnAdded { column: new.columns[*n].clone() });
+        }
+
+        pairs.sort_by_key(|(_, n)| *n);
+
+        for row in 0..old.len().min(new.len()) {
+            for (o, n) in pairs.iter() {
+                let old_cell = cell_at(old, row, *o);
+                let new_cell = cell_at(new, row, *n);
+
+                if old_cell != new_cell {
+                    changes.push(X2daChange::CellChanged {
+                        row,
+                        column: new.columns[*n].clone(),
+                        old: old_cell.clone(),
+                        new: new_cell.clone(),
+                    });
+                }
+            }
+        }
+
+        for row in new.len()..old.len() {
+            changes.push(X2daChange::RowRemoved { row, cells: old.rows[row].clone() });
+        }
+
+        for row in old.len()..new.len() {
+            changes.push(X2daChange::RowAdded { row, cells: new.rows[row].clone() });
+        }
+
+        X2daDiff {
+            changes,
+        }
+    }
+
+    pub fn is_empty(&self)
+        -> bool
+    {
+        self.changes.is_empty()
+    }
+
+    /// `{"changes": [...]}` with one object per change, tagged by `type`.
+    /// Empty cells are `null`.
+    pub fn to_json(&self)
+        -> String
+    {
+        let changes = self.changes
+            .iter()
+            .map(|change| match change {
+                X2daChange::ColumnAdded { column } =>
+                    format!(r#"{{"type":"column_added","column":{}}}"#, json_string(column)),
+                X2daChange::ColumnRemoved { column } =>
+                    format!(r#"{{"type":"column_removed","column":{}}}"#, json_string(column)),
+                X2daChange::ColumnRenamed { from, to } =>
+                    format!(
+                        r#"{{"type":"column_renamed","from":{},"to":{}}}"#,
+                        json_string(from), json_string(to)
+                    ),
+                X2daChange::RowAdded { row, cells } =>
+                    format!(r#"{{"type":"row_added","row":{},"cells":{}}}"#, row, json_cells(cells)),
+                X2daChange::RowRemoved { row, cells } =>
+                    format!(r#"{{"type":"row_removed","row":{},"cells":{}}}"#, row, json_cells(cells)),
+                X2daChange::CellChanged { row, column, old, new } =>
+                    format!(
+                        r#"{{"type":"cell_changed","row":{},"column":{},"old":{},"new":{}}}"#,
+                        row, json_string(column), json_cell(old), json_cell(new)
+                    ),
+            })
+            .collect::<Vec<String>>();
+
+        format!(r#"{{"changes":[{}]}}"#, changes.join(","))
+    }
+}
+
+/// Whether at least half of the rows both tables have agree on the column.
+/// Without shared rows there is nothing to compare, so it never matches.
+fn same_values(old: &Dynamic2da, o: usize, new: &Dynamic2da, n: usize)
+    -> bool
+{
+    let rows = old.len().min(new.len());
+
+    let equal = (0..rows)
+        .filter(|row| cell_at(old, *row, o) == cell_at(new, *row, n))
+        .count();
+
+    rows > 0 && equal * 2 >= rows
+}
+
+/// The cell at `row` and `column`, empty where the row is too short.
+fn cell_at(table: &Dynamic2da, row: usize, column: usize)
+    -> &Option<String>
+{
+    table.rows
+        .get(row)
+        .and_then(|r| r.get(column))
+        .unwrap_or(&None)
+}
+
+fn cell_str(cell: &Option<String>)
+    -> &str
+{
+    cell.as_deref().unwrap_or(NULL_STRING)
+}
+
+fn json_string(s: &str)
+    -> String
+{
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+fn json_cell(cell: &Option<String>)
+    -> String
+{
+    cell.as_deref().map(json_string).unwrap_or_else(|| String::from("null"))
+}
+
+fn json_cells(cells: &[Option<String>])
+    -> String
+{
+    format!("[{}]", cells.iter().map(json_cell).collect::<Vec<String>>().join(","))
+}
+
+impl fmt::Display for X2daChange
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)
+        -> fmt::Result
+    {
+        match self {
+            X2daChange::ColumnAdded { column } =>
+                write!(f, "+ column {}", column),
+            X2daChange::ColumnRemoved { column } =>
+                write!(f, "- column {}", column),
+            X2daChange::ColumnRenamed { from, to } =>
+                write!(f, "~ column {} renamed to {}", from, to),
+            X2daChange::RowAdded { row, cells } =>
+                write!(f, "+ row {}: {}", row, cells.iter().map(cell_str).collect::<Vec<_>>().join(" ")),
+            X2daChange::RowRemoved { row, cells } =>
+                write!(f, "- row {}: {}", row, cells.iter().map(cell_str).collect::<Vec<_>>().join(" ")),
+            X2daChange::CellChanged { row, column, old, new } =>
+                write!(f, "~ row {} {}: {} -> {}", row, column, cell_str(old), cell_str(new)),
+        }
+    }
+}
+
+/// One change per line.
+impl fmt::Display for X2daDiff
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)
+        -> fmt::Result
+    {
+        for change in self.changes.iter() {
+            writeln!(f, "{}", change)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::io::Cursor;
+
+    fn table(s: &str)
+        -> Dynamic2da
+    {
+        let s = format!("2DA V2.0\n\n{}", s);
+        Dynamic2da::parse_from(&mut Cursor::new(s.into_bytes())).unwrap()
+    }
+
+    #[test]
+    fn diff_identical_ignores_layout() {
+        let old = table("Label Cost\n0 sword 10\n");
+        let new = table("label\tcost\n\n0\t\"sword\"\t10\n");
+
+        assert!(X2daDiff::new(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn diff_cells_and_rows() {
+        let old = table("Label Cost\n0 sword 10\n1 axe 20\n2 bow 5\n");
+        let new = table("Label Cost\n0 sword 15\n1 axe ****\n");
+
+        let diff = X2daDiff::new(&old, &new);
+
+        assert_eq!(
+            vec![
+                X2daChange::CellChanged {
+                    row: 0,
+                    column: String::from("Cost"),
+                    old: Some(String::from("10")),
+                    new: Some(String::from("15")),
+                },
+                X2daChange::CellChanged {
+                    row: 1,
+                    column: String::from("Cost"),
+                    old: Some(String::from("20")),
+                    new: None,
+                },
+                X2daChange::RowRemoved {
+                    row: 2,
+                    cells: vec![Some(String::from("bow")), Some(String::from("5"))],
+                },
+            ],
+            diff.changes
+        );
+
+        assert_eq!(
+            "~ row 0 Cost: 10 -> 15\n~ row 1 Cost: 20 -> ****\n- row 2: bow 5\n",
+            diff.to_string()
+        );
+    }
+
+    #[test]
+    fn diff_columns() {
+        let old = table("Label Cost Weight\n0 sword 10 3\n1 axe 20 4\n");
+        let new = table("Name Weight Model\n0 sword 3 w_a\n1 axe 4 w_b\n2 bow 1 ****\n");
+
+        let diff = X2daDiff::new(&old, &new);
+
+        assert_eq!(
+            vec![
+                X2daChange::ColumnRenamed { from: String::from("Label"), to: String::from("Name") },
+                X2daChange::ColumnRemoved { column: String::from("Cost") },
+                X2daChange::ColumnAdded { column: String::from("Model") },
+                X2daChange::RowAdded {
+                    row: 2,
+                    cells: vec![Some(String::from("bow")), Some(String::from("1")), None],
+                },
+            ],
+            diff.changes
+        );
+    }
+
+    #[test]
+    fn diff_columns_without_shared_rows() {
+        let old = table("Label Cost\n");
+        let new = table("Label Price\n0 sword 10\n");
+
+        let diff = X2daDiff::new(&old, &new);
+
+        assert_eq!(
+            vec![
+                X2daChange::ColumnRemoved { column: String::from("Cost") },
+                X2daChange::ColumnAdded { column: String::from("Price") },
+                X2daChange::RowAdded {
+                    row: 0,
+                    cells: vec![Some(String::from("sword")), Some(String::from("10"))],
+                },
+            ],
+            diff.changes
+        );
+    }
+
+    #[test]
+    fn diff_short_rows() {
+        let old = table("Label Cost\n0 sword 10\n");
+        let mut new = old.clone();
+        new.columns.push(String::from("Weight"));
+        new.rows[0].truncate(1);
+
+        let diff = X2daDiff::new(&old, &new);
+
+        assert_eq!(
+            vec![
+                X2daChange::ColumnAdded { column: String::from("Weight") },
+                X2daChange::CellChanged {
+                    row: 0,
+                    column: String::from("Cost"),
+                    old: Some(String::from("10")),
+                    new: None,
+                },
+            ],
+            diff.changes
+        );
+    }
+
+    #[test]
+    fn diff_json() {
+        let old = table("Label Cost\n0 sword 10\n");
+        let new = table("Label Cost\n0 \"long \\ sword\" ****\n");
+
+        let diff = X2daDiff::new(&old, &new);
+
+        assert_eq!(
+            concat!(
+                r#"{"changes":["#,
+                r#"{"type":"cell_changed","row":0,"column":"Label","old":"sword","new":"long \\ sword"},"#,
+                r#"{"type":"cell_changed","row":0,"column":"Cost","old":"10","new":null}"#,
+                r#"]}"#
+            ),
+            diff.to_json()
+        );
+    }
+}
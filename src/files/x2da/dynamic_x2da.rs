@@ -0,0 +1,391 @@
+use std::convert::TryFrom;
+use std::io::prelude::*;
+
+use crate::types::{
+    Error as MyError,
+    ResRef,
+    StrRef,
+    NULL_STRING,
+};
+
+use super::writer;
+use super::parser;
+
+use super::types::{
+    X2daBuilderConfig,
+    X2daHeader,
+    X2daError,
+};
+
+/// A 2da whose columns are only known at runtime, cells are kept as the raw
+/// strings from the file with `****` read as `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dynamic2da {
+    pub header: X2daHeader,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Option<String>>>,
+    pub row_labels: Vec<String>,
+    pub default: Option<String>,
+}
+
+impl Dynamic2da {
+    pub fn new(columns: Vec<String>)
+        -> Self
+    {
+        Dynamic2da {
+            header: X2daHeader::default(),
+            columns,
+            rows: Vec::new(),
+            row_labels: Vec::new(),
+            default: None,
+        }
+    }
+
+    pub fn parse_from<R: BufRead>(reader: &mut R)
+        -> Result<Self, MyError>
+    {
+        let lines = parser::parse_lines(reader)?;
+
+        let mut x2da = Dynamic2da::new(lines.columns);
+        x2da.header.version = lines.version;
+        x2da.default = lines.default;
+
+        for row in lines.rows {
+            x2da.row_labels.push(row.label);
+            x2da.rows.push(row.cells);
+        }
+
+        Ok(x2da)
+    }
+
+    /// The label the row had in the file, its index for added rows.
+    pub fn row_label(&self, row: usize)
+        -> String
+    {
+        self.row_labels
+            .get(row)
+            .cloned()
+            .unwrap_or_else(|| row.to_string())
+    }
+
+    pub fn len(&self)
+        -> usize
+    {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self)
+        -> bool
+    {
+        self.rows.is_empty()
+    }
+
+    /// Column names are matched case insensitively, like the game does.
+    pub fn column_index(&self, column: &str)
+        -> Option<usize>
+    {
+        self.columns
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(column))
+    }
+
+    /// The cell at `row` and `column`, `Ok(None)` for `****`.
+    pub fn cell(&self, row: usize, column: &str)
+        -> Result<Option<&str>, X2daError>
+    {
+        let index = self
+            .column_index(column)
+            .ok_or_else(|| X2daError::X2daUnknownColumn(column.to_owned()))?;
+
+        let row = self
+            .rows
+            .get(row)
+            .ok_or(X2daError::X2daRowOutOfRange(row))?;
+
+        row.get(index)
+            .map(Option::as_deref)
+            .ok_or(X2daError::X2daRowNotEnoughValues)
+    }
+
+    /// Like `cell`, treating unknown rows and columns as empty.
+    pub fn get(&self, row: usize, column: &str)
+        -> Option<&str>
+    {
+        self.cell(row, column).ok().flatten()
+    }
+
+    /// Accepts decimal and `0x` prefixed hex values.
+    pub fn get_u32(&self, row: usize, column: &str)
+        -> Result<Option<u32>, X2daError>
+    {
+        self.cell(row, column)?
+            .map(|v| match v.strip_prefix("0x").or_else(|| v.strip_prefix("0X")) {
+                Some(hex) => u32::from_str_radix(hex, 16),
+                None => v.parse::<u32>(),
+            })
+            .transpose()
+            .or(Err(X2daError::InvalidTableItem))
+    }
+
+    pub fn get_f32(&self, row: usize, column: &str)
+        -> Result<Option<f32>, X2daError>
+    {
+        self.cell(row, column)?
+            .map(|v| v.parse::<f32>())
+            .transpose()
+            .or(Err(X2daError::InvalidTableItem))
+    }
+
+    pub fn get_resref(&self, row: usize, column: &str)
+        -> Result<Option<ResRef>, X2daError>
+    {
+        self.cell(row, column)?
+            .map(ResRef::try_from)
+            .transpose()
+            .or(Err(X2daError::InvalidTableItem))
+    }
+
+    pub fn get_strref(&self, row: usize, column: &str)
+        -> Result<Option<StrRef>, X2daError>
+    {
+        Ok(self.get_u32(row, column)?.map(StrRef))
+    }
+
+    pub fn set(&mut self, row: usize, column: &str, value: Option<String>)
+        -> Result<&mut Self, X2daError>
+    {
+        validate_cell(&value)?;
+
+        let index = self
+            .column_index(column)
+            .ok_or_else(|| X2daError::X2daUnknownColumn(column.to_owned()))?;
+
+        let row = self
+            .rows
+            .get_mut(row)
+            .ok_or(X2daError::X2daRowOutOfRange(row))?;
+
+        *row.get_mut(index).ok_or(X2daError::X2daRowNotEnoughValues)? = value;
+
+        Ok(self)
+    }
+
+    pub fn add_row(&mut self, row: Vec<Option<String>>)
+        -> Result<&mut Self, X2daError>
+    {
+        match row.len() {
+            l if l < self.columns.len() => Err(X2daError::X2daRowNotEnoughValues)?,
+            l if l > self.columns.len() => Err(X2daError::X2daRowTooManyValues)?,
+            _ => {},
+        }
+
+        row.iter().try_for_each(validate_cell)?;
+
+        self.row_labels.push(self.rows.len().to_string());
+        self.rows.push(row);
+
+        Ok(self)
+    }
+
+    pub fn write_with_config<W: Write>(&self, config: X2daBuilderConfig, writer: &mut W)
+        -> Result<(), MyError>
+    {
+        if self.columns.is_empty() {
+            Err(X2daError::X2daWriteWithoutColumns)?;
+        }
+
+        for row in self.rows.iter() {
+            match row.len() {
+                l if l < self.columns.len() => Err(X2daError::X2daRowNotEnoughValues)?,
+                l if l > self.columns.len() => Err(X2daError::X2daRowTooManyValues)?,
+                _ => {},
+            }
+        }
+
+        let string_rows = self.rows
+            .iter()
+            .map(|row| row.iter().map(serialize_cell).collect())
+            .collect::<Vec<Vec<String>>>();
+
+        writer::write_strings(
+            writer,
+            &self.header,
+            &self.columns,
+            &self.row_labels,
+            self.default.as_deref(),
+            &string_rows,
+            &config
+        )
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W)
+        -> Result<(), MyError>
+    {
+        self.write_with_config(X2daBuilderConfig::default(), writer)
+    }
+}
+
+fn validate_cell(value: &Option<String>)
+    -> Result<(), X2daError>
+{
+    match value {
+        Some(v) if v.contains('"') => Err(X2daError::X2daItemContainsQuotes),
+        _ => Ok(()),
+    }
+}
+
+/// Raw cells are only quoted when they'd otherwise split into several.
+fn serialize_cell(value: &Option<String>)
+    -> String
+{
+    match value {
+        None => String::from(NULL_STRING),
+        Some(v) if v.is_empty() || v.contains(char::is_whitespace) => format!("\"{}\"", v),
+        Some(v) => v.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use super::super::data::x2da_file_string;
+    use crate::types::Version;
+    use std::io::Cursor;
+
+    fn sample()
+        -> Dynamic2da
+    {
+        Dynamic2da::parse_from(&mut Cursor::new(x2da_file_string().as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn parse_dynamic() {
+        let x2da = sample();
+
+        assert_eq!(vec!["Melon", "num", "numf"], x2da.columns);
+        assert_eq!(3, x2da.len());
+        assert_eq!(Some("A small melon"), x2da.get(1, "melon"));
+        assert_eq!(Ok(Some(2)), x2da.get_u32(2, "NUM"));
+        assert_eq!(Ok(Some(100.1)), x2da.get_f32(2, "numf"));
+        assert_eq!(None, x2da.get(3, "num"));
+    }
+
+    #[test]
+    fn typed_accessor_errors() {
+        let x2da = sample();
+
+        assert_eq!(Err(X2daError::InvalidTableItem), x2da.get_u32(0, "Melon"));
+        assert_eq!(Err(X2daError::X2daUnknownColumn(String::from("nope"))), x2da.get_f32(0, "nope"));
+        assert_eq!(Err(X2daError::X2daRowOutOfRange(9)), x2da.get_strref(9, "num"));
+    }
+
+    #[test]
+    fn nulls_and_hex() {
+        let data = "2DA V2.0\n\nLabel Model StrRef\n0 a c_a 0x10\n1 b **** ****\n2 c a_resref_much_too_long 1\n";
+        let x2da = Dynamic2da::parse_from(&mut Cursor::new(data.as_bytes())).unwrap();
+
+        assert_eq!(Ok(Some(StrRef(16))), x2da.get_strref(0, "strref"));
+        assert_eq!(Ok(None), x2da.get_resref(1, "Model"));
+        assert_eq!(Some("c_a"), x2da.get_resref(0, "Model").unwrap().as_deref().map(String::as_str));
+        assert_eq!(Err(X2daError::InvalidTableItem), x2da.get_resref(2, "Model"));
+    }
+
+    #[test]
+    fn write_dynamic() {
+        let expected =
+r#"
+2DA V2.0
+
+     Melon              num     numf        
+0    Cantelope          0       3.0         
+1    "A small melon"    ****    3.141592    
+2    Watermelon         2       100.1       
+"#;
+        let mut x2da = sample();
+        x2da.set(1, "num", None).unwrap();
+
+        let mut c = Cursor::new(Vec::new());
+        x2da.write(&mut c).unwrap();
+
+        let expected = expected.trim_start_matches("\n");
+
+        assert_eq!(expected, String::from_utf8(c.into_inner()).unwrap());
+    }
+
+    #[test]
+    fn write_dynamic_keeps_decimal_strings() {
+        let mut x2da = Dynamic2da::new(vec![String::from("Label")]);
+        x2da.add_row(vec![Some(String::from("1.5"))]).unwrap();
+
+        let config = X2daBuilderConfig {
+            float_precision: Some(3),
+            ..Default::default()
+        };
+
+        let mut c = Cursor::new(Vec::new());
+        x2da.write_with_config(config, &mut c).unwrap();
+
+        assert!(String::from_utf8(c.into_inner()).unwrap().contains("0    1.5"));
+    }
+
+    #[test]
+    fn add_row_checks_length() {
+        let mut x2da = Dynamic2da::new(vec![String::from("A"), String::from("B")]);
+
+        assert_eq!(Err(X2daError::X2daRowNotEnoughValues), x2da.add_row(vec![None]).map(|_| ()));
+        assert_eq!(
+            Err(X2daError::X2daItemContainsQuotes),
+            x2da.add_row(vec![None, Some(String::from("a\"b"))]).map(|_| ())
+        );
+        assert!(x2da.add_row(vec![None, Some(String::from("b"))]).is_ok());
+    }
+
+    #[test]
+    fn short_rows() {
+        let mut x2da = Dynamic2da::new(vec![String::from("A"), String::from("B")]);
+        x2da.rows.push(vec![Some(String::from("a"))]);
+
+        assert_eq!(Ok(Some("a")), x2da.cell(0, "A"));
+        assert_eq!(Err(X2daError::X2daRowNotEnoughValues), x2da.cell(0, "B"));
+        assert_eq!(None, x2da.get(0, "B"));
+        assert_eq!(
+            Err(X2daError::X2daRowNotEnoughValues),
+            x2da.set(0, "B", None).map(|_| ())
+        );
+
+        match x2da.write(&mut Cursor::new(Vec::new())) {
+            Err(MyError::X2daError(X2daError::X2daRowNotEnoughValues)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn long_rows() {
+        let mut x2da = Dynamic2da::new(vec![String::from("A")]);
+        x2da.rows.push(vec![Some(String::from("a")), Some(String::from("b"))]);
+
+        assert_eq!(Ok(Some("a")), x2da.cell(0, "A"));
+
+        match x2da.write(&mut Cursor::new(Vec::new())) {
+            Err(MyError::X2daError(X2daError::X2daRowTooManyValues)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut x2da = sample();
+        x2da.header.version = Version::V2B;
+
+        let mut c = Cursor::new(Vec::new());
+        x2da.write(&mut c).unwrap();
+
+        let bytes = c.into_inner();
+        assert!(bytes.starts_with(b"2DA V2.b\n"));
+
+        let parsed = Dynamic2da::parse_from(&mut Cursor::new(bytes)).unwrap();
+
+        assert_eq!(x2da, parsed);
+    }
+}
@@ -0,0 +1,434 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::types::NULL_STRING;
+
+use super::dynamic_x2da::Dynamic2da;
+use super::types::X2daError;
+
+type Row = Vec<Option<String>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeSide {
+    Ours,
+    Theirs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum X2daConflict {
+    /// Both sides changed the same cell of a row differently, ours is kept.
+    Cell {
+        row: usize,
+        column: String,
+        base: Option<String>,
+        ours: Option<String>,
+        theirs: Option<String>,
+    },
+    /// Both sides added a different row at the same index, ours is kept.
+    Row {
+        row: usize,
+        ours: Row,
+        theirs: Row,
+    },
+    /// A side wrote to a row reserved for the other one and was dropped.
+    Reserved {
+        row: usize,
+        owner: MergeSide,
+    },
+}
+
+fn cell_str(cell: &Option<String>)
+    -> &str
+{
+    cell.as_deref().unwrap_or(NULL_STRING)
+}
+
+impl fmt::Display for X2daConflict
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)
+        -> fmt::Result
+    {
+        match self {
+            X2daConflict::Cell { row, column, base, ours, theirs } =>
+                write!(
+                    f,
+                    "Row {} column {} changed on both sides: base <{}>, ours <{}>, theirs <{}>.",
+                    row, column, cell_str(base), cell_str(ours), cell_str(theirs)
+                ),
+            X2daConflict::Row { row, .. } =>
+                write!(f, "Row {} was added differently on both sides.", row),
+            X2daConflict::Reserved { row, owner } =>
+                write!(f, "Row {} is reserved for {:?}, the other side's row was dropped.", row, owner),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct X2daMergeResult {
+    pub merged: Dynamic2da,
+    pub conflicts: Vec<X2daConflict>,
+}
+
+impl X2daMergeResult {
+    pub fn has_conflicts(&self)
+        -> bool
+    {
+        !self.conflicts.is_empty()
+    }
+}
+
+/// Three-way merge of two edited copies of a 2da against the base game one.
+///
+/// Rows are matched by index and cells by case insensitive column name,
+/// columns only one side added are kept. A row or column missing from a side
+/// counts as unchanged. Reservations hand a range of appended rows to one
+/// side only.
+#[derive(Debug, Default)]
+pub struct X2daMerge {
+    reservations: Vec<(MergeSide, Range<usize>)>,
+}
+
+impl X2daMerge {
+    pub fn new()
+        -> Self
+    {
+        X2daMerge {
+            reservations: Vec::new(),
+        }
+    }
+
+    /// Reserves `rows` for `side`. Reservations can only cover rows past the
+    /// end of the base table and can't overlap.
+    pub fn reserve(&mut self, side: MergeSide, rows: Range<usize>)
+        -> &mut Self
+    {
+        self.reservations.push((side, rows));
+        self
+    }
+
+    fn owner(&self, row: usize)
+        -> Option<MergeSide>
+    {
+        self.reservations
+            .iter()
+            .find(|(_, range)| range.contains(&row))
+            .map(|(side, _)| *side)
+    }
+
+    fn validate_reservations(&self, base: &Dynamic2da)
+        -> Result<(), X2daError>
+    {
+        for (i, (_, range)) in self.reservations.iter().enumerate() {
+            let overlaps = self.reservations[..i]
+                .iter()
+                .any(|(_, other)| range.start < other.end && other.start < range.end);
+
+            if range.start < base.len() || range.start >= range.end || overlaps {
+                return Err(X2daError::X2daInvalidReservation(range.start, range.end));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn merge(&self, base: &Dynamic2da, ours: &Dynamic2da, theirs: &Dynamic2da)
+        -> Result<X2daMergeResult, X2daError>
+    {
+        self.validate_reservations(base)?;
+
+        let mut columns = base.columns.clone();
+
+        for column in ours.columns.iter().chain(theirs.columns.iter()) {
+            if !columns.iter().any(|c| c.eq_ignore_ascii_case(column)) {
+                columns.push(column.clone());
+            }
+        }
+
+        let row_count = base.len().max(ours.len()).max(theirs.len());
+        let mut conflicts = Vec::new();
+        let mut rows = Vec::with_capacity(row_count);
+
+        for i in 0..row_count {
+            let b = row_values(base, i, &columns);
+            let o = row_values(ours, i, &columns);
+            let t = row_values(theirs, i, &columns);
+
+            let row = match self.owner(i) {
+                Some(owner) => {
+                    let (kept, dropped) = match owner {
+                        MergeSide::Ours => (o, t),
+                        MergeSide::Theirs => (t, o),
+                    };
+
+                    // Blank rows only pad the table out to the reservation.
+                    if dropped.map(|r| r.iter().any(Option::is_some)).unwrap_or(false) {
+                        conflicts.push(X2daConflict::Reserved { row: i, owner });
+                    }
+
+                    kept
+                },
+                None => match (b, o, t) {
+                    (None, o, None) => o,
+                    (None, None, t) => t,
+                    (None, Some(o), Some(t)) => {
+                        let (o, t) = (
+                            fill_missing(o.clone(), ours, &columns, &t),
+                            fill_missing(t, theirs, &columns, &o),
+                        );
+
+                        if o != t {
+                            conflicts.push(X2daConflict::Row {
+                                row: i,
+                                ours: o.clone(),
+                                theirs: t,
+                            });
+                        }
+
+                        Some(o)
+                    },
+                    (Some(b), o, t) => {
+                        let o = o.map(|o| fill_missing(o, ours, &columns, &b))
+                            .unwrap_or_else(|| b.clone());
+                        let t = t.map(|t| fill_missing(t, theirs, &columns, &b))
+                            .unwrap_or_else(|| b.clone());
+
+                        Some(merge_row(i, &columns, b, o, t, &mut conflicts))
+                    },
+                },
+            };
+
+            rows.push(row);
+        }
+
+        while let Some(None) = rows.last() {
+            rows.pop();
+        }
+
+        let mut merged = Dynamic2da::new(columns);
+        merged.header = base.header.clone();
+        merged.default = merge_value(&base.default, &ours.default, &theirs.default)
+            .unwrap_or_else(|| ours.default.clone());
+
+        for row in rows {
+            let row = row.unwrap_or_else(|| vec![None; merged.columns.len()]);
+            merged.add_row(row)?;
+        }
+
+        Ok(X2daMergeResult {
+            merged,
+            conflicts,
+        })
+    }
+}
+
+/// The row laid out in the merged column order, `None` if the table is
+/// shorter.
+fn row_values(table: &Dynamic2da, row: usize, columns: &[String])
+    -> Option<Row>
+{
+    table.rows.get(row)?;
+
+    Some(
+        columns
+            .iter()
+            .map(|c| table.get(row, c).map(String::from))
+            .collect()
+    )
+}
+
+/// Takes the cells of the columns `table` doesn't have from `other`, so a
+/// side that lacks a column never disagrees about it.
+fn fill_missing(mut row: Row, table: &Dynamic2da, columns: &[String], other: &Row)
+    -> Row
+{
+    for (i, column) in columns.iter().enumerate() {
+        if table.column_index(column).is_none() {
+            row[i] = other[i].clone();
+        }
+    }
+
+    row
+}
+
+/// The merged value, `None` if both sides changed it differently.
+fn merge_value<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T)
+    -> Option<T>
+{
+    if ours == theirs || theirs == base {
+        Some(ours.clone())
+    } else if ours == base {
+        Some(theirs.clone())
+    } else {
+        None
+    }
+}
+
+fn merge_row(
+    row: usize,
+    columns: &[String],
+    base: Row,
+    ours: Row,
+    theirs: Row,
+    conflicts: &mut Vec<X2daConflict>
+)
+    -> Row
+{
+    base.into_iter()
+        .zip(ours)
+        .zip(theirs)
+        .zip(columns)
+        .map(|(((b, o), t), column)| {
+            match merge_value(&b, &o, &t) {
+                Some(value) => value,
+                None => {
+                    conflicts.push(X2daConflict::Cell {
+                        row,
+                        column: column.clone(),
+                        base: b,
+                        ours: o.clone(),
+                        theirs: t,
+                    });
+
+                    o
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::io::Cursor;
+
+    fn table(s: &str)
+        -> Dynamic2da
+    {
+        let s = format!("2DA V2.0\n\n{}", s);
+        Dynamic2da::parse_from(&mut Cursor::new(s.into_bytes())).unwrap()
+    }
+
+    fn base()
+        -> Dynamic2da
+    {
+        table("Label Cost\n0 sword 10\n1 axe 20\n")
+    }
+
+    #[test]
+    fn merge_independent_changes() {
+        let ours = table("Label Cost\n0 sword 15\n1 axe 20\n");
+        let theirs = table("Label Cost\n0 sword 10\n1 big_axe 20\n");
+
+        let result = X2daMerge::new().merge(&base(), &ours, &theirs).unwrap();
+
+        assert!(!result.has_conflicts());
+        assert_eq!(Some("15"), result.merged.get(0, "Cost"));
+        assert_eq!(Some("big_axe"), result.merged.get(1, "Label"));
+    }
+
+    #[test]
+    fn merge_cell_conflict_keeps_ours() {
+        let ours = table("Label Cost\n0 sword 15\n1 axe 20\n");
+        let theirs = table("Label Cost\n0 sword 30\n1 axe 20\n");
+
+        let result = X2daMerge::new().merge(&base(), &ours, &theirs).unwrap();
+
+        assert_eq!(Some("15"), result.merged.get(0, "Cost"));
+        assert_eq!(
+            vec![X2daConflict::Cell {
+                row: 0,
+                column: String::from("Cost"),
+                base: Some(String::from("10")),
+                ours: Some(String::from("15")),
+                theirs: Some(String::from("30")),
+            }],
+            result.conflicts
+        );
+    }
+
+    #[test]
+    fn merge_added_rows_and_columns() {
+        let ours = table("Label Cost\n0 sword 10\n1 axe 20\n2 bow 5\n");
+        let theirs = table("Label Cost Weight\n0 sword 10 3\n1 axe 20 ****\n2 bow 5 1\n3 sling 1 ****\n");
+
+        let result = X2daMerge::new().merge(&base(), &ours, &theirs).unwrap();
+
+        assert_eq!(vec!["Label", "Cost", "Weight"], result.merged.columns);
+        assert_eq!(4, result.merged.len());
+        assert_eq!(Some("3"), result.merged.get(0, "weight"));
+        assert_eq!(Some("sling"), result.merged.get(3, "Label"));
+        assert_eq!(Some("1"), result.merged.get(2, "Weight"));
+        assert!(!result.has_conflicts());
+
+        let theirs = table("Label Cost Weight\n0 sword 10 3\n1 axe 20 ****\n2 bow 6 1\n");
+        let result = X2daMerge::new().merge(&base(), &ours, &theirs).unwrap();
+
+        assert_eq!(1, result.conflicts.len());
+        assert!(matches!(result.conflicts[0], X2daConflict::Row { row: 2, .. }));
+    }
+
+    #[test]
+    fn merge_missing_column_counts_as_unchanged() {
+        let ours = table("Label\n0 sword\n1 great_axe\n");
+        let theirs = table("Label Cost\n0 sword 15\n1 axe 20\n");
+
+        let result = X2daMerge::new().merge(&base(), &ours, &theirs).unwrap();
+
+        assert!(!result.has_conflicts());
+        assert_eq!(Some("15"), result.merged.get(0, "Cost"));
+        assert_eq!(Some("20"), result.merged.get(1, "Cost"));
+        assert_eq!(Some("great_axe"), result.merged.get(1, "Label"));
+    }
+
+    #[test]
+    fn merge_reservations() {
+        let ours = table("Label Cost\n0 sword 10\n1 axe 20\n2 bow 5\n");
+        let theirs = table("Label Cost\n0 sword 10\n1 axe 20\n2 **** ****\n3 dart 2\n4 sling 1\n");
+
+        let result = X2daMerge::new()
+            .reserve(MergeSide::Ours, 2..4)
+            .reserve(MergeSide::Theirs, 4..6)
+            .merge(&base(), &ours, &theirs)
+            .unwrap();
+
+        assert_eq!(Some("bow"), result.merged.get(2, "Label"));
+        assert_eq!(None, result.merged.get(3, "Label"));
+        assert_eq!(Some("sling"), result.merged.get(4, "Label"));
+        assert_eq!(
+            vec![X2daConflict::Reserved { row: 3, owner: MergeSide::Ours }],
+            result.conflicts
+        );
+    }
+
+    #[test]
+    fn merge_invalid_reservations() {
+        let e = X2daMerge::new()
+            .reserve(MergeSide::Ours, 1..4)
+            .merge(&base(), &base(), &base())
+            .unwrap_err();
+
+        assert_eq!(X2daError::X2daInvalidReservation(1, 4), e);
+
+        let e = X2daMerge::new()
+            .reserve(MergeSide::Ours, 2..6)
+            .reserve(MergeSide::Theirs, 5..8)
+            .merge(&base(), &base(), &base())
+            .unwrap_err();
+
+        assert_eq!(X2daError::X2daInvalidReservation(5, 8), e);
+    }
+
+    #[test]
+    fn merged_writes_through_writer() {
+        let ours = table("Label Cost\n0 sword 15\n1 axe 20\n");
+        let result = X2daMerge::new().merge(&base(), &ours, &base()).unwrap();
+
+        let mut c = Cursor::new(Vec::new());
+        result.merged.write(&mut c).unwrap();
+
+        let written = String::from_utf8(c.into_inner()).unwrap();
+
+        assert_eq!(result.merged, table(written.trim_start_matches("2DA V2.0\n\n")));
+    }
+}
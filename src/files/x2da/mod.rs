@@ -2,7 +2,11 @@ pub(crate) mod types;
 pub mod writer;
 pub mod parser;
 pub mod x2da_file;
+pub mod dynamic_x2da;
+pub mod merge;
+pub mod diff;
 mod regex_parser;
+mod binary;
 
 
 #[cfg(test)]
@@ -101,4 +105,71 @@ r#"
             },
         ]
     }
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod derive_test
+{
+    use super::x2da_file::X2daFile;
+    use super::data::{x2da_file_string, x2da_sample_rows};
+    use crate::types::X2daRow;
+    use std::io::Cursor;
+
+    #[derive(X2daRow, Debug, PartialEq)]
+    struct Derived2da {
+        #[x2da(rename = "Melon")]
+        a_text: Option<String>,
+        #[x2da(rename = "num")]
+        a_u32: Option<u32>,
+        #[x2da(rename = "numf")]
+        a_f32: Option<f32>,
+    }
+
+    #[test]
+    fn derived_columns() {
+        assert_eq!(3, Derived2da::SIZE);
+        assert_eq!(
+            Some(vec![String::from("Melon"), String::from("num"), String::from("numf")]),
+            Derived2da::columns()
+        );
+    }
+
+    #[test]
+    fn derived_matches_hand_written() {
+        let mut c = Cursor::new(x2da_file_string().as_bytes());
+        let mut file = X2daFile::<Derived2da>::parse_from(&mut c).unwrap();
+
+        let sample = x2da_sample_rows();
+        let rows = file.rows
+            .iter()
+            .map(|r| (r.a_text.clone(), r.a_u32, r.a_f32))
+            .collect::<Vec<_>>();
+        let expected = sample
+            .iter()
+            .map(|r| (r.a_text.clone(), r.a_u32, r.a_f32))
+            .collect::<Vec<_>>();
+
+        assert_eq!(expected, rows);
+
+        let mut written = Cursor::new(Vec::new());
+        file.write(&mut written).unwrap();
+
+        let reparsed = X2daFile::<Derived2da>::parse_from(&mut Cursor::new(written.into_inner())).unwrap();
+
+        assert_eq!(file.rows, reparsed.rows);
+    }
+
+    #[test]
+    fn derived_invalid_item() {
+        let e = Derived2da::from_line("0 melon notanumber 1.0").unwrap_err();
+
+        assert_eq!(super::types::X2daError::InvalidTableItem, e);
+    }
+
+    #[test]
+    fn new_uses_derived_columns() {
+        let file = X2daFile::<Derived2da>::new();
+
+        assert_eq!(Derived2da::columns(), file.columns);
+    }
+}
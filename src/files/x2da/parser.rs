@@ -2,40 +2,150 @@ use std::io::prelude::*;
 
 use crate::types::{
     Error as MyError,
+    FileType,
+    Version,
+    NULL_STRING,
 };
 
 use super::x2da_file::X2daFile;
+use super::regex_parser;
+use super::binary;
 use super::types::{
     X2daError,
     X2daRow,
-    X2daColumns,
     X2daHeader,
 };
 
-pub fn parse<T: X2daRow, F: Seek + BufRead>(reader: F)
-    -> Result<X2daFile<T>, MyError>
+const DEFAULT_PREFIX: &str = "DEFAULT:";
+
+/// A 2da split into its parts before the cells are converted to any row type.
+pub(crate) struct X2daLines {
+    pub version: Version,
+    pub default: Option<String>,
+    pub columns: Vec<String>,
+    pub rows: Vec<X2daLine>,
+}
+
+pub(crate) struct X2daLine {
+    /// 1 based, for error messages.
+    pub line: usize,
+    pub label: String,
+    pub cells: Vec<Option<String>>,
+}
+
+/// Reads either the binary `V2.b` or the text `V2.0` layout.
+pub(crate) fn parse_lines<R: BufRead>(mut reader: R)
+    -> Result<X2daLines, MyError>
+{
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    if binary::is_binary(&bytes) {
+        binary::parse(&bytes)
+    } else {
+        parse_text_lines(bytes.as_slice())
+    }
+}
+
+/// Reads the text layout leniently, the way the game does: blank lines and
+/// tabs are allowed anywhere, an optional `DEFAULT:` line may come before the
+/// columns, rows keep their own labels, missing cells are read as `****` and
+/// cells past the last column are ignored.
+fn parse_text_lines<R: BufRead>(reader: R)
+    -> Result<X2daLines, MyError>
 {
-    
     let lines = reader
         .lines()
         .collect::<std::io::Result<Vec<_>>>()?;
 
-    let columns = str::split_whitespace(&lines[2])
-        .map(|s| String::from(s))
+    let mut lines = lines
+        .iter()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim_start_matches('\u{feff}').trim()))
+        .filter(|(_, l)| !l.is_empty());
+
+    match lines.next() {
+        Some((_, l)) if l.to_uppercase().starts_with("2DA") => {},
+        Some((line, _)) => Err(X2daError::X2daInvalidHeader.at_line(line))?,
+        None => Err(X2daError::X2daInvalidHeader.at_line(1))?,
+    }
+
+    let mut default = None;
+
+    let columns = loop {
+        match lines.next() {
+            Some((_, l)) if l.to_uppercase().starts_with(DEFAULT_PREFIX) => {
+                default = regex_parser::parse_string(&l[DEFAULT_PREFIX.len()..])
+                    .into_iter()
+                    .next();
+            },
+            Some((_, l)) => break l
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<String>>(),
+            None => Err(X2daError::X2daMissingColumns)?,
+        }
+    };
+
+    let rows = lines
+        .map(|(line, l)| {
+            let mut strings = regex_parser::parse_string(l).into_iter();
+            let label = strings.next().unwrap_or_default();
+
+            let mut cells = strings
+                .take(columns.len())
+                .map(|s| match s.as_ref() {
+                    NULL_STRING => None,
+                    _ => Some(s),
+                })
+                .collect::<Vec<_>>();
+
+            cells.resize(columns.len(), None);
+
+            X2daLine {
+                line,
+                label,
+                cells,
+            }
+        })
         .collect();
 
-    X2daColumns::validate::<T>(&columns)?;
-    
-    let rows = lines[3..]
-        .iter()
-        .filter(|l| l.as_str() != "")
-        .map(T::from_line)
-        .collect::<Result<Vec<T>, X2daError>>()?;
+    Ok(X2daLines {
+        version: Version::V2,
+        default,
+        columns,
+        rows,
+    })
+}
+
+pub fn parse<T: X2daRow, F: Seek + BufRead>(reader: F)
+    -> Result<X2daFile<T>, MyError>
+{
+    let lines = parse_lines(reader)?;
+
+    if lines.columns.len() != T::SIZE {
+        Err(X2daError::X2daWrongNumberColumns(T::SIZE, lines.columns.len()))?;
+    }
+
+    let mut rows = Vec::with_capacity(lines.rows.len());
+    let mut row_labels = Vec::with_capacity(lines.rows.len());
+
+    for row in lines.rows {
+        let line = row.line;
+
+        rows.push(T::from_strings(row.cells).map_err(|e| e.at_line(line))?);
+        row_labels.push(row.label);
+    }
 
     Ok(X2daFile {
-        header: Some(X2daHeader::default()),
+        header: Some(X2daHeader {
+            version: lines.version,
+            file_type: FileType::X2da,
+        }),
         rows: rows,
-        columns: Some(columns),
+        columns: Some(lines.columns),
+        row_labels,
+        default: lines.default,
     })
 }
 
@@ -132,4 +242,78 @@ mod test
 
         assert_eq!(expected, row);
     }
-}
\ No newline at end of file
+
+    fn parse_dynamic(s: &str)
+        -> X2daLines
+    {
+        parse_lines(Cursor::new(s.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn parse_real_world_layout() {
+        let x2da = "2DA V2.0  \r\n\r\nDEFAULT: ****\r\n\r\n\tLabel\tModel\tNum\r\n\n0\tfirst\tc_a\t1\n\n5 \"second one\" c_b\n7 third c_c 3 junk junk\n";
+
+        let lines = parse_dynamic(x2da);
+
+        assert_eq!(Some(String::from("****")), lines.default);
+        assert_eq!(vec!["Label", "Model", "Num"], lines.columns);
+        assert_eq!(
+            vec!["0", "5", "7"],
+            lines.rows.iter().map(|r| r.label.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![Some(String::from("second one")), Some(String::from("c_b")), None], lines.rows[1].cells);
+        assert_eq!(3, lines.rows[2].cells.len());
+        assert_eq!(9, lines.rows[1].line);
+    }
+
+    #[test]
+    fn parse_errors_have_line_numbers() {
+        let x2da = "2DA V2.0\n\n     Melon    num    numf\n0    a    1    1.0\n\n2    b    x    1.0\n";
+
+        let e = parse::<Sample2da, _>(Cursor::new(x2da.as_bytes())).unwrap_err();
+
+        match e {
+            MyError::X2daError(e) => assert_eq!(X2daError::InvalidTableItem.at_line(6), e),
+            _ => panic!("expected an X2daError"),
+        }
+    }
+
+    #[test]
+    fn parse_keeps_labels_and_pads() {
+        let x2da = "2DA V2.0\nDEFAULT: 0\nMelon num numf\n10 a\n20 b 2 2.0\n";
+
+        let file = parse::<Sample2da, _>(Cursor::new(x2da.as_bytes())).unwrap();
+
+        assert_eq!(vec!["10", "20"], file.row_labels);
+        assert_eq!(Some(String::from("0")), file.default);
+        assert_eq!(None, file.rows[0].a_u32);
+        assert_eq!(Some(2.0), file.rows[1].a_f32);
+    }
+
+    #[test]
+    fn parse_missing_header_or_columns() {
+        assert!(matches!(
+            parse_lines(Cursor::new("A V2.0\n".as_bytes())),
+            Err(MyError::X2daError(X2daError::X2daAtLine(1, _)))
+        ));
+        assert!(matches!(
+            parse_lines(Cursor::new("2DA V2.0\n\nDEFAULT: 1\n".as_bytes())),
+            Err(MyError::X2daError(X2daError::X2daMissingColumns))
+        ));
+    }
+
+    #[test]
+    fn parse_binary_into_typed() {
+        let mut file = parse::<Sample2da, _>(Cursor::new(x2da_file_string().as_bytes())).unwrap();
+        file.header.as_mut().unwrap().version = Version::V2B;
+
+        let mut c = Cursor::new(Vec::new());
+        file.write(&mut c).unwrap();
+
+        let parsed = parse::<Sample2da, _>(Cursor::new(c.into_inner())).unwrap();
+
+        assert_eq!(file.rows, parsed.rows);
+        assert_eq!(file.columns, parsed.columns);
+        assert_eq!(Some(Version::V2B), parsed.header.map(|h| h.version));
+    }
+}
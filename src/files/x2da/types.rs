@@ -18,9 +18,26 @@ pub enum X2daError
     X2daColumnsOnlyAlphaAndUnderscore,
     X2daWriteWithoutHeader,
     X2daWriteWithoutColumns,
+    X2daUnknownColumn(String),
+    X2daRowOutOfRange(usize),
+    X2daInvalidHeader,
+    X2daMissingColumns,
+    X2daAtLine(usize, Box<X2daError>),
+    X2daBinaryTooLarge,
+    X2daInvalidReservation(usize, usize),
     InvalidTableItem
 }
 
+impl X2daError
+{
+    /// Attaches the 1 based line number the error was found on.
+    pub fn at_line(self, line: usize)
+        -> Self
+    {
+        X2daError::X2daAtLine(line, Box::new(self))
+    }
+}
+
 impl fmt::Display for X2daError
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>)
@@ -36,11 +53,25 @@ impl fmt::Display for X2daError
             X2daError::X2daWrongNumberColumns(expected, found) =>
                 write!(f, "Found {} columns, expected {}", found, expected),
             X2daError::X2daColumnsOnlyAlphaAndUnderscore =>
-                write!(f, "X2da columns can only contain lowercase/uppercase letters and underscores."),
+                write!(f, "X2da columns can only contain lowercase/uppercase letters, digits and underscores."),
             X2daError::X2daWriteWithoutColumns =>
                 write!(f, "X2da can't be build without columns defined."),
             X2daError::X2daWriteWithoutHeader =>
                 write!(f, "X2da can't be written without a header created."),
+            X2daError::X2daUnknownColumn(column) =>
+                write!(f, "X2da doesn't have a column named <{}>.", column),
+            X2daError::X2daRowOutOfRange(row) =>
+                write!(f, "X2da doesn't have a row {}.", row),
+            X2daError::X2daInvalidHeader =>
+                write!(f, "X2da didn't start with a <2DA V2.0> header."),
+            X2daError::X2daMissingColumns =>
+                write!(f, "X2da ended before its column names."),
+            X2daError::X2daAtLine(line, e) =>
+                write!(f, "Line {}: {}", line, e),
+            X2daError::X2daBinaryTooLarge =>
+                write!(f, "Binary X2da string data can't be larger than 65535 bytes."),
+            X2daError::X2daInvalidReservation(start, end) =>
+                write!(f, "Rows {}..{} can't be reserved, they overlap the base table or another reservation.", start, end),
             X2daError::InvalidTableItem =>
                 write!(f, "X2da contained a tableitem that couldn't be parsed successfully."),
         }
@@ -55,6 +86,13 @@ pub trait X2daRow
     
     type Row: AsRef<[Option<Box<dyn X2daItem>>]>;
 
+    /// Column names known up front, `X2daFile::new` starts out with them.
+    /// `#[derive(X2daRow)]` fills these in from the field names.
+    fn columns() -> Option<Vec<String>>
+    {
+        None
+    }
+
     fn to_row(&self) -> Self::Row;
 
     fn from_strings(strings: Vec<Option<String>>)
@@ -98,6 +136,15 @@ pub trait X2daItem
 
     fn serialize_to_string(&self) -> String;
 
+    /// Like `serialize_to_string`, with a fixed number of decimals for
+    /// floats.
+    #[inline]
+    fn serialize_with_precision(&self, _precision: Option<usize>)
+        -> String
+    {
+        self.serialize_to_string()
+    }
+
     fn boxed(self) -> Box<dyn X2daItem>
         where Self: Sized + 'static,
     {
@@ -139,6 +186,15 @@ impl X2daItem for f32 {
     {
         format!("{:?}", self)
     }
+
+    fn serialize_with_precision(&self, precision: Option<usize>)
+        -> String
+    {
+        match precision {
+            Some(precision) => format!("{:.*}", precision, self),
+            None => self.serialize_to_string(),
+        }
+    }
 }
 
 pub struct X2daColumns {}
@@ -156,7 +212,7 @@ impl X2daColumns {
             .all(|col| {
                 col
                     .chars()
-                    .all(|c| c.is_alphabetic() || c == '_')
+                    .all(|c| c.is_alphanumeric() || c == '_')
             });
 
         if ! valid_chars {
@@ -167,7 +223,7 @@ impl X2daColumns {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct X2daHeader {
     pub version: Version,
     pub file_type: FileType,
@@ -183,10 +239,47 @@ impl Default for X2daHeader {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum X2daLineEnding
+{
+    Lf,
+    CrLf,
+}
+
+impl X2daLineEnding
+{
+    pub fn as_str(&self)
+        -> &'static str
+    {
+        match self {
+            X2daLineEnding::Lf => "\n",
+            X2daLineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Text layout options, the defaults give the aligned output this crate has
+/// always written. The binary `V2.b` layout ignores them.
+#[derive(Debug, Clone)]
 pub struct X2daBuilderConfig
 {
+    /// Spaces between the widest cell of a column and the next column.
     pub spacing_length: usize,
+    /// Separates cells with a single tab instead of aligning with spaces.
+    pub use_tabs: bool,
+    /// Quotes every string cell of typed rows, otherwise only cells that
+    /// are empty or contain whitespace are quoted. Dynamic tables don't know
+    /// which cells are strings and only quote when needed either way.
+    pub quote_strings: bool,
+    /// Writes the `f32` cells of typed rows with this many decimals.
+    /// Dynamic tables don't know which cells are floats and keep them as
+    /// they are.
+    pub float_precision: Option<usize>,
+    /// Writes the labels rows were parsed with instead of numbering them.
+    pub keep_row_labels: bool,
+    pub line_ending: X2daLineEnding,
+    /// Writes the table's `DEFAULT:` value when it has one.
+    pub write_default: bool,
 }
 
 impl Default for X2daBuilderConfig
@@ -194,7 +287,13 @@ impl Default for X2daBuilderConfig
     fn default() -> Self
     {
         X2daBuilderConfig {
-            spacing_length: 4
+            spacing_length: 4,
+            use_tabs: false,
+            quote_strings: true,
+            float_precision: None,
+            keep_row_labels: false,
+            line_ending: X2daLineEnding::Lf,
+            write_default: true,
         }
     }
-}
\ No newline at end of file
+}
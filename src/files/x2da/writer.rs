@@ -1,5 +1,6 @@
 use crate::types::{
     Error as MyError,
+    Version,
     NULL_STRING,
 };
 
@@ -10,6 +11,7 @@ use std::convert::From;
 
 
 use super::x2da_file::X2daFile;
+use super::binary;
 
 use super::types::{
     X2daError,
@@ -52,7 +54,10 @@ pub fn write<F, T>(
         Err(X2daError::X2daWriteWithoutHeader)?;
     }
 
-    let mut writer = BufWriter::new(writer);
+    let columns = x2da_file.columns.as_ref().unwrap();
+    let header = x2da_file.header.as_ref().unwrap();
+
+    let precision = config.float_precision.filter(|_| header.version != Version::V2B);
 
     let string_rows = x2da_file.rows
         .iter()
@@ -62,59 +67,136 @@ pub fn write<F, T>(
                 .iter()
                 .map(|item| item
                     .as_ref()
-                    .map(|i| i.serialize_to_string())
+                    .map(|i| i.serialize_with_precision(precision))
                     .unwrap_or(String::from(NULL_STRING))
                 )
                 .collect()
         })
         .collect::<Vec<Vec<String>>>();
-        
-    // dbg!("{:?}", &string_rows);
 
-    let max_lengths = max_lengths(&x2da_file, &string_rows);
-    let columns = x2da_file.columns.as_ref().unwrap();
-    let header = x2da_file.header.as_ref().unwrap();
+    write_strings(
+        writer,
+        header,
+        columns,
+        &x2da_file.row_labels,
+        x2da_file.default.as_deref(),
+        &string_rows,
+        &config
+    )
+}
 
-    write_header(&mut writer, header)?;
-    write_columns(&mut writer, &config, columns, &max_lengths)?;
+/// Writes already serialized rows, shared by the typed and dynamic tables.
+/// The header's version picks the binary `V2.b` or the text layout, the
+/// binary one always keeps the row labels and has no `DEFAULT:` line.
+pub(crate) fn write_strings<F>(
+    writer: &mut F,
+    header: &X2daHeader,
+    columns: &[String],
+    labels: &[String],
+    default: Option<&str>,
+    string_rows: &[Vec<String>],
+    config: &X2daBuilderConfig
+)
+    -> Result<(), MyError>
+    where F: Write
+{
+    let mut writer = BufWriter::new(writer);
+
+    if header.version == Version::V2B {
+        binary::write(&mut writer, columns, labels, string_rows)?;
+        writer.flush()?;
+
+        return Ok(());
+    }
+
+    let labels = (0..string_rows.len())
+        .map(|i| match labels.get(i) {
+            Some(label) if config.keep_row_labels => label.clone(),
+            _ => i.to_string(),
+        })
+        .collect::<Vec<String>>();
+
+    let string_rows = string_rows
+        .iter()
+        .map(|row| row.iter().map(|c| format_cell(c, config)).collect())
+        .collect::<Vec<Vec<String>>>();
+
+    let default = default
+        .filter(|_| config.write_default)
+        .map(|d| format_cell(d, config));
+
+    let max_lengths = max_lengths(columns, &string_rows);
+    let label_length = labels.iter().map(String::len).max().unwrap_or(0).max(1);
+
+    write_header(&mut writer, config, header, default.as_deref())?;
+    write_columns(&mut writer, config, columns, label_length, &max_lengths)?;
 
     string_rows
         .iter()
-        .enumerate()
-        .map(|(idx, row)| {
-            write_row(&mut writer, &config, &max_lengths, idx, row)
+        .zip(labels.iter())
+        .map(|(row, label)| {
+            write_row(&mut writer, config, &max_lengths, label_length, label, row)
         })
         .collect::<Result<Vec<_>, io::Error>>()?;
 
-    // dbg!("{:?}", max_lengths);
+    writer.flush()?;
 
     Ok(())
 }
 
+/// Applies the quoting option to a serialized cell.
+fn format_cell(cell: &str, config: &X2daBuilderConfig)
+    -> String
+{
+    if cell == NULL_STRING {
+        return cell.to_owned();
+    }
+
+    let quoted = cell.len() >= 2 && cell.starts_with('"') && cell.ends_with('"');
+    let inner = if quoted { &cell[1..cell.len() - 1] } else { cell };
+
+    let needs_quotes = inner.is_empty() || inner.contains(char::is_whitespace);
+
+    if needs_quotes || (quoted && config.quote_strings) {
+        format!("\"{}\"", inner)
+    } else {
+        inner.to_owned()
+    }
+}
 
 fn write_row<F>(
     writer: &mut F,
     config: &X2daBuilderConfig,
-    max_lengths: &Vec<usize>,
-    idx: usize,
-    row: &Vec<String>
+    max_lengths: &[usize],
+    label_length: usize,
+    label: &str,
+    row: &[String]
 )
     -> Result<(), io::Error>
     where F: Write
 {
     let spacing = config.spacing_length;
-    write!(writer, "{:<width$}", idx, width = spacing + 1)?;
+
+    if config.use_tabs {
+        write!(writer, "{}", label)?;
+    } else {
+        write!(writer, "{:<width$}", label, width = label_length + spacing)?;
+    }
     
     row
         .iter()
         .enumerate()
         .map(|(i, item)| {
-            let padding = spacing + max_lengths[i];
-            write!(writer, "{:width$}", item, width = padding)
+            if config.use_tabs {
+                write!(writer, "\t{}", item)
+            } else {
+                let padding = spacing + max_lengths[i];
+                write!(writer, "{:width$}", item, width = padding)
+            }
         })
         .collect::<Result<Vec<_>, io::Error>>()?;
 
-    write!(writer, "\n")?;
+    write!(writer, "{}", config.line_ending.as_str())?;
 
     Ok(())
 }
@@ -122,51 +204,69 @@ fn write_row<F>(
 fn write_columns<F>(
     writer: &mut F,
     config: &X2daBuilderConfig,
-    columns: &Vec<String>,
-    max_lengths: &Vec<usize>
+    columns: &[String],
+    label_length: usize,
+    max_lengths: &[usize]
 )
     -> Result<(), io::Error>
     where F: Write
 {
-    write!(writer, "{:<width$}", "", width = config.spacing_length + 1)?;
+    if !config.use_tabs {
+        write!(writer, "{:<width$}", "", width = label_length + config.spacing_length)?;
+    }
     
     columns
         .iter()
         .enumerate()
         .map(|(i, col)| {
-            let padding = config.spacing_length + max_lengths[i];
-            write!(writer, "{:width$}", col, width = padding)
+            if config.use_tabs {
+                write!(writer, "\t{}", col)
+            } else {
+                let padding = config.spacing_length + max_lengths[i];
+                write!(writer, "{:width$}", col, width = padding)
+            }
         })
         .collect::<Result<Vec<_>, io::Error>>()?;
 
-    write!(writer, "\n")?;
+    write!(writer, "{}", config.line_ending.as_str())?;
 
     Ok(())
 }
 
-fn write_header<F>( writer: &mut F, header: &X2daHeader)
+fn write_header<F>(
+    writer: &mut F,
+    config: &X2daBuilderConfig,
+    header: &X2daHeader,
+    default: Option<&str>
+)
     -> Result<(), io::Error>
     where F: Write,
 {
+    let line_ending = config.line_ending.as_str();
+
     write!(
         writer,
-        "{}{}\n\n",
+        "{}{}{}",
         header.file_type.as_str_ref(),
-        header.version.as_str_ref()
+        header.version.as_str_ref(),
+        line_ending
     )?;
 
+    match default {
+        Some(default) => write!(writer, "DEFAULT: {}{}", default, line_ending)?,
+        None => write!(writer, "{}", line_ending)?,
+    }
+
     Ok(())
 }
 
-fn max_lengths<T>(
-    x2da_file: &X2daFile<T>,
-    string_rows: &Vec<Vec<String>>
+fn max_lengths(
+    columns: &[String],
+    string_rows: &[Vec<String>]
 )
     -> Vec<usize>
-    where T: X2daRow
 {
-    (0..T::SIZE)
-        .into_iter()
+    (0..columns.len())
         .map(|i| {
             string_rows
                 .iter()
@@ -176,7 +276,7 @@ fn max_lengths<T>(
                         _ => state,
                     }
                 })
-                .max(x2da_file.columns.as_ref().unwrap()[i].len())
+                .max(columns[i].len())
         })
         .collect()
 }
@@ -186,6 +286,7 @@ mod test
 {
     // #TODO: Error with floating point numbers ending with 5.
     use super::*;
+    use super::super::types::X2daLineEnding;
     use std::io::Cursor;
     use super::super::data::{
         Sample2da,
@@ -227,7 +328,7 @@ mod test
         let cols = vec![
             String::from("blah"),
             String::from("nah"),
-            String::from("Cah3"),
+            String::from("Cah-3"),
         ];
 
         let e = X2daFile::<Sample2da>::new()
@@ -237,6 +338,17 @@ mod test
         assert_eq!(X2daError::X2daColumnsOnlyAlphaAndUnderscore, e);    
     }
 
+    #[test]
+    fn x2da_build_columns_with_digits() {
+        let cols = vec![
+            String::from("blah"),
+            String::from("Col2"),
+            String::from("Cah_3"),
+        ];
+
+        assert!(X2daFile::<Sample2da>::new().set_columns(cols).is_ok());
+    }
+
     #[test]
     fn x2da_build_invalid_item() {
         let my_2da_row = Sample2da {
@@ -360,4 +472,96 @@ r#"
         
         assert_eq!(expected, s);
     }
+
+    fn sample_file()
+        -> X2daFile<Sample2da>
+    {
+        let mut file = X2daFile::new();
+
+        file.set_columns(vec![
+            String::from("Melon"),
+            String::from("num"),
+            String::from("numf"),
+        ]).unwrap();
+
+        for row in x2da_sample_rows() {
+            file.add_row(row).unwrap();
+        }
+
+        file
+    }
+
+    #[test]
+    fn x2da_build_tabs_labels_default() {
+        let expected = "2DA V2.0\r\nDEFAULT: ****\r\n\tMelon\tnum\tnumf\r\n\
+            10\tCantelope\t0\t3.0\r\n\
+            20\t\"A small melon\"\t1\t3.141592\r\n\
+            2\tWatermelon\t2\t100.1\r\n";
+
+        let mut file = sample_file();
+        file.row_labels = vec![String::from("10"), String::from("20")];
+        file.default = Some(String::from(NULL_STRING));
+
+        let config = X2daBuilderConfig {
+            use_tabs: true,
+            quote_strings: false,
+            keep_row_labels: true,
+            line_ending: X2daLineEnding::CrLf,
+            ..Default::default()
+        };
+
+        let mut c = Cursor::new(Vec::new());
+        file.write_with_config(config, &mut c).unwrap();
+
+        assert_eq!(expected, String::from_utf8(c.into_inner()).unwrap());
+    }
+
+    #[test]
+    fn x2da_build_float_precision() {
+        let expected =
+r#"
+2DA V2.0
+
+   Melon            num  numf     
+0  "Cantelope"      0    3.000    
+1  "A small melon"  1    3.142    
+2  "Watermelon"     2    100.100  
+"#;
+
+        let mut file = sample_file();
+        file.default = Some(String::from("0"));
+
+        let config = X2daBuilderConfig {
+            spacing_length: 2,
+            float_precision: Some(3),
+            write_default: false,
+            ..Default::default()
+        };
+
+        let mut c = Cursor::new(Vec::new());
+        file.write_with_config(config, &mut c).unwrap();
+
+        assert_eq!(expected.trim_start_matches("\n"), String::from_utf8(c.into_inner()).unwrap());
+    }
+
+    #[test]
+    fn x2da_build_wide_labels_stay_aligned() {
+        let mut file = sample_file();
+        file.row_labels = vec![String::from("100"), String::from("1")];
+
+        let config = X2daBuilderConfig {
+            keep_row_labels: true,
+            ..Default::default()
+        };
+
+        let mut c = Cursor::new(Vec::new());
+        file.write_with_config(config, &mut c).unwrap();
+
+        let s = String::from_utf8(c.into_inner()).unwrap();
+        let lines = s.lines().collect::<Vec<_>>();
+
+        assert!(lines[2].starts_with("       Melon"));
+        assert!(lines[3].starts_with("100    \"Cantelope\""));
+        assert!(lines[4].starts_with("1      \"A small melon\""));
+    }
 }
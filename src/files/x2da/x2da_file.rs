@@ -22,6 +22,10 @@ pub struct X2daFile<T>
     pub rows: Vec<T>,
     pub columns: Option<Vec<String>>,
     pub header: Option<X2daHeader>,
+    /// The labels in front of each parsed row, the writer numbers rows itself.
+    pub row_labels: Vec<String>,
+    /// The value of the `DEFAULT:` line, if the table had one.
+    pub default: Option<String>,
 }
 
 impl<T> X2daFile<T>
@@ -31,8 +35,10 @@ impl<T> X2daFile<T>
     {
         X2daFile {
             rows: Vec::new(),
-            columns: None,
+            columns: T::columns(),
             header: Some(X2daHeader::default()),
+            row_labels: Vec::new(),
+            default: None,
         }
     }
 
@@ -1,5 +1,7 @@
 use std::convert::TryInto;
 
+use crate::types::Error as MyError;
+
 #[inline]
 pub fn u32_from_bytes(b: &[u8]) -> u32 {
     u32::from_le_bytes(b.try_into().unwrap())
@@ -9,3 +11,52 @@ pub fn u32_from_bytes(b: &[u8]) -> u32 {
 pub fn u16_from_bytes(b: &[u8]) -> u16 {
     u16::from_le_bytes(b.try_into().unwrap())
 }
+
+/// Slices `size` bytes at `offset`, failing instead of panicking when the
+/// range runs past the end of `b`.
+#[inline]
+pub fn checked_slice(b: &[u8], offset: usize, size: usize)
+    -> Result<&[u8], MyError>
+{
+    offset
+        .checked_add(size)
+        .and_then(|end| b.get(offset..end))
+        .ok_or(MyError::OffsetOutOfBounds(offset))
+}
+
+#[inline]
+pub fn checked_u32_from_bytes(b: &[u8], offset: usize)
+    -> Result<u32, MyError>
+{
+    checked_slice(b, offset, 4).map(u32_from_bytes)
+}
+
+#[inline]
+pub fn checked_u16_from_bytes(b: &[u8], offset: usize)
+    -> Result<u16, MyError>
+{
+    checked_slice(b, offset, 2).map(u16_from_bytes)
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn checked_slice_in_bounds() {
+        let bytes = [1, 2, 3, 4];
+
+        assert_eq!(&[2, 3], checked_slice(&bytes, 1, 2).unwrap());
+    }
+
+    #[test]
+    fn checked_slice_out_of_bounds() {
+        let bytes = [1, 2, 3, 4];
+
+        match checked_u32_from_bytes(&bytes, 2).unwrap_err() {
+            MyError::OffsetOutOfBounds(2) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
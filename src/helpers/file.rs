@@ -5,7 +5,7 @@ use std::path::Path;
 pub fn read_file_to_vec<P: AsRef<Path>>(file_path: P)
     -> std::io::Result<Vec<u8>>
 {
-    let mut f = File::open(file_path).unwrap();
+    let mut f = File::open(file_path)?;
     let file_size = f.metadata()?.len() as usize;
 
     let mut bytes = Vec::with_capacity(file_size);
@@ -1,4 +1,6 @@
 pub mod file;
 pub mod conversion;
 pub mod date;
-pub mod reader;
\ No newline at end of file
+pub mod reader;
+#[cfg(test)]
+pub mod temp_dir;
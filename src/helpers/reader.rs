@@ -45,6 +45,24 @@ pub trait ReaderExt: Read + Seek {
             .map(|_| u16::from_le_bytes(buf))
     }
 
+    fn read_u8(&mut self)
+        -> Result<u8, io::Error>
+    {
+        let mut buf = [0; 1];
+
+        self.read_exact(&mut buf)
+            .map(|_| buf[0])
+    }
+
+    fn read_u64(&mut self)
+        -> Result<u64, io::Error>
+    {
+        let mut buf = [0; 8];
+
+        self.read_exact(&mut buf)
+            .map(|_| u64::from_le_bytes(buf))
+    }
+
     fn read_f32(&mut self)
         -> Result<f32, io::Error>
     {
@@ -54,6 +72,15 @@ pub trait ReaderExt: Read + Seek {
             .map(|_| f32::from_le_bytes(buf))
     }
 
+    fn read_f64(&mut self)
+        -> Result<f64, io::Error>
+    {
+        let mut buf = [0; 8];
+
+        self.read_exact(&mut buf)
+            .map(|_| f64::from_le_bytes(buf))
+    }
+
     fn seek_from_current(&mut self, i: i64)
         -> Result<u64, io::Error>
     {
@@ -116,6 +143,16 @@ mod test
         assert_eq!(num, val);
     }
     
+    #[test]
+    fn valid_read_u64() {
+        let num: u64 = 0x0102_0304_0506_0708;
+        let mut c = Cursor::new(num.to_le_bytes());
+
+        let val = c.read_u64().unwrap();
+
+        assert_eq!(num, val);
+    }
+
     #[test]
     fn valid_read_u32() {
         let num: u32 = 10;
@@ -0,0 +1,47 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A fresh directory under the system temp dir for a test, named after the
+/// process so concurrent test runs don't share it. Removed when dropped,
+/// even if the test panics.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str)
+        -> Self
+    {
+        let dir = std::env::temp_dir()
+            .join(format!("nwn_files_{}_{}", name, std::process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        TempDir(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self)
+        -> &Self::Target
+    {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self)
+        -> &Path
+    {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self)
+    {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -1,19 +1,35 @@
+// Lets `#[derive(X2daRow)]` refer to `::nwn_files` from within this crate.
+extern crate self as nwn_files;
+
 mod helpers;
 mod files;
 
-use files::{bif, key, ssf, x2da, tlk};
+use files::{bif, erf, key, ssf, x2da, tlk, gff};
 use std::path::Path;
 use helpers::file::read_file_to_vec;
 
 pub mod types;
+pub mod resman;
 pub use bif::parse as parse_bif;
 
 pub use ssf::ssf_file::SsfFile;
 pub use ssf::writer::SsfBuilder;
 pub use x2da::x2da_file::X2daFile;
-pub use key::{BifFile, BifResource};
-pub use bif::BifFile2;
+pub use x2da::dynamic_x2da::Dynamic2da;
+pub use x2da::merge::X2daMerge;
+pub use x2da::diff::X2daDiff;
+pub use key::{BifFile, BifResource, KeyBuilder};
+pub use bif::{BifFile2, BifBuilder, BifReader};
 pub use tlk::tlk_file::TlkFile;
+pub use tlk::talk_table::TalkTable;
+pub use tlk::tlk_reader::TlkReader;
+pub use tlk::translation::TlkTranslation;
+pub use tlk::diff::TlkDiff;
+pub use tlk::merge::TlkMerge;
+pub use gff::gff_file::GffFile;
+pub use resman::ResMan;
+pub use erf::erf_reader::ErfReader;
+pub use erf::directory::resource_file_name;
 
 pub use types::{
     ErfFile
@@ -21,7 +37,7 @@ pub use types::{
 
 
 pub fn parse_key<P: AsRef<Path>>(file_path: P)
-    -> Result<key::KeyFile2, std::io::Error>
+    -> Result<key::KeyFile2, types::Error>
 {
     let bytes = read_file_to_vec(file_path)?;
     key::parse(bytes)
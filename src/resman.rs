@@ -0,0 +1,528 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+
+use crate::files::bif::BifReader;
+use crate::files::key::KeyFile2;
+use crate::helpers::file::read_file_to_vec;
+use crate::types::{
+    ErfFile,
+    GffValue,
+    Resource,
+    ResourceType,
+    ResRef,
+    Error as MyError,
+};
+use crate::GffFile;
+
+type ResKey = (String, ResourceType);
+
+/// Where a mounted source sits in the lookup order. Sources of a lower kind
+/// shadow those of a higher kind, sources of the same kind shadow the ones
+/// mounted after them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResSourceKind {
+    Override,
+    Module,
+    Hak,
+    Key,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResEntry {
+    pub name: ResRef,
+    pub resource_type: ResourceType,
+    pub source: String,
+}
+
+enum Location {
+    File(PathBuf),
+    Erf(usize),
+    Bif(usize, usize),
+}
+
+enum Storage {
+    Directory,
+    Erf(ErfFile),
+    Key {
+        bif_paths: Vec<PathBuf>,
+        readers: RefCell<Vec<Option<BifReader<BufReader<File>>>>>,
+    },
+}
+
+struct Mount {
+    name: String,
+    kind: ResSourceKind,
+    index: HashMap<ResKey, (ResRef, Location)>,
+    storage: Storage,
+}
+
+impl Mount {
+    fn read(&self, location: &Location, name: &ResRef, resource_type: &ResourceType)
+        -> Result<Resource, MyError>
+    {
+        let data = match (location, &self.storage) {
+            (Location::File(path), _) => read_file_to_vec(path)?,
+            (Location::Erf(i), Storage::Erf(erf)) => erf.resources[*i].data.clone(),
+            (Location::Bif(bif, i), Storage::Key { bif_paths, readers }) => {
+                let mut readers = readers.borrow_mut();
+
+                if readers[*bif].is_none() {
+                    readers[*bif] = Some(BifReader::open(&bif_paths[*bif])?);
+                }
+
+                readers[*bif].as_mut().unwrap().read(*i)?.bytes
+            },
+            _ => unreachable!("Locations always match the storage of their mount."),
+        };
+
+        Ok(Resource {
+            name: name.clone(),
+            data,
+            resource_type: resource_type.clone(),
+        })
+    }
+}
+
+/// Resolves resources the way the game does, across the override
+/// directory, the module, its haks and the base game's key and bifs.
+pub struct ResMan {
+    mounts: Vec<Mount>,
+}
+
+#[inline]
+fn res_key(name: &str, resource_type: &ResourceType)
+    -> ResKey
+{
+    (name.to_lowercase(), resource_type.clone())
+}
+
+impl ResMan {
+    pub fn new()
+        -> Self
+    {
+        ResMan {
+            mounts: Vec::new(),
+        }
+    }
+
+    fn add_mount(&mut self, mount: Mount)
+        -> &mut Self
+    {
+        self.mounts.push(mount);
+        self.mounts.sort_by_key(|m| m.kind);
+        self
+    }
+
+    /// Mounts every `name.ext` file in `dir` whose extension is a known
+    /// resource type.
+    pub fn mount_override<P: AsRef<Path>>(&mut self, dir: P)
+        -> Result<&mut Self, MyError>
+    {
+        let mut index = HashMap::new();
+
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+
+            if ! path.is_file() {
+                continue;
+            }
+
+            let stem = path.file_stem().and_then(|s| s.to_str());
+            let extension = path.extension().and_then(|s| s.to_str());
+
+            if let (Some(stem), Some(extension)) = (stem, extension) {
+                let resource_type = match ResourceType::from_extension(extension) {
+                    Some(resource_type) => resource_type,
+                    None => continue,
+                };
+
+                if let Ok(name) = ResRef::try_from(stem) {
+                    index.insert(res_key(stem, &resource_type), (name, Location::File(path)));
+                }
+            }
+        }
+
+        Ok(self.add_mount(Mount {
+            name: dir.as_ref().to_string_lossy().into_owned(),
+            kind: ResSourceKind::Override,
+            index,
+            storage: Storage::Directory,
+        }))
+    }
+
+    pub fn mount_erf_file(&mut self, name: &str, kind: ResSourceKind, erf: ErfFile)
+        -> &mut Self
+    {
+        let index = erf.resources
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                (res_key(&r.name, &r.resource_type), (r.name.clone(), Location::Erf(i)))
+            })
+            .collect();
+
+        self.add_mount(Mount {
+            name: name.to_owned(),
+            kind,
+            index,
+            storage: Storage::Erf(erf),
+        })
+    }
+
+    pub fn mount_erf<P: AsRef<Path>>(&mut self, path: P, kind: ResSourceKind)
+        -> Result<&mut Self, MyError>
+    {
+        let mut f = File::open(&path)?;
+        let erf = ErfFile::parse_from(&mut f)?;
+        let name = path.as_ref().to_string_lossy().into_owned();
+
+        Ok(self.mount_erf_file(&name, kind, erf))
+    }
+
+    pub fn mount_hak<P: AsRef<Path>>(&mut self, path: P)
+        -> Result<&mut Self, MyError>
+    {
+        self.mount_erf(path, ResSourceKind::Hak)
+    }
+
+    /// Mounts a module and then every hak listed in its `module.ifo`, in
+    /// the order the module lists them, from `hak_dir`.
+    pub fn mount_module<P: AsRef<Path>, H: AsRef<Path>>(&mut self, path: P, hak_dir: H)
+        -> Result<&mut Self, MyError>
+    {
+        let mut f = File::open(&path)?;
+        let erf = ErfFile::parse_from(&mut f)?;
+        let haks = module_hak_list(&erf)?;
+        let name = path.as_ref().to_string_lossy().into_owned();
+
+        self.mount_erf_file(&name, ResSourceKind::Module, erf);
+
+        for hak in haks {
+            self.mount_hak(hak_dir.as_ref().join(format!("{}.hak", hak)))?;
+        }
+
+        Ok(self)
+    }
+
+    /// Mounts a key file, resolving its bif names (`data\2da.bif`) against
+    /// `root`, the game's install directory.
+    pub fn mount_key<P: AsRef<Path>, R: AsRef<Path>>(&mut self, key_path: P, root: R)
+        -> Result<&mut Self, MyError>
+    {
+        let key = crate::parse_key(&key_path)?;
+        let name = key_path.as_ref().to_string_lossy().into_owned();
+
+        Ok(self.mount_key_file(&name, key, root))
+    }
+
+    pub fn mount_key_file<R: AsRef<Path>>(&mut self, name: &str, key: KeyFile2, root: R)
+        -> &mut Self
+    {
+        let mut index = HashMap::new();
+
+        let bif_paths = key.0
+            .into_iter()
+            .enumerate()
+            .map(|(bif_index, bif)| {
+                for r in bif.resources {
+                    if let Ok(name) = ResRef::try_from(r.name.as_str()) {
+                        let location = Location::Bif(bif_index, r.resource_index);
+                        index.insert(res_key(&r.name, &r.resource_type), (name, location));
+                    }
+                }
+
+                let relative = bif.name.replace('\\', "/");
+                root.as_ref().join(relative)
+            })
+            .collect::<Vec<PathBuf>>();
+
+        let readers = RefCell::new(bif_paths.iter().map(|_| None).collect());
+
+        self.add_mount(Mount {
+            name: name.to_owned(),
+            kind: ResSourceKind::Key,
+            index,
+            storage: Storage::Key {
+                bif_paths,
+                readers,
+            },
+        })
+    }
+
+    pub fn contains(&self, name: &ResRef, resource_type: ResourceType)
+        -> bool
+    {
+        let key = res_key(name, &resource_type);
+
+        self.mounts
+            .iter()
+            .any(|m| m.index.contains_key(&key))
+    }
+
+    pub fn try_get(&self, name: &ResRef, resource_type: ResourceType)
+        -> Result<Option<Resource>, MyError>
+    {
+        let key = res_key(name, &resource_type);
+
+        self.mounts
+            .iter()
+            .find_map(|m| m.index.get(&key).map(|found| (m, found)))
+            .map(|(m, (name, location))| m.read(location, name, &resource_type))
+            .transpose()
+    }
+
+    /// The highest priority version of a resource, `None` if no source has
+    /// it or it couldn't be read.
+    pub fn get(&self, name: &ResRef, resource_type: ResourceType)
+        -> Option<Resource>
+    {
+        self.try_get(name, resource_type).ok().flatten()
+    }
+
+    /// Names of every source providing the resource, highest priority
+    /// first. Every source after the first is shadowed.
+    pub fn providers(&self, name: &ResRef, resource_type: ResourceType)
+        -> Vec<&str>
+    {
+        let key = res_key(name, &resource_type);
+
+        self.mounts
+            .iter()
+            .filter(|m| m.index.contains_key(&key))
+            .map(|m| m.name.as_str())
+            .collect()
+    }
+
+    fn entries(&self, shadowed: bool)
+        -> Vec<ResEntry>
+    {
+        let mut seen = HashSet::new();
+        let mut entries = Vec::new();
+
+        for m in self.mounts.iter() {
+            for (key, (name, _)) in m.index.iter() {
+                if seen.insert(key.clone()) != shadowed {
+                    entries.push(ResEntry {
+                        name: name.clone(),
+                        resource_type: key.1.clone(),
+                        source: m.name.clone(),
+                    });
+                }
+            }
+        }
+
+        entries.sort_by_key(|e| (e.name.to_lowercase(), e.resource_type.id()));
+        entries
+    }
+
+    /// Every resource that `get` can return, with the source it comes from.
+    pub fn list(&self)
+        -> Vec<ResEntry>
+    {
+        self.entries(false)
+    }
+
+    /// Every resource hidden by a higher priority source.
+    pub fn shadowed(&self)
+        -> Vec<ResEntry>
+    {
+        self.entries(true)
+    }
+}
+
+impl Default for ResMan {
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+fn module_hak_list(erf: &ErfFile)
+    -> Result<Vec<String>, MyError>
+{
+    let ifo = erf.resources
+        .iter()
+        .find(|r| r.resource_type == ResourceType::ifo && r.name.eq_ignore_ascii_case("module"));
+
+    let ifo = match ifo {
+        Some(ifo) => GffFile::parse_from(&mut Cursor::new(&ifo.data))?,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut haks = match ifo.root.get("Mod_HakList") {
+        Some(GffValue::List(list)) => list
+            .iter()
+            .filter_map(|s| match s.get("Mod_Hak") {
+                Some(GffValue::CExoString(hak)) => Some(hak.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    // Modules from before the hak list only have a single hak field.
+    if haks.is_empty() {
+        if let Some(GffValue::CExoString(hak)) = ifo.root.get("Mod_Hak") {
+            if ! hak.is_empty() {
+                haks.push(hak.clone());
+            }
+        }
+    }
+
+    Ok(haks)
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::types::{FileType, GffStruct};
+    use crate::{BifBuilder, KeyBuilder};
+    use crate::helpers::temp_dir::TempDir;
+
+    fn resource(name: &str, resource_type: ResourceType, data: &[u8])
+        -> Resource
+    {
+        Resource {
+            name: ResRef::try_from(name).unwrap(),
+            data: data.to_vec(),
+            resource_type,
+        }
+    }
+
+    fn test_dir(name: &str)
+        -> TempDir
+    {
+        let dir = TempDir::new(&format!("resman_{}", name));
+        fs::create_dir_all(dir.join("data")).unwrap();
+        fs::create_dir_all(dir.join("hak")).unwrap();
+        fs::create_dir_all(dir.join("override")).unwrap();
+        dir
+    }
+
+    fn write_key(root: &Path)
+    {
+        let mut bif = BifBuilder::new();
+        bif
+            .add_resource(resource("appearance", ResourceType::x2da, b"base appearance"))
+            .add_resource(resource("baseitems", ResourceType::x2da, b"base baseitems"))
+            .add_resource(resource("nw_it_gem001", ResourceType::uti, b"base gem"));
+
+        let mut key = KeyBuilder::new();
+        key.add_bif(bif.key_bif_file("data\\base.bif"));
+        key.write(&mut File::create(root.join("chitin.key")).unwrap()).unwrap();
+
+        bif.write(&mut File::create(root.join("data").join("base.bif")).unwrap()).unwrap();
+    }
+
+    fn write_erf(path: &Path, file_type: FileType, mut resources: Vec<Resource>)
+    {
+        let mut erf = ErfFile::new();
+        erf.add_resources(&mut resources);
+        erf.write(&mut File::create(path).unwrap(), file_type).unwrap();
+    }
+
+    fn module_ifo(haks: &[&str])
+        -> Resource
+    {
+        let list = haks
+            .iter()
+            .map(|hak| {
+                let mut s = GffStruct::new(8);
+                s.add_field("Mod_Hak", GffValue::CExoString(hak.to_string()));
+                s
+            })
+            .collect();
+
+        let mut ifo = GffFile::new("IFO ");
+        ifo.root.add_field("Mod_HakList", GffValue::List(list));
+
+        let mut data = Vec::new();
+        ifo.write(&mut data).unwrap();
+
+        resource("module", ResourceType::ifo, &data)
+    }
+
+    fn sample_resman(dir: &Path)
+        -> ResMan
+    {
+        write_key(dir);
+
+        write_erf(&dir.join("hak").join("top.hak"), FileType::Hak, vec![
+            resource("appearance", ResourceType::x2da, b"top appearance"),
+        ]);
+        write_erf(&dir.join("hak").join("bottom.hak"), FileType::Hak, vec![
+            resource("appearance", ResourceType::x2da, b"bottom appearance"),
+            resource("baseitems", ResourceType::x2da, b"bottom baseitems"),
+        ]);
+        write_erf(&dir.join("test.mod"), FileType::Mod, vec![
+            module_ifo(&["top", "bottom"]),
+            resource("nw_it_gem001", ResourceType::uti, b"module gem"),
+        ]);
+
+        fs::write(dir.join("override").join("NW_IT_GEM001.uti"), b"override gem").unwrap();
+
+        let mut resman = ResMan::new();
+
+        // Mount order doesn't matter, only the kind of source does.
+        resman
+            .mount_key(dir.join("chitin.key"), dir)
+            .unwrap()
+            .mount_module(dir.join("test.mod"), dir.join("hak"))
+            .unwrap()
+            .mount_override(dir.join("override"))
+            .unwrap();
+
+        resman
+    }
+
+    fn get_data(resman: &ResMan, name: &str, resource_type: ResourceType)
+        -> Vec<u8>
+    {
+        resman.get(&ResRef::try_from(name).unwrap(), resource_type).unwrap().data
+    }
+
+    #[test]
+    fn resman_priority() {
+        let dir = test_dir("priority");
+        let resman = sample_resman(&dir);
+
+        assert_eq!(b"override gem".to_vec(), get_data(&resman, "nw_it_gem001", ResourceType::uti));
+        assert_eq!(b"top appearance".to_vec(), get_data(&resman, "appearance", ResourceType::x2da));
+        assert_eq!(b"bottom baseitems".to_vec(), get_data(&resman, "baseitems", ResourceType::x2da));
+        assert!(resman.get(&ResRef::try_from("appearance").unwrap(), ResourceType::uti).is_none());
+    }
+
+    #[test]
+    fn resman_reads_from_bif() {
+        let dir = test_dir("bif");
+        write_key(&dir);
+
+        let mut resman = ResMan::new();
+        resman.mount_key(dir.join("chitin.key"), &dir).unwrap();
+
+        assert_eq!(b"base baseitems".to_vec(), get_data(&resman, "baseitems", ResourceType::x2da));
+        assert_eq!(3, resman.list().len());
+    }
+
+    #[test]
+    fn resman_shadowing() {
+        let dir = test_dir("shadowing");
+        let resman = sample_resman(&dir);
+        let appearance = ResRef::try_from("appearance").unwrap();
+
+        let providers = resman.providers(&appearance, ResourceType::x2da);
+
+        assert_eq!(3, providers.len());
+        assert!(providers[0].ends_with("top.hak"));
+        assert!(providers[2].ends_with("chitin.key"));
+
+        // appearance x2, baseitems x1, nw_it_gem001 x2
+        assert_eq!(5, resman.shadowed().len());
+        assert_eq!(4, resman.list().len());
+    }
+}
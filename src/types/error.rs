@@ -2,9 +2,10 @@ use std::fmt;
 use std::convert::From;
 use std::io;
 
-use crate::types::FileType;
+use crate::types::{FileType, SsfSlot};
 use crate::types::ResRefError;
 use crate::files::x2da::types::X2daError as E2da;
+use crate::files::gff::types::GffError;
 
 #[derive(Debug)]
 pub enum Error
@@ -15,6 +16,23 @@ pub enum Error
     PathAlreadyExists(String),
     InvalidFileTypeForErf(FileType),
     ResRefError(ResRefError),
+    GffError(GffError),
+    BifResourceIndexTooLarge(usize),
+    BifIndexTooLarge(usize),
+    BifTooLarge(usize),
+    BadMagic(FileType, String),
+    UnsupportedVersion(FileType, String),
+    OffsetOutOfBounds(usize),
+    InvalidResourceIndex(usize),
+    UnknownExtension(String),
+    DuplicateResource(String),
+    UnsafeResourceName(String),
+    UnencodableString(String, &'static str),
+    InvalidTranslation(usize, String),
+    InvalidStrRef(u32),
+    TlkFull,
+    TlkEntryOutOfOrder(usize),
+    TooManySsfEntries(usize),
 }
 
 impl fmt::Display for Error
@@ -38,7 +56,41 @@ impl fmt::Display for Error
             Error::X2daError(e) =>
                 write!(f, "{}", e),
             Error::ResRefError(e) =>
-                write!(f, "{}", e)
+                write!(f, "{}", e),
+            Error::GffError(e) =>
+                write!(f, "{}", e),
+            Error::BifResourceIndexTooLarge(index) =>
+                write!(f, "Bif resource index <{}> doesn't fit in the 20 bits of a resource id.", index),
+            Error::BifIndexTooLarge(index) =>
+                write!(f, "Bif index <{}> doesn't fit in the 12 bits of a resource id.", index),
+            Error::BifTooLarge(offset) =>
+                write!(f, "Bif data reaching offset <{}> doesn't fit in the 32 bit offsets of a bif.", offset),
+            Error::BadMagic(file_type, found) =>
+                write!(f, "Expected a <{}> file, found file type <{}>.", file_type.as_str_ref(), found),
+            Error::UnsupportedVersion(file_type, found) =>
+                write!(f, "Version <{}> of <{}> files isn't supported.", found, file_type.as_str_ref()),
+            Error::OffsetOutOfBounds(offset) =>
+                write!(f, "Offset <{}> is past the end of the file.", offset),
+            Error::InvalidResourceIndex(index) =>
+                write!(f, "There is no resource at index <{}>.", index),
+            Error::UnknownExtension(path) =>
+                write!(f, "<{}> doesn't have the extension of a known resource type.", path),
+            Error::DuplicateResource(name) =>
+                write!(f, "Resource <{}> was found more than once.", name),
+            Error::UnsafeResourceName(name) =>
+                write!(f, "Resource name <{}> isn't a plain file name.", name),
+            Error::UnencodableString(string, encoding) =>
+                write!(f, "<{}> can't be encoded as <{}>.", string, encoding),
+            Error::InvalidTranslation(line, message) =>
+                write!(f, "Invalid translation file at line <{}>: {}", line, message),
+            Error::InvalidStrRef(strref) =>
+                write!(f, "StrRef <{}> doesn't belong to this tlk.", strref),
+            Error::TlkFull =>
+                write!(f, "The tlk has no free StrRef left to move an entry to."),
+            Error::TlkEntryOutOfOrder(index) =>
+                write!(f, "Tlk entry <{}> is out of order or past the string count.", index),
+            Error::TooManySsfEntries(count) =>
+                write!(f, "A soundset has {} slots, <{}> entries don't fit.", SsfSlot::COUNT, count),
         }
     }
 }
@@ -67,4 +119,12 @@ impl From<ResRefError> for Error {
     }
 }
 
+impl From<GffError> for Error {
+    fn from(e: GffError)
+        -> Self
+    {
+        Error::GffError(e)
+    }
+}
+
 impl std::error::Error for Error {}
\ No newline at end of file
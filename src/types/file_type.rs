@@ -12,6 +12,7 @@ pub enum FileType {
     Hak,
     X2da,
     Tlk,
+    Gff,
 }
 
 impl FileType {
@@ -19,7 +20,7 @@ impl FileType {
     {
         match self {
             FileType::Erf => "ERF ",
-            FileType::Bif => "BIF ",
+            FileType::Bif => "BIFF",
             FileType::Key => "KEY ",
             FileType::Ssf => "SSF ",
             FileType::Mod => "MOD ",
@@ -27,17 +28,32 @@ impl FileType {
             FileType::Hak => "HAK ",
             FileType::X2da => "2DA ",
             FileType::Tlk => "TLK ",
+            FileType::Gff => "GFF ",
             FileType::Unknown => "",
         }
     }
 }
 
+impl FileType {
+    /// The erf based file type for a file extension, `Unknown` otherwise.
+    pub fn from_erf_extension(extension: &str) -> FileType
+    {
+        match extension.to_lowercase().as_str() {
+            "erf" => FileType::Erf,
+            "hak" => FileType::Hak,
+            "mod" => FileType::Mod,
+            "sav" => FileType::Sav,
+            _ => FileType::Unknown,
+        }
+    }
+}
+
 impl From<&str> for FileType {
     fn from(s: &str) -> FileType
     {
         match s {
             "ERF " => FileType::Erf,
-            "BIF " => FileType::Bif,
+            "BIFF" | "BIF " => FileType::Bif,
             "KEY " => FileType::Key,
             "SSF " => FileType::Ssf,
             "MOD " => FileType::Mod,
@@ -45,6 +61,7 @@ impl From<&str> for FileType {
             "HAK " => FileType::Hak,
             "2DA " => FileType::X2da,
             "TLK " => FileType::Tlk,
+            "GFF " => FileType::Gff,
             _ => FileType::Unknown,
         }
     }
@@ -69,4 +86,10 @@ mod tests
         assert_eq!(FileType::Erf, FileType::from("ERF "));
         assert_eq!(FileType::Ssf, FileType::from("SSF "));
     }
+
+    #[test]
+    fn file_type_from_erf_extension() {
+        assert_eq!(FileType::Hak, FileType::from_erf_extension("HAK"));
+        assert_eq!(FileType::Unknown, FileType::from_erf_extension("2da"));
+    }
 }
\ No newline at end of file
@@ -1,6 +1,16 @@
 use std::convert::From;
 
-#[derive(Debug, Copy, Clone,)]
+use encoding_rs::{
+    Encoding,
+    BIG5,
+    EUC_KR,
+    GBK,
+    SHIFT_JIS,
+    WINDOWS_1250,
+    WINDOWS_1252,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum LanguageId
 {
     Unknown = 75666776,
@@ -30,8 +40,68 @@ impl From<u32> for LanguageId
             128 => LanguageId::Korean,
             129 => LanguageId::ChineseTraditional,
             130 => LanguageId::ChineseSimplified,
-            121 => LanguageId::Japanese,
+            131 => LanguageId::Japanese,
+            _ => LanguageId::Unknown,
+        }
+    }
+}
+
+impl LanguageId
+{
+    /// The code page the game uses for strings of this language.
+    pub fn encoding(&self)
+        -> &'static Encoding
+    {
+        match self {
+            LanguageId::Polish => WINDOWS_1250,
+            LanguageId::Korean => EUC_KR,
+            LanguageId::ChineseTraditional => BIG5,
+            LanguageId::ChineseSimplified => GBK,
+            LanguageId::Japanese => SHIFT_JIS,
+            _ => WINDOWS_1252,
+        }
+    }
+
+    /// The BCP 47 tag translation tools know the language by.
+    pub fn code(&self)
+        -> &'static str
+    {
+        match self {
+            LanguageId::Unknown => "und",
+            LanguageId::English => "en",
+            LanguageId::French => "fr",
+            LanguageId::German => "de",
+            LanguageId::Italian => "it",
+            LanguageId::Spanish => "es",
+            LanguageId::Polish => "pl",
+            LanguageId::Korean => "ko",
+            LanguageId::ChineseTraditional => "zh-TW",
+            LanguageId::ChineseSimplified => "zh-CN",
+            LanguageId::Japanese => "ja",
+        }
+    }
+
+    /// The language of a BCP 47 tag, ignoring case and any region except
+    /// for Chinese. `_` is accepted in place of `-` like gettext writes it.
+    pub fn from_code(code: &str)
+        -> Self
+    {
+        let code = code.trim().replace('_', "-").to_ascii_lowercase();
+        let mut parts = code.split('-');
+
+        match (parts.next().unwrap_or_default(), parts.next()) {
+            ("en", _) => LanguageId::English,
+            ("fr", _) => LanguageId::French,
+            ("de", _) => LanguageId::German,
+            ("it", _) => LanguageId::Italian,
+            ("es", _) => LanguageId::Spanish,
+            ("pl", _) => LanguageId::Polish,
+            ("ko", _) => LanguageId::Korean,
+            ("zh", Some("tw")) | ("zh", Some("hk")) | ("zh", Some("hant")) =>
+                LanguageId::ChineseTraditional,
+            ("zh", _) => LanguageId::ChineseSimplified,
+            ("ja", _) => LanguageId::Japanese,
             _ => LanguageId::Unknown,
         }
     }
-}
\ No newline at end of file
+}
@@ -5,18 +5,38 @@ mod error;
 mod language_id;
 mod file_type;
 mod version;
+mod strref;
 
-pub use resref::{ResRef, ResRefError};
+pub use resref::{ResRef, ResRefError, RES_REF_LENGTH, RES_REF_LENGTH_LONG};
 pub use resource_type::ResourceType;
 pub use resource::Resource;
 pub use error::Error;
 pub use language_id::LanguageId;
+pub use encoding_rs::Encoding;
 pub use file_type::FileType;
 pub use version::Version;
+pub use strref::{StrRef, CUSTOM_TLK_FLAG};
 pub use crate::files::tlk::types::{TlkEntry, TlkSound};
-pub use crate::files::x2da::types::{X2daRow, X2daItem, X2daError};
+pub use crate::files::tlk::talk_table::TlkGender;
+pub use crate::files::tlk::translation::{TranslationUnit, TranslationFormat, TranslationMerge};
+pub use crate::files::tlk::diff::TlkChange;
+pub use crate::files::tlk::merge::{TlkCollision, TlkRemap, TlkMergeResult};
+pub use crate::files::x2da::types::{X2daRow, X2daItem, X2daError, X2daBuilderConfig, X2daLineEnding};
+pub use crate::files::x2da::merge::{X2daConflict, X2daMergeResult, MergeSide};
+pub use crate::files::x2da::diff::X2daChange;
+#[cfg(feature = "derive")]
+pub use nwn_files_derive::X2daRow;
 pub use crate::files::erf::types::{ErfFile};
-pub use crate::files::ssf::types::{SsfEntry};
+pub use crate::files::ssf::types::{SsfEntry, SsfSlot};
+pub use crate::files::gff::types::{
+    GffStruct,
+    GffField,
+    GffValue,
+    GffFieldType,
+    GffError,
+    CExoLocString,
+    CExoLocSubString,
+};
 
 use std::io::prelude::*;
 
@@ -1,152 +1,215 @@
-
 #![allow(non_camel_case_types)]
 
-use std::convert::From;
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum ResourceType {
-    Unknown = 0,
-    bmp = 1,
-    tga = 2,
-    wav = 4,
-    plt = 6,
-    ini = 7,
-    txt = 10,
-    mdl = 2002,
-    nss = 2009,
-    ncs = 2010,
-    are = 2012,
-    set = 2013,
-    ifo = 2014,
-    bic = 2015,
-    wok = 2016,
-    x2da = 2017,
-    txi = 2020,
-    git = 2023,
-    uti = 2025,
-    utc = 2027,
-    dlg = 2029,
-    itp = 2030,
-    utt = 2032,
-    dds = 2033,
-    uts = 2035,
-    ltr = 2036,
-    gff = 2037,
-    fac = 2038,
-    ute = 2040,
-    utd = 2042,
-    utp = 2044,
-    dft = 2045,
-    gic = 2046,
-    gui = 2047,
-    utm = 2051,
-    dwk = 2052,
-    pwk = 2053,
-    jrl = 2056,
-    utw = 2058,
-    ssf = 2060,
-    ndb = 2064,
-    ptm = 2065,
-    ptt = 2066,
-}
+use std::convert::{TryFrom, From};
 
-impl From<u16> for ResourceType {
-    fn from(i: u16) -> Self {
-        match i {
-            1 => ResourceType::bmp,
-            2 => ResourceType::tga,
-            4 => ResourceType::wav,
-            6 => ResourceType::plt,
-            7 => ResourceType::ini,
-            10 => ResourceType::txt,
-            2002 => ResourceType::mdl,
-            2009 => ResourceType::nss,
-            2010 => ResourceType::ncs,
-            2012 => ResourceType::are,
-            2013 => ResourceType::set,
-            2014 => ResourceType::ifo,
-            2015 => ResourceType::bic,
-            2016 => ResourceType::wok,
-            2017 => ResourceType::x2da,
-            2020 => ResourceType::txi,
-            2023 => ResourceType::git,
-            2025 => ResourceType::uti,
-            2027 => ResourceType::utc,
-            2029 => ResourceType::dlg,
-            2030 => ResourceType::itp,
-            2032 => ResourceType::utt,
-            2033 => ResourceType::dds,
-            2035 => ResourceType::uts,
-            2036 => ResourceType::ltr,
-            2037 => ResourceType::gff,
-            2038 => ResourceType::fac,
-            2040 => ResourceType::ute,
-            2042 => ResourceType::utd,
-            2044 => ResourceType::utp,
-            2045 => ResourceType::dft,
-            2046 => ResourceType::gic,
-            2047 => ResourceType::gui,
-            2051 => ResourceType::utm,
-            2052 => ResourceType::dwk,
-            2053 => ResourceType::pwk,
-            2056 => ResourceType::jrl,
-            2058 => ResourceType::utw,
-            2060 => ResourceType::ssf,
-            2064 => ResourceType::ndb,
-            2065 => ResourceType::ptm,
-            2066 => ResourceType::ptt,
-            _ => ResourceType::Unknown,
+macro_rules! resource_types {
+    ($($variant:ident = $id:expr => $extension:expr,)*) => {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum ResourceType {
+            $($variant,)*
+            /// A type missing from the table, keeping its id so it can be
+            /// written back unchanged.
+            Unknown(u16),
         }
-    }
+
+        impl ResourceType {
+            pub fn id(&self)
+                -> u16
+            {
+                match self {
+                    $(ResourceType::$variant => $id,)*
+                    ResourceType::Unknown(id) => *id,
+                }
+            }
+
+            /// The file extension, without the dot. `None` for unknown types.
+            pub fn extension(&self)
+                -> Option<&'static str>
+            {
+                match self {
+                    $(ResourceType::$variant => Some($extension),)*
+                    ResourceType::Unknown(_) => None,
+                }
+            }
+
+            /// Looks up a resource type from a file extension, ignoring case.
+            pub fn from_extension(extension: &str)
+                -> Option<ResourceType>
+            {
+                match extension.to_lowercase().as_str() {
+                    $($extension => Some(ResourceType::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl From<u16> for ResourceType {
+            fn from(i: u16) -> Self {
+                match i {
+                    $($id => ResourceType::$variant,)*
+                    _ => ResourceType::Unknown(i),
+                }
+            }
+        }
+    };
 }
 
+resource_types! {
+    bmp = 1 => "bmp",
+    mve = 2 => "mve",
+    tga = 3 => "tga",
+    wav = 4 => "wav",
+    plt = 6 => "plt",
+    ini = 7 => "ini",
+    bmu = 8 => "bmu",
+    mpg = 9 => "mpg",
+    txt = 10 => "txt",
+    wma = 11 => "wma",
+    wmv = 12 => "wmv",
+    xmv = 13 => "xmv",
+    plh = 2000 => "plh",
+    tex = 2001 => "tex",
+    mdl = 2002 => "mdl",
+    thg = 2003 => "thg",
+    fnt = 2005 => "fnt",
+    lua = 2007 => "lua",
+    slt = 2008 => "slt",
+    nss = 2009 => "nss",
+    ncs = 2010 => "ncs",
+    r#mod = 2011 => "mod",
+    are = 2012 => "are",
+    set = 2013 => "set",
+    ifo = 2014 => "ifo",
+    bic = 2015 => "bic",
+    wok = 2016 => "wok",
+    x2da = 2017 => "2da",
+    tlk = 2018 => "tlk",
+    txi = 2022 => "txi",
+    git = 2023 => "git",
+    bti = 2024 => "bti",
+    uti = 2025 => "uti",
+    btc = 2026 => "btc",
+    utc = 2027 => "utc",
+    dlg = 2029 => "dlg",
+    itp = 2030 => "itp",
+    btt = 2031 => "btt",
+    utt = 2032 => "utt",
+    dds = 2033 => "dds",
+    bts = 2034 => "bts",
+    uts = 2035 => "uts",
+    ltr = 2036 => "ltr",
+    gff = 2037 => "gff",
+    fac = 2038 => "fac",
+    bte = 2039 => "bte",
+    ute = 2040 => "ute",
+    btd = 2041 => "btd",
+    utd = 2042 => "utd",
+    btp = 2043 => "btp",
+    utp = 2044 => "utp",
+    dft = 2045 => "dft",
+    gic = 2046 => "gic",
+    gui = 2047 => "gui",
+    css = 2048 => "css",
+    ccs = 2049 => "ccs",
+    btm = 2050 => "btm",
+    utm = 2051 => "utm",
+    dwk = 2052 => "dwk",
+    pwk = 2053 => "pwk",
+    btg = 2054 => "btg",
+    utg = 2055 => "utg",
+    jrl = 2056 => "jrl",
+    sav = 2057 => "sav",
+    utw = 2058 => "utw",
+    x4pc = 2059 => "4pc",
+    ssf = 2060 => "ssf",
+    hak = 2061 => "hak",
+    nwm = 2062 => "nwm",
+    bik = 2063 => "bik",
+    ndb = 2064 => "ndb",
+    ptm = 2065 => "ptm",
+    ptt = 2066 => "ptt",
+    bak = 2067 => "bak",
+    dat = 2068 => "dat",
+    shd = 2069 => "shd",
+    xbc = 2070 => "xbc",
+    wbm = 2071 => "wbm",
+    mtr = 2072 => "mtr",
+    ktx = 2073 => "ktx",
+    ttf = 2074 => "ttf",
+    sql = 2075 => "sql",
+    tml = 2076 => "tml",
+    sq3 = 2077 => "sq3",
+    lod = 2078 => "lod",
+    gif = 2079 => "gif",
+    png = 2080 => "png",
+    jpg = 2081 => "jpg",
+    caf = 2082 => "caf",
+    jui = 2083 => "jui",
+    sslm = 2084 => "sslm",
+    mp3 = 2085 => "mp3",
+    ids = 9996 => "ids",
+    erf = 9997 => "erf",
+    bif = 9998 => "bif",
+    key = 9999 => "key",
+}
 
+/// Ids past `u16::MAX` become `Unknown(0xFFFF)`, the invalid resource type,
+/// rather than being truncated into an unrelated one.
 impl From<u32> for ResourceType {
     fn from(i: u32) -> Self {
-        match i {
-            1 => ResourceType::bmp,
-            2 => ResourceType::tga,
-            4 => ResourceType::wav,
-            6 => ResourceType::plt,
-            7 => ResourceType::ini,
-            10 => ResourceType::txt,
-            2002 => ResourceType::mdl,
-            2009 => ResourceType::nss,
-            2010 => ResourceType::ncs,
-            2012 => ResourceType::are,
-            2013 => ResourceType::set,
-            2014 => ResourceType::ifo,
-            2015 => ResourceType::bic,
-            2016 => ResourceType::wok,
-            2017 => ResourceType::x2da,
-            2020 => ResourceType::txi,
-            2023 => ResourceType::git,
-            2025 => ResourceType::uti,
-            2027 => ResourceType::utc,
-            2029 => ResourceType::dlg,
-            2030 => ResourceType::itp,
-            2032 => ResourceType::utt,
-            2033 => ResourceType::dds,
-            2035 => ResourceType::uts,
-            2036 => ResourceType::ltr,
-            2037 => ResourceType::gff,
-            2038 => ResourceType::fac,
-            2040 => ResourceType::ute,
-            2042 => ResourceType::utd,
-            2044 => ResourceType::utp,
-            2045 => ResourceType::dft,
-            2046 => ResourceType::gic,
-            2047 => ResourceType::gui,
-            2051 => ResourceType::utm,
-            2052 => ResourceType::dwk,
-            2053 => ResourceType::pwk,
-            2056 => ResourceType::jrl,
-            2058 => ResourceType::utw,
-            2060 => ResourceType::ssf,
-            2064 => ResourceType::ndb,
-            2065 => ResourceType::ptm,
-            2066 => ResourceType::ptt,
-            _ => ResourceType::Unknown,
+        match u16::try_from(i) {
+            Ok(id) => ResourceType::from(id),
+            Err(_) => ResourceType::Unknown(u16::MAX),
         }
     }
-}
\ No newline at end of file
+}
+
+impl From<&ResourceType> for u16 {
+    fn from(resource_type: &ResourceType) -> Self {
+        resource_type.id()
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn resource_type_from_extension() {
+        assert_eq!(Some(ResourceType::uti), ResourceType::from_extension("uti"));
+        assert_eq!(Some(ResourceType::x2da), ResourceType::from_extension("2DA"));
+        assert_eq!(Some(ResourceType::r#mod), ResourceType::from_extension("mod"));
+        assert_eq!(None, ResourceType::from_extension("exe"));
+    }
+
+    #[test]
+    fn resource_type_to_extension() {
+        assert_eq!(Some("uti"), ResourceType::uti.extension());
+        assert_eq!(Some("2da"), ResourceType::x2da.extension());
+        assert_eq!(None, ResourceType::Unknown(1234).extension());
+    }
+
+    #[test]
+    fn resource_type_ids() {
+        assert_eq!(ResourceType::tga, ResourceType::from(3u16));
+        assert_eq!(ResourceType::txi, ResourceType::from(2022u16));
+        assert_eq!(ResourceType::sq3, ResourceType::from(2077u32));
+        assert_eq!(ResourceType::mp3, ResourceType::from(2085u16));
+        assert_eq!(Some(ResourceType::sslm), ResourceType::from_extension("sslm"));
+        assert_eq!(2069, ResourceType::shd.id());
+    }
+
+    #[test]
+    fn unknown_resource_type_keeps_id() {
+        let resource_type = ResourceType::from(4242u16);
+
+        assert_eq!(ResourceType::Unknown(4242), resource_type);
+        assert_eq!(4242, resource_type.id());
+    }
+
+    #[test]
+    fn resource_type_id_past_u16() {
+        assert_eq!(ResourceType::Unknown(u16::MAX), ResourceType::from(0x10000u32 + 3));
+    }
+}
@@ -3,9 +3,12 @@ use std::error::Error;
 use std::fmt;
 use std::ops::{ Deref, DerefMut };
 
-const RES_REF_LENGTH: usize = 16;
+pub const RES_REF_LENGTH: usize = 16;
 
-#[derive(Debug, PartialEq, Clone, Default)]
+/// NWN:EE containers like ERF V1.1 allow longer resrefs.
+pub const RES_REF_LENGTH_LONG: usize = 32;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
 pub struct ResRef(String);
 
 #[derive(Debug, PartialEq)]
@@ -18,7 +21,7 @@ impl fmt::Display for ResRefError {
     {
         match self {
             ResRefError::InvalidLengthTooLong =>
-                write!(f, "ResRefError: ResRef needs to be 16 chars or less."),
+                write!(f, "ResRefError: ResRef needs to be 16 chars or less, or 32 where the container allows it."),
         }
     }
 }
@@ -28,22 +31,46 @@ impl Error for ResRefError {}
 impl ResRef {
     fn new(s: String) -> Result<Self, ResRefError>
     {
-        if s.len() > RES_REF_LENGTH {
+        ResRef::with_max_length(s, RES_REF_LENGTH)
+    }
+
+    pub fn with_max_length(s: String, max_length: usize) -> Result<Self, ResRefError>
+    {
+        if s.len() > max_length {
             Err(ResRefError::InvalidLengthTooLong)
         } else {
             Ok(ResRef(s))
         }
     }
 
-    pub fn serialize(self) -> Vec<u8>
+    /// Like `ResRef::try_from(&[u8])` for containers whose resrefs can be
+    /// longer than 16 chars.
+    pub fn from_bytes_with_max_length(s: &[u8], max_length: usize) -> Result<Self, ResRefError>
+    {
+        let string = String::from_utf8_lossy(s)
+            .trim_end_matches(char::from(0))
+            .to_owned();
+
+        ResRef::with_max_length(string, max_length)
+    }
+
+    /// Serializes to the usual 16 null padded bytes.
+    /// Errors if the resref is longer, see `serialize_with_length`.
+    pub fn serialize(self) -> Result<Vec<u8>, ResRefError>
+    {
+        self.serialize_with_length(RES_REF_LENGTH)
+    }
+
+    pub fn serialize_with_length(self, length: usize) -> Result<Vec<u8>, ResRefError>
     {
-        let difference = RES_REF_LENGTH - self.0.len();
-        let padding = std::iter::repeat(0).take(difference);
+        if self.0.len() > length {
+            return Err(ResRefError::InvalidLengthTooLong);
+        }
 
         let mut bytes = self.0.into_bytes();
-        bytes.extend(padding);
+        bytes.resize(length, 0);
 
-        bytes
+        Ok(bytes)
     }
 }
 
@@ -75,11 +102,7 @@ impl TryFrom<&[u8]> for ResRef {
     fn try_from(s: &[u8])
         -> Result<ResRef, Self::Error>
     {
-        let string = String::from_utf8_lossy(&s)
-            .trim_end_matches(char::from(0))
-            .to_owned();
-
-        ResRef::new(string)
+        ResRef::from_bytes_with_max_length(s, RES_REF_LENGTH)
     }
 }
 
@@ -148,10 +171,24 @@ mod test
     #[test]
     fn res_ref_to_bytes() {
         let resref = ResRef::try_from("abc").unwrap();
-        let serialized = resref.serialize();
+        let serialized = resref.serialize().unwrap();
 
         let expected = [97, 98, 99, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
 
         assert_eq!(&expected, serialized.as_slice());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn long_res_ref() {
+        let name = "a".repeat(32);
+
+        assert!(ResRef::try_from(name.as_str()).is_err());
+
+        let resref = ResRef::with_max_length(name, RES_REF_LENGTH_LONG).unwrap();
+        let serialized = resref.clone().serialize_with_length(RES_REF_LENGTH_LONG).unwrap();
+
+        assert_eq!(32, serialized.len());
+        assert_eq!(ResRefError::InvalidLengthTooLong, resref.clone().serialize_with_length(RES_REF_LENGTH).unwrap_err());
+        assert_eq!(ResRefError::InvalidLengthTooLong, resref.serialize().unwrap_err());
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+use std::convert::From;
+
+/// Set on string references that point into the module's custom tlk rather
+/// than dialog.tlk.
+pub const CUSTOM_TLK_FLAG: u32 = 0x01000000;
+
+/// A reference into the talk table, as stored in 2das and gffs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StrRef(pub u32);
+
+impl StrRef {
+    /// The value used for "no string", e.g. by gff localized strings.
+    pub const NONE: StrRef = StrRef(u32::MAX);
+
+    /// A reference to entry `index` of the custom tlk.
+    pub fn custom(index: u32)
+        -> Self
+    {
+        StrRef(index | CUSTOM_TLK_FLAG)
+    }
+
+    pub fn is_custom(&self)
+        -> bool
+    {
+        *self != StrRef::NONE && self.0 & CUSTOM_TLK_FLAG != 0
+    }
+
+    /// The entry index within its tlk, without the custom flag.
+    pub fn index(&self)
+        -> u32
+    {
+        self.0 & !CUSTOM_TLK_FLAG
+    }
+}
+
+impl From<u32> for StrRef
+{
+    fn from(value: u32) -> Self
+    {
+        StrRef(value)
+    }
+}
+
+impl From<StrRef> for u32
+{
+    fn from(value: StrRef) -> Self
+    {
+        value.0
+    }
+}
+
+impl fmt::Display for StrRef
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)
+        -> fmt::Result
+    {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn strref_custom() {
+        let s = StrRef::custom(12);
+
+        assert_eq!(StrRef(0x0100000C), s);
+        assert!(s.is_custom());
+        assert_eq!(12, s.index());
+        assert!(!StrRef(12).is_custom());
+        assert!(!StrRef::NONE.is_custom());
+    }
+}
@@ -5,8 +5,11 @@ use std::str::Utf8Error;
 pub enum Version {
     Unknown,
     V1,
+    V1_1,
     V2,
+    V2B,
     V3,
+    V3_2,
 }
 
 impl Version {
@@ -15,11 +18,26 @@ impl Version {
     {
         match self {
             Version::V1 => "V1.0",
+            Version::V1_1 => "V1.1",
             Version::V2 => "V2.0",
+            Version::V2B => "V2.b",
             Version::V3 => "V3.0",
+            Version::V3_2 => "V3.2",
             Version::Unknown => "",
         }
     }
+
+    /// The space padded form used by KEY and BIF headers.
+    pub fn as_short_str_ref(&self)
+        -> &'static str
+    {
+        match self {
+            Version::V1 => "V1  ",
+            Version::V2 => "V2  ",
+            Version::V3 => "V3  ",
+            _ => self.as_str_ref(),
+        }
+    }
 }
 
 impl From<&str> for Version
@@ -28,8 +46,11 @@ impl From<&str> for Version
     {
         match s {
             "V1.0" | "V1  " => Version::V1,
+            "V1.1" => Version::V1_1,
             "V2.0" | "V2  " => Version::V2,
+            "V2.b" => Version::V2B,
             "V3.0" | "V3  " => Version::V3,
+            "V3.2" => Version::V3_2,
             _ => Version::Unknown,
         }
     }
@@ -60,6 +81,13 @@ mod test
         assert_eq!("V1.0", v.as_str_ref());
     }
 
+    #[test]
+    fn version_as_short_str_ref()
+    {
+        assert_eq!("V1  ", Version::V1.as_short_str_ref());
+        assert_eq!(Version::V1, Version::from(Version::V1.as_short_str_ref()));
+    }
+
     #[test]
     fn version_from_str() {
         assert_eq!(Version::V1, Version::from("V1.0"));
@@ -1,4 +1,5 @@
 use nwn_files;
+use nwn_files::types::{Error as MyError, FileType};
 
 use std::path::Path;
 use std::fs::File;
@@ -63,7 +64,16 @@ fn extract_2da_from_bif() {
 
 
 #[test]
-#[ignore]
 fn parse_bad_bif() {
-    unimplemented!();
+    let path = std::env::temp_dir()
+        .join(format!("nwn_files_bad_{}.bif", std::process::id()));
+    std::fs::write(&path, b"ERF V1.0").unwrap();
+
+    let result = nwn_files::parse_bif(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    match result.unwrap_err() {
+        MyError::BadMagic(FileType::Bif, found) => assert_eq!("ERF ", found),
+        e => panic!("Unexpected error {}", e),
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use nwn_files;
 
-use nwn_files::types::{ResourceType};
+use nwn_files::types::{ResourceType, Error as MyError};
 
 use std::path::Path;
 use std::fs::File;
@@ -70,7 +70,16 @@ fn extract_all_ssf() {
 }
 
 #[test]
-#[ignore]
 fn parse_bad_key() {
-    unimplemented!();
+    let path = std::env::temp_dir()
+        .join(format!("nwn_files_bad_{}.key", std::process::id()));
+    std::fs::write(&path, b"KEY V1  \x01\x00").unwrap();
+
+    let result = nwn_files::parse_key(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    match result.unwrap_err() {
+        MyError::OffsetOutOfBounds(8) => {},
+        e => panic!("Unexpected error {}", e),
+    }
 }
\ No newline at end of file
@@ -6,7 +6,7 @@ use std::fs::File;
 
 use nwn_files::SsfBuilder;
 use nwn_files::SsfFile;
-use nwn_files::types::{SsfEntry, ResRef};
+use nwn_files::types::{SsfEntry, SsfSlot, ResRef};
 
 mod helpers;
 
@@ -44,6 +44,6 @@ fn write_ssf() {
 
     let parsed = SsfFile::parse_from(&mut f).unwrap();
 
-    assert_eq!(30, parsed.entries.len());
+    assert_eq!(SsfSlot::COUNT, parsed.entries.len());
     assert_eq!(res_ref, parsed.entries[0].res_ref);
 }